@@ -0,0 +1,195 @@
+// Gas used by messages once the contract holds a given number of listings and orders, so
+// that storage changes can be compared before and after. Run against a live node with
+// `cargo test --features benchmarks -- --nocapture`. Each measurement is printed as a line
+// of JSON e.g. {"message":"create_order","state_size":100,"ref_time":1,"proof_size":1}
+use crate::escrow::{EscrowRef, InstantiateArgs};
+use crate::pagination::Direction;
+use crate::traits::Escrow as _;
+use crate::AccountId;
+use ink_e2e::build_message;
+
+type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const ETHEREUM_ADDRESS: &str = "0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA";
+// Number of listings, and of the buyer's orders, in place before each measurement
+const STATE_SIZES: [u64; 3] = [1, 10, 100];
+
+fn report(message: &str, state_size: u64, ref_time: u64, proof_size: u64) {
+    println!(
+        "{{\"message\":\"{}\",\"state_size\":{},\"ref_time\":{},\"proof_size\":{}}}",
+        message, state_size, ref_time, proof_size
+    );
+}
+
+// Submits the message and reports the gas it consumed
+macro_rules! measure {
+    ($client:ident, $signer:expr, $state_size:expr, $value:expr, $name:literal, $message:expr) => {{
+        let result = $client
+            .call($signer, $message, $value, None)
+            .await
+            .expect(concat!($name, " failed"));
+        let gas_consumed = result.dry_run.exec_result.gas_consumed;
+        report(
+            $name,
+            $state_size,
+            gas_consumed.ref_time(),
+            gas_consumed.proof_size(),
+        );
+    }};
+}
+
+// Dry runs the query and reports the gas it would consume
+macro_rules! measure_query {
+    ($client:ident, $state_size:expr, $name:literal, $message:expr) => {{
+        let result = $client
+            .call_dry_run(&ink_e2e::alice(), &$message, 0, None)
+            .await;
+        let gas_consumed = result.exec_result.gas_consumed;
+        report(
+            $name,
+            $state_size,
+            gas_consumed.ref_time(),
+            gas_consumed.proof_size(),
+        );
+    }};
+}
+
+#[ink_e2e::test]
+async fn benchmark_messages(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+    let charlie: AccountId = ink_e2e::account_id(ink_e2e::AccountKeyring::Charlie);
+    for state_size in STATE_SIZES {
+        let contract: AccountId = client
+            .instantiate(
+                "escrow",
+                &ink_e2e::bob(),
+                EscrowRef::new(InstantiateArgs::default()),
+                0,
+                None,
+            )
+            .await
+            .expect("instantiate failed")
+            .account_id;
+        let create_vendor = build_message::<EscrowRef>(contract.clone())
+            .call(|escrow| escrow.create_vendor(ETHEREUM_ADDRESS.to_string()));
+        client
+            .call(&ink_e2e::bob(), create_vendor, 0, None)
+            .await
+            .expect("create_vendor failed");
+        for _ in 0..state_size {
+            let create_listing = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.create_listing(None, "USD".to_string(), 1));
+            client
+                .call(&ink_e2e::bob(), create_listing, 0, None)
+                .await
+                .expect("create_listing failed");
+        }
+        let deposit_into_listing = build_message::<EscrowRef>(contract.clone())
+            .call(|escrow| escrow.deposit_into_listing(0));
+        client
+            .call(&ink_e2e::bob(), deposit_into_listing, 1_000_000, None)
+            .await
+            .expect("deposit_into_listing failed");
+        for _ in 0..state_size {
+            let create_order = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.create_order(0, 1));
+            client
+                .call(&ink_e2e::charlie(), create_order, 0, None)
+                .await
+                .expect("create_order failed");
+        }
+
+        // Listings
+        let create_listing = build_message::<EscrowRef>(contract.clone())
+            .call(|escrow| escrow.create_listing(None, "USD".to_string(), 1));
+        measure!(
+            client,
+            &ink_e2e::bob(),
+            state_size,
+            0,
+            "create_listing",
+            create_listing
+        );
+        let deposit_into_listing = build_message::<EscrowRef>(contract.clone())
+            .call(|escrow| escrow.deposit_into_listing(0));
+        measure!(
+            client,
+            &ink_e2e::bob(),
+            state_size,
+            1_000,
+            "deposit_into_listing",
+            deposit_into_listing
+        );
+        let withdraw_from_listing = build_message::<EscrowRef>(contract.clone())
+            .call(|escrow| escrow.withdraw_from_listing(0, 1));
+        measure!(
+            client,
+            &ink_e2e::bob(),
+            state_size,
+            0,
+            "withdraw_from_listing",
+            withdraw_from_listing
+        );
+
+        // Orders, ids carry on from the ones created above
+        let order_id: u64 = state_size;
+        let create_order =
+            build_message::<EscrowRef>(contract.clone()).call(|escrow| escrow.create_order(0, 1));
+        measure!(
+            client,
+            &ink_e2e::charlie(),
+            state_size,
+            0,
+            "create_order",
+            create_order
+        );
+        let update_order_payment_verification = build_message::<EscrowRef>(contract.clone())
+            .call(|escrow| escrow.update_order_payment_verification(order_id, [1; 32], None));
+        measure!(
+            client,
+            &ink_e2e::charlie(),
+            state_size,
+            0,
+            "update_order_payment_verification",
+            update_order_payment_verification
+        );
+        let finalise_order = build_message::<EscrowRef>(contract.clone())
+            .call(|escrow| escrow.finalise_order(order_id));
+        measure!(
+            client,
+            &ink_e2e::bob(),
+            state_size,
+            0,
+            "finalise_order",
+            finalise_order
+        );
+        let create_order =
+            build_message::<EscrowRef>(contract.clone()).call(|escrow| escrow.create_order(0, 1));
+        client
+            .call(&ink_e2e::charlie(), create_order, 0, None)
+            .await
+            .expect("create_order failed");
+        let cancel_order = build_message::<EscrowRef>(contract.clone())
+            .call(|escrow| escrow.cancel_order(order_id + 1));
+        measure!(
+            client,
+            &ink_e2e::charlie(),
+            state_size,
+            0,
+            "cancel_order",
+            cancel_order
+        );
+
+        // Queries
+        let listings = build_message::<EscrowRef>(contract.clone())
+            .call(|escrow| escrow.listings(None, 10, Direction::Ascending));
+        measure_query!(client, state_size, "listings", listings);
+        let orders_for_buyer = build_message::<EscrowRef>(contract.clone())
+            .call(|escrow| escrow.orders_for_buyer(charlie, 0, 10));
+        measure_query!(client, state_size, "orders_for_buyer", orders_for_buyer);
+        let show_order =
+            build_message::<EscrowRef>(contract.clone()).call(|escrow| escrow.show_order(order_id));
+        measure_query!(client, state_size, "show_order", show_order);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use crate::pagination::PaginatedMapping;
+use crate::{AccountId, Timestamp};
+use ink::prelude::vec::Vec;
+use ink::storage::Mapping;
+
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct Dispute {
+    pub(crate) id: u64,
+    pub(crate) order_id: u64,
+    pub(crate) raised_by: AccountId,
+    pub(crate) raised_at: Timestamp,
+    pub(crate) resolved_at: Option<Timestamp>,
+    pub(crate) arbitrator: Option<AccountId>,
+    pub(crate) escalated: bool,
+}
+
+#[derive(Debug, Default)]
+#[ink::storage_item]
+pub struct Disputes {
+    pub(crate) values: PaginatedMapping<u64, Dispute>,
+    pub(crate) id_by_order: Mapping<u64, u64>,
+}
+impl Disputes {
+    // Newest first
+    pub fn index(&self, page: u64, size: u16) -> Vec<Dispute> {
+        self.values.page(page, size)
+    }
+
+    pub fn length(&self) -> u64 {
+        self.values.length()
+    }
+
+    pub fn create(&mut self, value: &Dispute) {
+        if self.values.push(value) {
+            self.id_by_order.insert(value.order_id, &value.id);
+        }
+    }
+
+    pub fn update(&mut self, value: &Dispute) {
+        self.values.update(value.id, value);
+    }
+
+    pub fn resolve(&mut self, order_id: u64, resolved_at: Timestamp) {
+        if let Some(id) = self.id_by_order.get(order_id) {
+            let mut dispute: Dispute = self.values.get(id).unwrap();
+            dispute.resolved_at = Some(resolved_at);
+            self.values.update(id, &dispute);
+        }
+    }
+}
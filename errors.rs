@@ -0,0 +1,231 @@
+use crate::Balance;
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum EscrowError {
+    AlreadyApproved,
+    AlreadyRated,
+    // The listing's available amount
+    AmountUnavailable(Balance),
+    AssetMismatch,
+    BatchTooLarge,
+    Blacklisted,
+    CodeHashInvalid,
+    CuratedMarketplace,
+    DisputeAlreadyEscalated,
+    EscrowNotEmpty,
+    EthereumAddressTooLong,
+    InsufficientApprovals,
+    // The hold required for the order
+    IncorrectHold(Balance),
+    // The listing's available amount
+    InsufficientFunds(Balance),
+    InvalidCurrency,
+    InvalidFee,
+    InvalidLimits,
+    InvalidNonce,
+    InvalidRating,
+    InvalidSignature,
+    InvalidStatus,
+    InvalidSigners,
+    InvalidTimeouts,
+    ListingCanOnlyBeCreatedByAVendor,
+    ListingClosed,
+    ListingExpired,
+    ListingLimitReached,
+    ListingNotExpired,
+    ListingNotFound,
+    ListingOrderLimitReached,
+    ListingPaused,
+    MaintenanceMode,
+    OpenOrderLimitReached,
+    // The most the buyer can order
+    OrderAmountAboveLimit(Balance),
+    // The least that can be ordered
+    OrderAmountBelowLimit(Balance),
+    OrderRateLimitReached,
+    StatusCanNotBeChanged,
+    StorageUpToDate,
+    SubscriberLimitReached,
+    OrderCancelled,
+    OrderFinalised,
+    OrderLimitReached,
+    OrderNotFinalised,
+    OrderNotFound,
+    OrderNotExpired,
+    Overflow,
+    ReentrantCall,
+    Paused,
+    QueuedActionNotFound,
+    SignatureExpired,
+    TimelockNotExpired,
+    TokenTransferFailed,
+    VendorAlreadyExists,
+    VendorInactive,
+    VendorNotFound,
+    Underflow,
+    Unauthorised,
+    YieldPoolCallFailed,
+    YieldPoolInUse,
+    YieldPoolNotSet,
+    InvalidImport,
+    StateImportLocked,
+    VendorListingLimitReached,
+    StorageVersionUnsupported,
+    StorageDecodeFailed,
+    ExportStarted,
+    ExportNotStarted,
+    FundsExported,
+}
+
+// Codes are stable across releases so that SDKs and frontends can branch on and localise
+// errors. New errors take the next unused code and codes are never reused.
+impl EscrowError {
+    pub fn code(&self) -> u16 {
+        match self {
+            EscrowError::AlreadyApproved => 1,
+            EscrowError::AlreadyRated => 2,
+            EscrowError::AmountUnavailable(_) => 3,
+            EscrowError::AssetMismatch => 4,
+            EscrowError::BatchTooLarge => 5,
+            EscrowError::Blacklisted => 6,
+            EscrowError::CodeHashInvalid => 7,
+            EscrowError::CuratedMarketplace => 8,
+            EscrowError::DisputeAlreadyEscalated => 9,
+            EscrowError::EscrowNotEmpty => 10,
+            EscrowError::EthereumAddressTooLong => 11,
+            EscrowError::InsufficientApprovals => 12,
+            EscrowError::IncorrectHold(_) => 13,
+            EscrowError::InsufficientFunds(_) => 14,
+            EscrowError::InvalidCurrency => 15,
+            EscrowError::InvalidFee => 16,
+            EscrowError::InvalidLimits => 17,
+            EscrowError::InvalidNonce => 18,
+            EscrowError::InvalidRating => 19,
+            EscrowError::InvalidSignature => 20,
+            EscrowError::InvalidStatus => 21,
+            EscrowError::InvalidSigners => 22,
+            EscrowError::InvalidTimeouts => 23,
+            EscrowError::ListingCanOnlyBeCreatedByAVendor => 24,
+            EscrowError::ListingClosed => 25,
+            EscrowError::ListingExpired => 26,
+            EscrowError::ListingLimitReached => 27,
+            EscrowError::ListingNotExpired => 28,
+            EscrowError::ListingNotFound => 29,
+            EscrowError::ListingOrderLimitReached => 30,
+            EscrowError::ListingPaused => 31,
+            EscrowError::MaintenanceMode => 32,
+            EscrowError::OpenOrderLimitReached => 33,
+            EscrowError::OrderAmountAboveLimit(_) => 34,
+            EscrowError::OrderAmountBelowLimit(_) => 35,
+            EscrowError::OrderRateLimitReached => 36,
+            EscrowError::StatusCanNotBeChanged => 37,
+            EscrowError::StorageUpToDate => 38,
+            EscrowError::SubscriberLimitReached => 39,
+            EscrowError::OrderCancelled => 40,
+            EscrowError::OrderFinalised => 41,
+            EscrowError::OrderLimitReached => 42,
+            EscrowError::OrderNotFinalised => 43,
+            EscrowError::OrderNotFound => 44,
+            EscrowError::OrderNotExpired => 45,
+            EscrowError::Overflow => 46,
+            EscrowError::ReentrantCall => 47,
+            EscrowError::Paused => 48,
+            EscrowError::QueuedActionNotFound => 49,
+            EscrowError::SignatureExpired => 50,
+            EscrowError::TimelockNotExpired => 51,
+            EscrowError::TokenTransferFailed => 52,
+            EscrowError::VendorAlreadyExists => 53,
+            EscrowError::VendorInactive => 54,
+            EscrowError::VendorNotFound => 55,
+            EscrowError::Underflow => 56,
+            EscrowError::Unauthorised => 57,
+            EscrowError::YieldPoolCallFailed => 58,
+            EscrowError::YieldPoolInUse => 59,
+            EscrowError::YieldPoolNotSet => 60,
+            EscrowError::InvalidImport => 61,
+            EscrowError::StateImportLocked => 62,
+            EscrowError::VendorListingLimitReached => 63,
+            EscrowError::StorageVersionUnsupported => 64,
+            EscrowError::StorageDecodeFailed => 65,
+            EscrowError::ExportStarted => 66,
+            EscrowError::ExportNotStarted => 67,
+            EscrowError::FundsExported => 68,
+        }
+    }
+}
+
+// The name of the error with the code, as it appears in the metadata
+pub fn decode_error(code: u16) -> Option<&'static str> {
+    Some(match code {
+        1 => "AlreadyApproved",
+        2 => "AlreadyRated",
+        3 => "AmountUnavailable",
+        4 => "AssetMismatch",
+        5 => "BatchTooLarge",
+        6 => "Blacklisted",
+        7 => "CodeHashInvalid",
+        8 => "CuratedMarketplace",
+        9 => "DisputeAlreadyEscalated",
+        10 => "EscrowNotEmpty",
+        11 => "EthereumAddressTooLong",
+        12 => "InsufficientApprovals",
+        13 => "IncorrectHold",
+        14 => "InsufficientFunds",
+        15 => "InvalidCurrency",
+        16 => "InvalidFee",
+        17 => "InvalidLimits",
+        18 => "InvalidNonce",
+        19 => "InvalidRating",
+        20 => "InvalidSignature",
+        21 => "InvalidStatus",
+        22 => "InvalidSigners",
+        23 => "InvalidTimeouts",
+        24 => "ListingCanOnlyBeCreatedByAVendor",
+        25 => "ListingClosed",
+        26 => "ListingExpired",
+        27 => "ListingLimitReached",
+        28 => "ListingNotExpired",
+        29 => "ListingNotFound",
+        30 => "ListingOrderLimitReached",
+        31 => "ListingPaused",
+        32 => "MaintenanceMode",
+        33 => "OpenOrderLimitReached",
+        34 => "OrderAmountAboveLimit",
+        35 => "OrderAmountBelowLimit",
+        36 => "OrderRateLimitReached",
+        37 => "StatusCanNotBeChanged",
+        38 => "StorageUpToDate",
+        39 => "SubscriberLimitReached",
+        40 => "OrderCancelled",
+        41 => "OrderFinalised",
+        42 => "OrderLimitReached",
+        43 => "OrderNotFinalised",
+        44 => "OrderNotFound",
+        45 => "OrderNotExpired",
+        46 => "Overflow",
+        47 => "ReentrantCall",
+        48 => "Paused",
+        49 => "QueuedActionNotFound",
+        50 => "SignatureExpired",
+        51 => "TimelockNotExpired",
+        52 => "TokenTransferFailed",
+        53 => "VendorAlreadyExists",
+        54 => "VendorInactive",
+        55 => "VendorNotFound",
+        56 => "Underflow",
+        57 => "Unauthorised",
+        58 => "YieldPoolCallFailed",
+        59 => "YieldPoolInUse",
+        60 => "YieldPoolNotSet",
+        61 => "InvalidImport",
+        62 => "StateImportLocked",
+        63 => "VendorListingLimitReached",
+        64 => "StorageVersionUnsupported",
+        65 => "StorageDecodeFailed",
+        66 => "ExportStarted",
+        67 => "ExportNotStarted",
+        68 => "FundsExported",
+        _ => return None,
+    })
+}
@@ -1,30 +1,329 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(all(test, feature = "benchmarks"))]
+mod benchmarks;
+mod disputes;
+mod errors;
+mod listings;
+mod orders;
+mod pagination;
+pub mod traits;
+mod vendors;
+
+// The contract's environment types, for the modules outside of it
+type AccountId = ink::primitives::AccountId;
+type Balance = <ink::env::DefaultEnvironment as ink::env::Environment>::Balance;
+type Key = ink::primitives::Key;
+type Timestamp = <ink::env::DefaultEnvironment as ink::env::Environment>::Timestamp;
+
 #[ink::contract]
 mod escrow {
+    use crate::disputes::{Dispute, Disputes};
+    use crate::errors::{self, EscrowError};
+    use crate::listings::{Listing, ListingExposure, ListingSummary, Listings};
+    use crate::orders::{
+        Order, OrderAction, OrderActionIntent, OrderDetail, OrderIntent, OrderState, OrderStatus,
+        OrderSummary, Orders, StatusTransition, UnpackedOrder,
+    };
+    use crate::pagination::{page_bounds, Direction, Page, ScanPage};
+    use crate::traits::{
+        self, ON_ORDER_EVENT_SELECTOR, ON_ORDER_SETTLED_SELECTOR, YIELD_POOL_BOND_SELECTOR,
+        YIELD_POOL_UNBOND_SELECTOR,
+    };
+    use crate::vendors::{self, Vendor, VendorProfile};
+    use ink::env::{
+        call::{build_call, ExecutionInput, Selector},
+        hash::Blake2x256,
+        DefaultEnvironment,
+    };
     use ink::prelude::{vec, vec::Vec};
-    use ink::storage::Mapping;
+    use ink::storage::{Lazy, Mapping};
     use ink_prelude::string::String;
-    use openbrush::{contracts::ownable::*, traits::Storage};
+    use openbrush::{
+        contracts::{
+            ownable::*,
+            traits::{
+                psp22::PSP22Ref,
+                psp34::{extensions::mintable::PSP34MintableRef, Id},
+            },
+        },
+        traits::Storage,
+    };
+
+    // === CONSTANTS ===
+    // Operations that can be paused individually, combined as a bitmask
+    pub const PAUSE_CREATE_LISTING: u8 = 1 << 0;
+    pub const PAUSE_CREATE_ORDER: u8 = 1 << 1;
+    pub const PAUSE_CREATE_VENDOR: u8 = 1 << 2;
+    pub const PAUSE_DEPOSIT: u8 = 1 << 3;
+    pub const PAUSE_WITHDRAW: u8 = 1 << 4;
+    // Bump when the storage layout changes and add the matching step to migrate_record
+    // 1 => versioning added. Listings, orders and vendors later gained fields without a bump, so
+    //      their layouts under this version are told apart by size, see Orders::get_v1.
+    // 2 => payment verifications stored as hashes
+    // 3 => buyer and vendor order ids kept in chunks
+    // 4 => order status packed into OrderState
+    // 5 => order ids by status kept in chunks
+    // 6 => listing ids by asset and currency kept in chunks
+    // 7 => ids of orders awaiting payment verification by vendor kept in chunks
+    pub const STORAGE_VERSION: u16 = 7;
+    // Bump when event fields change so indexers can branch decoding after an upgrade
+    pub const EVENT_VERSION: u16 = 1;
+    // 48 hours
+    pub const DEFAULT_TIMELOCK_DELAY: Timestamp = 172_800_000;
+    // Fees are expressed in basis points of the order amount
+    pub const MAX_FEE_BPS: u16 = 1_000;
+    // 1 hour
+    pub const DEFAULT_PAYMENT_WINDOW: Timestamp = 3_600_000;
+    // 24 hours
+    pub const DEFAULT_VERIFICATION_WINDOW: Timestamp = 86_400_000;
+    // 72 hours
+    pub const DEFAULT_DISPUTE_WINDOW: Timestamp = 259_200_000;
+    // 90 days that finalised and cancelled orders are kept before they can be archived
+    pub const ORDER_RETENTION_PERIOD: Timestamp = 7_776_000_000;
+    // Number of privileged actions kept in the audit log before the oldest are overwritten
+    pub const AUDIT_LOG_CAPACITY: u64 = 1_000;
+    // Number of order status transitions kept before the oldest are overwritten
+    pub const STATUS_TRANSITIONS_CAPACITY: u64 = 500;
+    // Ids per chunk of the buyer and vendor order indexes
+    pub const ID_CHUNK_SIZE: u64 = 64;
+    // Most ids a batch getter accepts in one call
+    pub const MAX_BATCH_SIZE: usize = 100;
+    // 30 day buckets that reputation activity is grouped into
+    pub const REPUTATION_EPOCH: Timestamp = 2_592_000_000;
+    // Number of buckets, counting back from the current one, that reputation averages cover
+    pub const REPUTATION_EPOCHS: u64 = 12;
+    // Weight of each bucket relative to the one after it e.g. 7_500 => 75%
+    pub const REPUTATION_DECAY_BPS: u128 = 7_500;
+    // 1 hour after rating an order that the rating can still be amended
+    pub const RATING_AMEND_WINDOW: Timestamp = 3_600_000;
+    // Most contracts that can subscribe to order events
+    pub const MAX_SUBSCRIBERS: usize = 5;
+    // Gas, in ref time, each subscriber gets to handle an order event. Messages that move a
+    // single order on hand out at most MAX_SUBSCRIBERS times this per transition, while batch
+    // messages only emit events.
+    pub const SUBSCRIBER_GAS_LIMIT: u64 = 1_000_000_000;
+    // Gas, in ref time, a trade manager gets to handle its order being settled
+    pub const TRADE_MANAGER_GAS_LIMIT: u64 = 5_000_000_000;
+    // Vendors kept on the leaderboard of the current reputation epoch's volume
+    pub const TOP_VENDORS_CAPACITY: usize = 20;
+    // Finalised orders, as buyer and vendor combined, at which an account is minted a badge
+    pub const BADGE_MILESTONES: [u64; 3] = [10, 100, 1_000];
+    // Ceiling for max_page_size, which caps the items any index query returns at once
+    pub const MAX_PAGE_SIZE: u16 = 100;
+    // Most ids a filtered index looks at in one call, as it can't tell beforehand how many match
+    pub const MAX_SCAN_LENGTH: u64 = 1_000;
+    // Longest user-supplied string that gets stored, in bytes, so it can't bloat storage
+    pub const MAX_ETHEREUM_ADDRESS_LENGTH: usize = 42;
 
     // === ENUMS ===
-    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    // Changes that must wait out the timelock delay before being executed
+    // and, once a threshold is set, be approved by that many signers
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum AdminAction {
+        CodeHash([u8; 32]),
+        FeeBps(u16),
+        // Signers and the number of their approvals needed to execute an action
+        Signers(Vec<AccountId>, u8),
+        // Freezes the instance for its state and funds to move to the given account,
+        // a new instance deployed to import them
+        StartExport(AccountId),
+        TimelockDelay(Timestamp),
+        Treasury(AccountId),
+    }
+
+    // Privileged actions recorded in the audit log
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum AuditAction {
+        AcceptOwnership,
+        Activate,
+        AddPaymentProcessor([u8; 33]),
+        AddSubscriber(AccountId),
+        AddToBlacklist(AccountId),
+        ApproveAction,
+        AssignArbitrator(AccountId),
+        CancelAction,
+        CancelOrder,
+        ClaimFees(Option<AccountId>),
+        ExecuteAction,
+        ExportFunds(Option<AccountId>),
+        FinaliseOrder,
+        ForceCancelOrder(CancelReason),
+        GrantRole(Role, AccountId),
+        ImportState,
+        Migrate,
+        OnboardVendor(AccountId),
+        Pause(u8),
+        QueueAction,
+        RemoveFromBlacklist(AccountId),
+        RemovePaymentProcessor([u8; 33]),
+        RemoveSubscriber(AccountId),
+        RenounceOwnership,
+        RevokeRole(Role, AccountId),
+        SetBadgeCollection(Option<AccountId>),
+        SetCurated(bool),
+        SetDisputeWeights,
+        SetGovernor,
+        SetLimits,
+        SetMaintenanceMode(bool),
+        SetMaxPageSize(u16),
+        SetOrderSizeSchedule,
+        SetTimeouts,
+        SetUpkeepBounty(Balance),
+        SetYieldPool(Option<AccountId>),
+        TransferOwnership(AccountId),
+        Unpause(u8),
+        VerifyVendor(AccountId),
+    }
+
+    // Listing management a vendor can combine in one multicall
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub enum EscrowError {
-        AmountUnavailable,
-        InsufficientFunds,
-        ListingCanOnlyBeCreatedByAVendor,
-        ListingLimitReached,
-        ListingNotFound,
-        StatusCanNotBeChanged,
-        OrderCancelled,
-        OrderFinalised,
-        OrderNotFound,
-        VendorAlreadyExists,
-        Unauthorised,
+    pub enum Call {
+        Close(u32),
+        // Listing id and whether it should be paused
+        Pause(u32, bool),
+        // Listing id, price per unit and expiry
+        Update(u32, Balance, Option<Timestamp>),
+        // Listing id and amount
+        Withdraw(u32, Balance),
+    }
+
+    // Why a moderator had to step in and cancel an order
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum CancelReason {
+        BuyerUnresponsive,
+        Duplicate,
+        Fraud,
+        Other,
+        VendorUnresponsive,
+    }
+
+    // The owner implicitly holds every role.
+    // Admins can grant and revoke every role except Admin, which only the owner manages.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Role {
+        Admin,
+        Arbitrator,
+        Moderator,
+        Pauser,
+        // Can submit buyers' signed intents, paying the fees for them
+        Relayer,
     }
 
     // === EVENTS ===
+    // Listings take orders straight away on the vendor's behalf, so this comes along with
+    // CreateOrder, for vendors following what their listings take on
+    #[ink(event)]
+    pub struct AcceptOrder {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        vendor: AccountId,
+        #[ink(topic)]
+        listing_id: u32,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct AcceptOwnership {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Activate {
+        listings_length: u32,
+        orders_length: u64,
+    }
+
+    #[ink(event)]
+    pub struct AddPaymentProcessor {
+        public_key: [u8; 33],
+    }
+
+    #[ink(event)]
+    pub struct AddSubscriber {
+        subscriber: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct AddToBlacklist {
+        #[ink(topic)]
+        account: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ApproveAction {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        signer: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ApproveCancelAction {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        signer: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ArbitratorAssigned {
+        #[ink(topic)]
+        order_id: u64,
+        #[ink(topic)]
+        arbitrator: AccountId,
+        // When expire_order can cancel the order if the arbitrator hasn't resolved it
+        deadline: Timestamp,
+    }
+
+    // Final state of an order that has been removed from storage
+    #[ink(event)]
+    pub struct ArchiveOrder {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        caller: AccountId,
+        order: Order,
+    }
+
+    #[ink(event)]
+    pub struct CancelAction {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    #[ink(event)]
+    pub struct CancelOrder {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        buyer: AccountId,
+        #[ink(topic)]
+        vendor: AccountId,
+        caller: AccountId,
+    }
+
     #[ink(event)]
     pub struct CreateListing {
         #[ink(topic)]
@@ -32,12 +331,28 @@ mod escrow {
         vendor: AccountId,
     }
 
+    #[ink(event)]
+    pub struct CreateManagedOrder {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        buyer: AccountId,
+        #[ink(topic)]
+        manager: AccountId,
+    }
+
     #[ink(event)]
     pub struct CreateOrder {
         #[ink(topic)]
         id: u64,
+        #[ink(topic)]
         buyer: AccountId,
+        #[ink(topic)]
         vendor: AccountId,
+        listing_id: u32,
+        amount: Balance,
+        price_per_unit: Balance,
+        currency: String,
     }
 
     #[ink(event)]
@@ -47,673 +362,8773 @@ mod escrow {
     }
 
     #[ink(event)]
-    pub struct UpdateOrder {
+    pub struct DepositIntoListing {
         #[ink(topic)]
-        id: u64,
-        status: u8,
+        id: u32,
+        #[ink(topic)]
+        vendor: AccountId,
+        amount: Balance,
+        new_balance: Balance,
     }
 
-    // === STRUCTS ===
-    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub struct Config {
-        admin: AccountId,
+    #[ink(event)]
+    pub struct DisputeOrder {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        buyer: AccountId,
+        #[ink(topic)]
+        vendor: AccountId,
+        caller: AccountId,
     }
 
-    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub struct ListingsForFrontEnd {
-        listings: Vec<Listing>,
-        length: u32,
+    #[ink(event)]
+    pub struct DisputeEscalated {
+        #[ink(topic)]
+        order_id: u64,
+        #[ink(topic)]
+        caller: AccountId,
+        // Replaced by an admin level review
+        arbitrator: Option<AccountId>,
+        // When expire_order can cancel the order if it still hasn't been resolved
+        deadline: Timestamp,
     }
 
-    #[derive(scale::Decode, scale::Encode)]
-    #[cfg_attr(
-        feature = "std",
-        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
-    )]
-    #[derive(Debug, Clone)]
-    pub struct Listing {
+    #[ink(event)]
+    pub struct ExecuteAction {
+        #[ink(topic)]
         id: u32,
-        vendor: AccountId,
-        available_amount: Balance,
-        price_per_tzero: Balance,
+        action: AdminAction,
     }
 
-    #[derive(Debug, Default)]
-    #[ink::storage_item]
-    pub struct Listings {
-        values: Mapping<u32, Listing>,
-        length: u32,
+    #[ink(event)]
+    pub struct ExpireOrder {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        buyer: AccountId,
+        #[ink(topic)]
+        vendor: AccountId,
+        caller: AccountId,
+        // Disputed when the vendor hasn't reviewed a payment in time or a dispute has been
+        // escalated, otherwise cancelled
+        status: OrderStatus,
     }
-    impl Listings {
-        pub fn index(&self, page: u32, size: u16) -> Vec<Listing> {
-            let mut listings: Vec<Listing> = vec![];
-            // When there's no listings
-            if self.length == 0 {
-                return listings;
-            }
 
-            let listings_to_skip: Option<u32> = if page == 0 {
-                Some(0)
-            } else {
-                page.checked_mul(size.into())
-            };
-            let starting_index: u32;
-            let ending_index: u32;
-            // When the listings to skip is greater than max possible
-            if let Some(listings_to_skip_unwrapped) = listings_to_skip {
-                let ending_index_wrapped: Option<u32> =
-                    self.length.checked_sub(listings_to_skip_unwrapped);
-                // When listings to skip is greater than total number of listings
-                if ending_index_wrapped.is_none() {
-                    return listings;
-                }
-                ending_index = ending_index_wrapped.unwrap();
-                starting_index = ending_index.saturating_sub(size.into());
-            } else {
-                return listings;
-            }
-            for i in (starting_index..=ending_index).rev() {
-                listings.push(self.values.get(i).unwrap())
-            }
-            listings
-        }
+    #[ink(event)]
+    pub struct ExportFunds {
+        #[ink(topic)]
+        destination: AccountId,
+        asset: Option<AccountId>,
+        amount: Balance,
+    }
 
-        pub fn create(&mut self, value: &Listing) {
-            if self.values.insert(self.length, value).is_none() {
-                self.length += 1
-            }
-        }
+    #[ink(event)]
+    pub struct FeeCollected {
+        #[ink(topic)]
+        order_id: u64,
+        #[ink(topic)]
+        asset: Option<AccountId>,
+        // Order amount the fee was taken from
+        amount: Balance,
+        fee_bps: u16,
+        fee: Balance,
+        // Released to the buyer
+        payout: Balance,
+    }
 
-        pub fn update(&mut self, value: &Listing) {
-            self.values.insert(value.id, value);
-        }
+    #[ink(event)]
+    pub struct HoldSettled {
+        #[ink(topic)]
+        order_id: u64,
+        #[ink(topic)]
+        recipient: AccountId,
+        amount: Balance,
+        // Paid to the vendor rather than returned to the buyer
+        forfeited: bool,
     }
 
-    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub struct OrdersForFrontEnd {
-        orders: Vec<Order>,
-        length: u64,
+    #[ink(event)]
+    pub struct FinaliseOrder {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        buyer: AccountId,
+        #[ink(topic)]
+        vendor: AccountId,
+        caller: AccountId,
     }
 
-    // Order statuses
-    // 0 => Open
-    // 1 => PendingVerification
-    // 2 => Finalised
-    // 3 => Cancelled
-    // 4 => Disputed
-    #[derive(scale::Decode, scale::Encode)]
-    #[cfg_attr(
-        feature = "std",
-        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
-    )]
-    #[derive(Debug, Clone)]
-    pub struct Order {
+    #[ink(event)]
+    pub struct ForceCancelOrder {
+        #[ink(topic)]
         id: u64,
+        #[ink(topic)]
         buyer: AccountId,
+        #[ink(topic)]
         vendor: AccountId,
-        amount: Balance,
-        payment_verification: Option<String>,
-        status: u8,
-        listing_id: u32,
-        total_price: Balance,
+        caller: AccountId,
+        reason: CancelReason,
     }
 
-    #[derive(Debug, Default)]
-    #[ink::storage_item]
-    pub struct Orders {
-        values: Mapping<u64, Order>,
-        length: u64,
+    #[ink(event)]
+    pub struct GrantRole {
+        #[ink(topic)]
+        account: AccountId,
+        role: Role,
     }
-    impl Orders {
-        pub fn index(&self, page: u64, size: u16) -> Vec<Order> {
-            let mut orders: Vec<Order> = vec![];
-            // When there's no orders
-            if self.length == 0 {
-                return orders;
-            }
 
-            let orders_to_skip: Option<u64> = if page == 0 {
-                Some(0)
-            } else {
-                page.checked_mul(size.into())
-            };
+    #[ink(event)]
+    pub struct ImportState {
+        listings_length: u32,
+        orders_length: u32,
+        vendors_length: u32,
+    }
 
-            let starting_index: u64;
-            let ending_index: u64;
-            // When the orders to skip is greater than max possible
-            if let Some(orders_to_skip_unwrapped) = orders_to_skip {
-                let ending_index_wrapped: Option<u64> =
-                    self.length.checked_sub(orders_to_skip_unwrapped);
-                // When orders to skip is greater than total number of orders
-                if ending_index_wrapped.is_none() {
-                    return orders;
-                }
-                ending_index = ending_index_wrapped.unwrap();
-                starting_index = ending_index.saturating_sub(size.into());
-            } else {
-                return orders;
-            }
-            for i in (starting_index..=ending_index).rev() {
-                orders.push(self.values.get(i).unwrap())
-            }
-            orders
-        }
+    // Drained listings can be refilled with a deposit, closed ones can't
+    #[ink(event)]
+    pub struct ListingClosed {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        vendor: AccountId,
+        drained: bool,
+        // Available amount returned to the vendor
+        refunded: Balance,
+    }
 
-        pub fn create(&mut self, value: &Order) {
-            if self.values.insert(self.length, value).is_none() {
-                self.length += 1
-            }
-        }
+    #[ink(event)]
+    pub struct ListingExpired {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        vendor: AccountId,
+        expires_at: Timestamp,
+        // Available amount returned to the vendor
+        refunded: Balance,
+    }
 
-        pub fn update(&mut self, value: &Order) {
-            self.values.insert(value.id, value);
-        }
+    #[ink(event)]
+    pub struct Migrate {
+        from_version: u16,
+        to_version: u16,
+        event_version: u16,
     }
 
-    #[derive(scale::Decode, scale::Encode)]
-    #[cfg_attr(
-        feature = "std",
-        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
-    )]
-    #[derive(Debug, Clone)]
-    pub struct Vendor {
-        ethereum_address: String,
+    #[ink(event)]
+    pub struct MigrateVendors {
+        vendors_length: u32,
     }
 
-    // === CONTRACT ===
-    #[ink(storage)]
-    #[derive(Default, Storage)]
-    pub struct Escrow {
-        #[storage_field]
-        ownable: ownable::Data,
-        listings: Listings,
-        orders: Orders,
-        vendors: Mapping<AccountId, Vendor>,
+    // Consolidated status changes from a batch operation, as (order id, new status)
+    #[ink(event)]
+    pub struct OrderChanged {
+        #[ink(topic)]
+        caller: AccountId,
+        orders: Vec<(u64, u8)>,
     }
-    impl Escrow {
-        #[ink(constructor)]
-        pub fn new() -> Self {
-            let mut instance = Self::default();
-            instance._init_with_owner(Self::env().caller());
-            instance.listings = Listings {
-                values: Mapping::default(),
-                length: 0,
-            };
-            instance.orders = Orders {
-                values: Mapping::default(),
-                length: 0,
-            };
-            instance.vendors = Mapping::default();
-            instance
-        }
 
-        // === QUERIES ===
-        #[ink(message)]
-        pub fn config(&self) -> Config {
-            Config {
-                admin: self.ownable.owner(),
-            }
-        }
+    #[ink(event)]
+    pub struct MintBadge {
+        #[ink(topic)]
+        account: AccountId,
+        milestone: u64,
+    }
 
-        #[ink(message)]
-        pub fn listings(&mut self, page: u32, size: u16) -> ListingsForFrontEnd {
-            ListingsForFrontEnd {
-                listings: self.listings.index(page, size),
-                length: self.listings.length,
-            }
-        }
+    #[ink(event)]
+    pub struct Pause {
+        #[ink(topic)]
+        caller: AccountId,
+        operations: u8,
+    }
 
-        #[ink(message)]
-        pub fn orders(&mut self, page: u64, size: u16) -> OrdersForFrontEnd {
-            OrdersForFrontEnd {
-                orders: self.orders.index(page, size),
-                length: self.orders.length,
-            }
-        }
+    #[ink(event)]
+    pub struct PerformUpkeep {
+        #[ink(topic)]
+        caller: AccountId,
+        duties_length: u32,
+        bounty: Balance,
+    }
 
-        // === TXS ===
-        #[ink(message)]
-        pub fn cancel_order(&mut self, order_id: u64) -> Result<(), EscrowError> {
-            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
-            if let Some(mut order) = order_wrapped {
-                let caller: AccountId = Self::env().caller();
-                if order.buyer != caller || caller != self.ownable.owner() {
-                    return Err(EscrowError::Unauthorised);
-                } else if order.status == 2 || order.status == 3 {
-                    return Err(EscrowError::StatusCanNotBeChanged);
-                }
+    #[ink(event)]
+    pub struct PauseListing {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        vendor: AccountId,
+        paused: bool,
+    }
 
-                order.status = 3;
-                self.orders.update(&order);
+    #[ink(event)]
+    pub struct QueueAction {
+        #[ink(topic)]
+        id: u32,
+        action: AdminAction,
+        executable_at: Timestamp,
+    }
 
-                // Increase associated listing's availabe_amount
-                let mut listing: Listing = self.listings.values.get(order.listing_id).unwrap();
-                listing.available_amount += order.amount;
-                self.listings.update(&listing);
+    #[ink(event)]
+    pub struct RateOrder {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        caller: AccountId,
+        // The other party to the order, whose aggregate the rating counts towards
+        #[ink(topic)]
+        rated: AccountId,
+        rating: u8,
+        review: Option<[u8; 32]>,
+    }
 
-                // Emit event
-                self.env().emit_event(UpdateOrder {
-                    id: order.id,
-                    status: order.status,
-                });
-            } else {
-                return Err(EscrowError::OrderNotFound);
-            }
+    #[ink(event)]
+    pub struct RemoveFromBlacklist {
+        #[ink(topic)]
+        account: AccountId,
+    }
 
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct RemovePaymentProcessor {
+        public_key: [u8; 33],
+    }
 
-        #[ink(message)]
-        pub fn create_listing(&mut self, price_per_tzero: Balance) -> Result<(), EscrowError> {
-            if self.listings.length == u32::MAX {
-                return Err(EscrowError::ListingLimitReached);
-            }
-            let caller: AccountId = Self::env().caller();
-            if self.vendors.get(caller).is_none() {
-                return Err(EscrowError::ListingCanOnlyBeCreatedByAVendor);
-            }
+    #[ink(event)]
+    pub struct RemoveSubscriber {
+        subscriber: AccountId,
+    }
 
-            let listing: Listing = Listing {
-                id: self.listings.length,
-                vendor: caller,
-                available_amount: 0,
-                price_per_tzero,
-            };
-            self.listings.create(&listing);
+    #[ink(event)]
+    pub struct RenounceOwnership {
+        #[ink(topic)]
+        previous_owner: AccountId,
+    }
 
-            // Emit event
-            self.env().emit_event(CreateListing {
-                id: listing.id,
-                vendor: listing.vendor,
-            });
+    #[ink(event)]
+    pub struct RevokeRole {
+        #[ink(topic)]
+        account: AccountId,
+        role: Role,
+    }
 
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct SetBadgeCollection {
+        badge_collection: Option<AccountId>,
+    }
 
-        #[ink(message)]
-        pub fn create_order(
-            &mut self,
-            listing_id: u32,
-            amount: Balance,
-        ) -> Result<(), EscrowError> {
-            let listing_wrapped: Option<Listing> = self.listings.values.get(listing_id);
-            if let Some(mut listing) = listing_wrapped {
-                let caller: AccountId = Self::env().caller();
-                if listing.vendor == caller {
-                    return Err(EscrowError::Unauthorised);
-                }
-                if amount > listing.available_amount {
-                    return Err(EscrowError::AmountUnavailable);
-                }
+    #[ink(event)]
+    pub struct SetCurated {
+        curated: bool,
+    }
 
-                listing.available_amount -= amount;
-                self.listings.update(&listing);
+    #[ink(event)]
+    pub struct SetDisputeWeights {
+        dispute_weights: DisputeWeights,
+    }
 
-                let order: Order = Order {
-                    id: self.orders.length,
-                    buyer: caller,
-                    vendor: listing.vendor,
-                    amount,
-                    payment_verification: None,
-                    status: 0,
-                    listing_id: listing.id,
-                    total_price: listing.price_per_tzero * amount,
-                };
-                self.orders.create(&order);
+    #[ink(event)]
+    pub struct SetGovernor {
+        governor: Option<AccountId>,
+    }
 
-                // Emit event
-                self.env().emit_event(CreateOrder {
-                    id: order.id,
-                    buyer: order.buyer,
-                    vendor: order.vendor,
-                });
-            } else {
-                return Err(EscrowError::ListingNotFound);
-            }
+    #[ink(event)]
+    pub struct SetLimits {
+        limits: Limits,
+    }
 
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct SetListingYield {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        vendor: AccountId,
+        enabled: bool,
+    }
 
-        #[ink(message)]
-        pub fn create_vendor(&mut self, ethereum_address: String) -> Result<(), EscrowError> {
-            let caller: AccountId = Self::env().caller();
-            if self.vendors.get(caller).is_some() {
-                return Err(EscrowError::VendorAlreadyExists);
-            }
+    #[ink(event)]
+    pub struct SetMaintenanceMode {
+        #[ink(topic)]
+        caller: AccountId,
+        enabled: bool,
+    }
 
-            // Create vendor for caller
-            let vendor: Vendor = Vendor { ethereum_address };
-            self.vendors.insert(caller, &vendor);
+    #[ink(event)]
+    pub struct SetMaxPageSize {
+        max_page_size: u16,
+    }
 
-            // Emit event
-            self.env().emit_event(CreateVendor { caller });
+    #[ink(event)]
+    pub struct SetOrderSizeSchedule {
+        order_size_schedule: Vec<OrderSizeTier>,
+    }
 
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct SetTimeouts {
+        timeouts: Timeouts,
+    }
 
-        #[ink(message, payable)]
-        pub fn deposit_into_listing(&mut self, id: u32) -> Result<(), EscrowError> {
-            let listing_wrapped: Option<Listing> = self.listings.values.get(id);
-            if let Some(mut listing) = listing_wrapped {
-                if listing.vendor != Self::env().caller() {
-                    return Err(EscrowError::Unauthorised);
-                }
+    #[ink(event)]
+    pub struct SetTradeManager {
+        #[ink(topic)]
+        buyer: AccountId,
+        #[ink(topic)]
+        manager: AccountId,
+        approved: bool,
+    }
 
-                listing.available_amount += self.env().transferred_value();
-                self.listings.update(&listing);
-            } else {
-                return Err(EscrowError::ListingNotFound);
-            }
+    #[ink(event)]
+    pub struct SetUpkeepBounty {
+        upkeep_bounty: Balance,
+    }
 
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct SetYieldPool {
+        yield_pool: Option<AccountId>,
+    }
 
-        #[ink(message)]
-        pub fn dispute_order(&mut self, order_id: u64) -> Result<(), EscrowError> {
-            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
-            if let Some(mut order) = order_wrapped {
-                let caller: AccountId = Self::env().caller();
-                if order.vendor != caller {
-                    return Err(EscrowError::Unauthorised);
-                } else if order.status != 1 || order.status == 0 {
-                    return Err(EscrowError::StatusCanNotBeChanged);
-                }
+    #[ink(event)]
+    pub struct StartExport {
+        #[ink(topic)]
+        destination: AccountId,
+    }
 
-                order.status = 4;
-                self.orders.update(&order);
+    #[ink(event)]
+    pub struct TransferOwnership {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        pending_owner: AccountId,
+    }
 
-                // Emit event
-                self.env().emit_event(UpdateOrder {
-                    id: order.id,
-                    status: order.status,
-                });
-            } else {
-                return Err(EscrowError::OrderNotFound);
-            }
+    #[ink(event)]
+    pub struct TreasuryTransfer {
+        #[ink(topic)]
+        treasury: AccountId,
+        #[ink(topic)]
+        asset: Option<AccountId>,
+        amount: Balance,
+    }
 
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct Unpause {
+        #[ink(topic)]
+        caller: AccountId,
+        operations: u8,
+    }
 
-        #[ink(message)]
-        pub fn finalise_order(&mut self, order_id: u64) -> Result<(), EscrowError> {
-            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
-            if let Some(mut order) = order_wrapped {
-                let caller: AccountId = Self::env().caller();
-                if order.vendor != caller && caller != self.ownable.owner() {
-                    return Err(EscrowError::Unauthorised);
-                } else if order.status == 2 || order.status == 3 {
-                    return Err(EscrowError::StatusCanNotBeChanged);
-                }
+    #[ink(event)]
+    pub struct UpdateListing {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        vendor: AccountId,
+        price_per_unit: Balance,
+        expires_at: Option<Timestamp>,
+    }
 
-                order.status = 2;
-                self.orders.update(&order);
+    #[ink(event)]
+    pub struct UpdateOrderPaymentVerification {
+        #[ink(topic)]
+        id: u64,
+        #[ink(topic)]
+        buyer: AccountId,
+        #[ink(topic)]
+        vendor: AccountId,
+        caller: AccountId,
+    }
 
-                // Transfer funds to buyer
-                if self.env().transfer(order.buyer, order.amount).is_err() {
-                    panic!(
-                        "requested transfer failed. this can be the case if the contract does not\
-                         have sufficient free funds or if the transfer would have brought the\
-                         contract's balance below minimum balance."
-                    )
-                }
+    #[ink(event)]
+    pub struct UpdateVendor {
+        #[ink(topic)]
+        account: AccountId,
+        ethereum_address: String,
+    }
 
-                // Emit event
-                self.env().emit_event(UpdateOrder {
-                    id: order.id,
-                    status: order.status,
-                });
-            } else {
-                return Err(EscrowError::OrderNotFound);
-            }
+    #[ink(event)]
+    pub struct VerifyVendor {
+        #[ink(topic)]
+        account: AccountId,
+        verified: bool,
+        tier: u8,
+    }
 
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct WithdrawFromListing {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        vendor: AccountId,
+        amount: Balance,
+        new_balance: Balance,
+    }
 
-        #[ink(message)]
-        pub fn update_order_payment_verification(
-            &mut self,
-            order_id: u64,
-            payment_verification: String,
-        ) -> Result<(), EscrowError> {
-            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
-            if let Some(mut order) = order_wrapped {
-                let caller: AccountId = Self::env().caller();
-                if order.buyer != caller {
-                    return Err(EscrowError::Unauthorised);
-                } else if order.status == 2 {
-                    return Err(EscrowError::OrderFinalised);
-                } else if order.status == 3 {
-                    return Err(EscrowError::OrderCancelled);
-                }
-                order.payment_verification = Some(payment_verification);
-                order.status = 1;
-                self.orders.update(&order);
+    // === STRUCTS ===
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct AccountRoles {
+        is_vendor: bool,
+        is_verified: bool,
+        is_banned: bool,
+        is_arbitrator: bool,
+    }
+
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone)]
+    pub struct AuditEntry {
+        caller: AccountId,
+        action: AuditAction,
+        // Order or queued action the action applied to
+        target_id: Option<u64>,
+        timestamp: Timestamp,
+    }
+
+    // Lifetime trading history of a buyer
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct BuyerSummary {
+        orders_length: u64,
+        completed_orders_length: u64,
+        cancelled_orders_length: u64,
+        // Sum of the amounts of completed orders, across all assets
+        volume: Balance,
+        first_order_at: Timestamp,
+        last_order_at: Timestamp,
+    }
+
+    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Config {
+        admin: AccountId,
+        pending_admin: Option<AccountId>,
+        signers: Vec<AccountId>,
+        threshold: u8,
+        limits: Limits,
+        timeouts: Timeouts,
+        fee_bps: u16,
+        treasury: AccountId,
+        timelock_delay: Timestamp,
+        paused_operations: u8,
+        storage_version: u16,
+        event_version: u16,
+        governor: Option<AccountId>,
+        maintenance_mode: bool,
+        export_destination: Option<AccountId>,
+        curated: bool,
+        max_page_size: u16,
+        badge_collection: Option<AccountId>,
+        order_size_schedule: Vec<OrderSizeTier>,
+        dispute_weights: DisputeWeights,
+        yield_pool: Option<AccountId>,
+        upkeep_bounty: Balance,
+    }
+
+    // How much a dispute outcome moves the completion rate, in basis points of a settled order
+    // e.g. lost_bps of 20_000 => a lost dispute weighs as much as two cancelled orders
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct DisputeWeights {
+        won_bps: u16,
+        lost_bps: u16,
+    }
+    impl Default for DisputeWeights {
+        fn default() -> Self {
+            Self {
+                won_bps: 10_000,
+                lost_bps: 20_000,
+            }
+        }
+    }
+
+    // Parameters the contract is deployed with. The default keeps fees off, pays them to the
+    // deployer, leaves orders unrestricted and grants no arbitrators.
+    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct InstantiateArgs {
+        fee_bps: u16,
+        // None => the deployer
+        treasury: Option<AccountId>,
+        limits: Limits,
+        arbitrators: Vec<AccountId>,
+        // Deploys an instance for import_state to migrate an older one's state into. It stays
+        // in maintenance mode until activate is called.
+        importing: bool,
+    }
+    impl Default for InstantiateArgs {
+        fn default() -> Self {
+            InstantiateArgs {
+                fee_bps: 0,
+                treasury: None,
+                limits: Limits {
+                    min_order_amount: 0,
+                    max_order_amount: Balance::MAX,
+                    max_listings_per_vendor: u32::MAX,
+                    max_open_orders_per_buyer: u32::MAX,
+                    max_open_orders_per_listing: u32::MAX,
+                    max_orders_per_block_per_buyer: u32::MAX,
+                    hold_threshold: Balance::MAX,
+                    hold_amount: 0,
+                    min_payment_window: 0,
+                    max_payment_window: Timestamp::MAX,
+                },
+                arbitrators: vec![],
+                importing: false,
+            }
+        }
+    }
+
+    // A page of the state that export_state returns and import_state takes, for migrating to a
+    // new instance when set_code_hash isn't enough
+    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct StateBatch {
+        listings: Vec<Listing>,
+        orders: Vec<Order>,
+        // The vendors of the batch's listings
+        vendors: Vec<(AccountId, Vendor)>,
+        // The latest dispute of each of the batch's orders that has been disputed
+        disputes: Vec<Dispute>,
+        // Holds posted for the batch's orders that haven't been settled yet, by order id
+        holds: Vec<(u64, Balance)>,
+        // Where the next batch starts, None once everything has been exported
+        next_cursor: Option<u64>,
+    }
+
+    // Returned by check_invariants. holds is true when every check passes.
+    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct InvariantReport {
+        holds: bool,
+        // Available plus ordered amounts across all listings, should equal escrowed_amount
+        listed_amount: Balance,
+        escrowed_amount: Balance,
+        // Assets where the listed amounts plus accrued fees are more than the contract holds,
+        // as (asset, required, held)
+        underfunded_assets: Vec<(Option<AccountId>, Balance, Balance)>,
+        // Orders whose listing doesn't exist
+        orphaned_order_ids: Vec<u64>,
+    }
+
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct Limits {
+        min_order_amount: Balance,
+        max_order_amount: Balance,
+        max_listings_per_vendor: u32,
+        max_open_orders_per_buyer: u32,
+        // Bounds storage growth and the cost of sweeping a listing's orders
+        max_open_orders_per_listing: u32,
+        // Stops bots from locking up vendor liquidity with a flood of small orders
+        max_orders_per_block_per_buyer: u32,
+        // Orders above hold_threshold need the buyer to send hold_amount, which is refunded
+        // once the order is finalised or cancelled, unless it expires before being paid
+        hold_threshold: Balance,
+        hold_amount: Balance,
+        // Bounds for how long a buyer can be given to pay for an order
+        min_payment_window: Timestamp,
+        max_payment_window: Timestamp,
+    }
+
+    // Largest single order a buyer can place once they've completed this many orders
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct OrderSizeTier {
+        completed_orders_length: u64,
+        max_order_amount: Balance,
+    }
+
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone)]
+    pub struct QueuedAction {
+        id: u32,
+        action: AdminAction,
+        executable_at: Timestamp,
+        approvals: Vec<AccountId>,
+    }
+
+    // Ratings an account has received from the other party to its finalised orders, along
+    // with how reliably its orders are completed, in one of its roles
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct PartyReputation {
+        ratings_length: u64,
+        ratings_total: u64,
+        // Hundredths of a star e.g. 450 => 4.5, weighted towards recent epochs when queried
+        average_rating: u16,
+        // Orders settled since reputations were introduced
+        finalised_orders_length: u64,
+        cancelled_orders_length: u64,
+        // Share of those settled orders that were finalised, in basis points, weighted towards
+        // recent epochs and adjusted for dispute outcomes when queried
+        completion_rate_bps: u16,
+        // Disputes settled in the account's favour, or against it
+        disputes_won_length: u64,
+        disputes_lost_length: u64,
+        // Sum of the amounts of finalised orders, across all assets
+        volume: Balance,
+    }
+    impl PartyReputation {
+        fn add_rating(&mut self, rating: u8) {
+            self.ratings_length += 1;
+            self.ratings_total += u64::from(rating);
+            self.average_rating = (self.ratings_total * 100 / self.ratings_length) as u16;
+        }
+
+        fn amend_rating(&mut self, previous_rating: u8, rating: u8) {
+            self.ratings_total =
+                self.ratings_total - u64::from(previous_rating) + u64::from(rating);
+            self.average_rating = (self.ratings_total * 100 / self.ratings_length) as u16;
+        }
+
+        fn add_settled_order(&mut self, finalised: bool) {
+            if finalised {
+                self.finalised_orders_length += 1;
+            } else {
+                self.cancelled_orders_length += 1;
+            }
+            let settled: u64 = self.finalised_orders_length + self.cancelled_orders_length;
+            self.completion_rate_bps = (self.finalised_orders_length * 10_000 / settled) as u16;
+        }
+
+        fn add_volume(&mut self, amount: Balance) {
+            self.volume = self.volume.saturating_add(amount);
+        }
+
+        fn add_dispute(&mut self, won: bool) {
+            if won {
+                self.disputes_won_length += 1;
+            } else {
+                self.disputes_lost_length += 1;
+            }
+        }
+    }
+
+    // Weighted sums of reputation buckets, used to work out decayed averages and rates
+    #[derive(Default)]
+    struct DecayedReputation {
+        as_buyer: DecayedPartyReputation,
+        as_vendor: DecayedPartyReputation,
+        payments_length: u128,
+        total_time_to_pay: u128,
+    }
+    impl DecayedReputation {
+        fn add(&mut self, bucket: &Reputation, weight: u128) {
+            self.as_buyer.add(&bucket.as_buyer, weight);
+            self.as_vendor.add(&bucket.as_vendor, weight);
+            self.payments_length += u128::from(bucket.payments_length) * weight;
+            self.total_time_to_pay += u128::from(bucket.total_time_to_pay) * weight;
+        }
+
+        fn apply(&self, reputation: &mut Reputation, dispute_weights: &DisputeWeights) {
+            self.as_buyer
+                .apply(&mut reputation.as_buyer, dispute_weights);
+            self.as_vendor
+                .apply(&mut reputation.as_vendor, dispute_weights);
+            reputation.average_time_to_pay = self
+                .total_time_to_pay
+                .checked_div(self.payments_length)
+                .unwrap_or(0) as Timestamp;
+        }
+    }
+
+    #[derive(Default)]
+    struct DecayedPartyReputation {
+        ratings_length: u128,
+        ratings_total: u128,
+        finalised_orders_length: u128,
+        settled_orders_length: u128,
+        disputes_won_length: u128,
+        disputes_lost_length: u128,
+    }
+    impl DecayedPartyReputation {
+        fn add(&mut self, bucket: &PartyReputation, weight: u128) {
+            self.ratings_length += u128::from(bucket.ratings_length) * weight;
+            self.ratings_total += u128::from(bucket.ratings_total) * weight;
+            self.finalised_orders_length += u128::from(bucket.finalised_orders_length) * weight;
+            self.settled_orders_length +=
+                u128::from(bucket.finalised_orders_length + bucket.cancelled_orders_length)
+                    * weight;
+            self.disputes_won_length += u128::from(bucket.disputes_won_length) * weight;
+            self.disputes_lost_length += u128::from(bucket.disputes_lost_length) * weight;
+        }
+
+        // Won disputes count as extra finalised orders and lost ones as extra cancelled orders
+        fn apply(&self, reputation: &mut PartyReputation, dispute_weights: &DisputeWeights) {
+            reputation.average_rating = (self.ratings_total * 100)
+                .checked_div(self.ratings_length)
+                .unwrap_or(0) as u16;
+            let won: u128 = self.disputes_won_length * u128::from(dispute_weights.won_bps);
+            let lost: u128 = self.disputes_lost_length * u128::from(dispute_weights.lost_bps);
+            let completed: u128 = self.finalised_orders_length * 10_000 + won;
+            let settled: u128 = self.settled_orders_length * 10_000 + won + lost;
+            reputation.completion_rate_bps =
+                (completed * 10_000).checked_div(settled).unwrap_or(0) as u16;
+        }
+    }
+
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct Reputation {
+        as_buyer: PartyReputation,
+        as_vendor: PartyReputation,
+        // Time between placing an order and submitting its payment verification, as a buyer,
+        // with the average weighted towards recent epochs when queried
+        payments_length: u64,
+        total_time_to_pay: u64,
+        average_time_to_pay: Timestamp,
+    }
+    impl Reputation {
+        fn add_payment(&mut self, time_to_pay: Timestamp) {
+            self.payments_length += 1;
+            self.total_time_to_pay += time_to_pay;
+            self.average_time_to_pay = self.total_time_to_pay / self.payments_length;
+        }
+    }
+
+    // Vendors with the most volume in the epoch, highest first
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone, Default)]
+    pub struct TopVendors {
+        epoch: u64,
+        vendors: Vec<VendorVolume>,
+    }
+
+    // How long each party has to act before expire_order can move an order on
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct Timeouts {
+        // For the buyer to submit payment verification
+        payment_window: Timestamp,
+        // For the vendor to finalise or dispute a submitted payment, after which the order
+        // is disputed on the vendor's behalf
+        verification_window: Timestamp,
+        // For an arbitrator to resolve a dispute, after which it's escalated to admins
+        dispute_window: Timestamp,
+    }
+
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct VendorVolume {
+        vendor: AccountId,
+        volume: Balance,
+    }
+
+    // === CONTRACT ===
+    // Rarely changing configuration such as fees, limits and pause flags is kept in Lazy cells,
+    // so that messages only pay to load the parts they use.
+    #[ink(storage)]
+    #[derive(Default, Storage)]
+    pub struct Escrow {
+        #[storage_field]
+        ownable: ownable::Data,
+        listings: Listings,
+        orders: Orders,
+        disputes: Disputes,
+        vendors: Mapping<AccountId, Vendor>,
+        // Only counts vendors created since it was added, and on imported instances the vendors
+        // imported with a listing, see vendors_count
+        vendors_length: u32,
+        pending_owner: Option<AccountId>,
+        // Sum of all listing and order amounts still held on behalf of users
+        escrowed_amount: Balance,
+        roles: Mapping<(Role, AccountId), ()>,
+        // Bitmask of PAUSE_* operations that are currently blocked
+        paused_operations: Lazy<u8>,
+        storage_version: u16,
+        event_version: u16,
+        // Records of the current migration step that have been migrated so far
+        migration_cursor: Lazy<u64>,
+        // Receives fees and the contract's remaining balance when it is terminated
+        treasury: Lazy<AccountId>,
+        // Set from InstantiateArgs at deployment and only changed after that through the
+        // timelock. Zero, and left unwritten, unless fees were turned on.
+        fee_bps: Lazy<u16>,
+        // Fees collected per asset that haven't been claimed by the treasury yet
+        accrued_fees: Mapping<Option<AccountId>, Balance>,
+        // Paid out of the accrued AZERO fees for each duty perform_upkeep carries out
+        upkeep_bounty: Lazy<Balance>,
+        timelock_delay: Timestamp,
+        queued_actions: Mapping<u32, QueuedAction>,
+        queued_actions_length: u32,
+        // Signers who have approved cancelling each queued action
+        action_cancellations: Mapping<u32, Vec<AccountId>>,
+        // When threshold is above zero, queued actions need that many approvals from signers,
+        // to be executed or cancelled. This only covers AdminAction, see the README's trust
+        // model for what the owner can still change on their own.
+        signers: Vec<AccountId>,
+        threshold: u8,
+        // Sanctioned accounts that can't become vendors, list, order or deposit
+        blacklist: Mapping<AccountId, ()>,
+        limits: Lazy<Limits>,
+        // Caps order sizes by the buyer's completed orders, on top of limits, so that new
+        // accounts start small. Tiers are in ascending order.
+        order_size_schedule: Lazy<Vec<OrderSizeTier>>,
+        timeouts: Lazy<Timeouts>,
+        // Index queries return at most this many items, however many are asked for
+        max_page_size: Lazy<u16>,
+        // When set, parameters are managed by this governance contract instead of the owner
+        governor: Option<AccountId>,
+        // Ring buffer of the last AUDIT_LOG_CAPACITY privileged actions
+        audit_log: Mapping<u64, AuditEntry>,
+        // Total number of privileged actions ever recorded
+        audit_log_length: u64,
+        buyer_summaries: Mapping<AccountId, BuyerSummary>,
+        reputations: Mapping<AccountId, Reputation>,
+        // The same activity as reputations, bucketed by REPUTATION_EPOCH
+        reputation_epochs: Mapping<(AccountId, u64), Reputation>,
+        dispute_weights: Lazy<DisputeWeights>,
+        top_vendors: Lazy<TopVendors>,
+        // Contracts notified through OrderEventSubscriber::on_order_event when orders change
+        subscribers: Lazy<Vec<AccountId>>,
+        // Contracts each buyer lets open managed trades for them, as (buyer, manager)
+        trade_managers: Mapping<(AccountId, AccountId), ()>,
+        // The contract that opened each managed trade
+        order_managers: Mapping<u64, AccountId>,
+        // Hash of the text review, kept on IPFS, that came with each party's rating of an order
+        reviews: Mapping<(u64, AccountId), [u8; 32]>,
+        // When each party first rated an order, which amendments are allowed within
+        rated_at: Mapping<(u64, AccountId), Timestamp>,
+        // PSP34 collection that milestone badges are minted on. It has to let this contract
+        // mint and must reject transfers, so that badges stay with the account that earned them.
+        badge_collection: Option<AccountId>,
+        // traits::YieldPool contract that opted-in listings' idle AZERO is bonded into
+        yield_pool: Option<AccountId>,
+        // Amount bonded for each listing that opted into the yield pool
+        bonded_amounts: Mapping<u32, Balance>,
+        // Sum of bonded_amounts, which is held by the yield pool rather than the contract
+        bonded_amount: Balance,
+        // Stricter than pausing: only withdrawals and refunds of cancelled orders remain
+        // callable, intended for while a vulnerability is being patched
+        maintenance_mode: bool,
+        // Set on instances deployed to import another's state, until they're activated
+        importing: bool,
+        // The instance this one's state and funds are moving to, once the owner has started
+        // exporting. Nothing that changes listings or orders, or pays funds out, goes through
+        // from then on, so that nothing is left behind or paid out twice.
+        export_destination: Option<AccountId>,
+        // Listing id export_funds carries on from, and whether everything has been sent
+        export_funds_cursor: u32,
+        funds_exported: bool,
+        // When enabled, vendors can only be onboarded by a moderator
+        curated: bool,
+        // Block of each buyer's latest order and how many orders they created in it
+        orders_created_in_block: Mapping<AccountId, (BlockNumber, u32)>,
+        // Next nonce expected in each buyer's signed order intent
+        nonces: Mapping<AccountId, u64>,
+        // ECDSA public keys of payment processors whose attestations release orders
+        payment_processors: Mapping<[u8; 33], ()>,
+        // Anti-griefing holds posted for large orders that haven't been settled yet
+        holds: Mapping<u64, Balance>,
+        held_amount: Balance,
+        // Held during token calls. Lazy so that it's in storage before a reentrant call
+        // reads it, rather than only being written once the message returns.
+        reentrancy_lock: Lazy<bool>,
+    }
+    impl Escrow {
+        #[ink(constructor)]
+        pub fn new(args: InstantiateArgs) -> Result<Self, EscrowError> {
+            if args.fee_bps > MAX_FEE_BPS {
+                return Err(EscrowError::InvalidFee);
+            }
+            Self::validate_limits(&args.limits, DEFAULT_PAYMENT_WINDOW)?;
+            if args.arbitrators.len() > MAX_BATCH_SIZE {
+                return Err(EscrowError::BatchTooLarge);
+            }
+
+            // Storage starts out as each field's Default, so only what differs from it is written
+            let mut instance = Self::default();
+            instance._init_with_owner(Self::env().caller());
+            instance.storage_version = STORAGE_VERSION;
+            instance.event_version = EVENT_VERSION;
+            instance
+                .treasury
+                .set(&args.treasury.unwrap_or(Self::env().caller()));
+            if args.fee_bps > 0 {
+                instance.fee_bps.set(&args.fee_bps);
+            }
+            instance.timelock_delay = DEFAULT_TIMELOCK_DELAY;
+            instance.limits.set(&args.limits);
+            instance.timeouts.set(&Timeouts {
+                payment_window: DEFAULT_PAYMENT_WINDOW,
+                verification_window: DEFAULT_VERIFICATION_WINDOW,
+                dispute_window: DEFAULT_DISPUTE_WINDOW,
+            });
+            if args.importing {
+                instance.importing = true;
+                instance.maintenance_mode = true;
+            }
+            for account in args.arbitrators {
+                instance.roles.insert((Role::Arbitrator, account), &());
+                instance.record_audit(AuditAction::GrantRole(Role::Arbitrator, account), None);
 
                 // Emit event
-                self.env().emit_event(UpdateOrder {
-                    id: order.id,
-                    status: order.status,
+                Self::env().emit_event(GrantRole {
+                    account,
+                    role: Role::Arbitrator,
                 });
-            } else {
-                return Err(EscrowError::OrderNotFound);
             }
+            Ok(instance)
+        }
+
+        // === QUERIES ===
+        #[ink(message)]
+        pub fn account_roles(&self, account: AccountId) -> AccountRoles {
+            let vendor: Option<Vendor> = self.vendors.get(account);
+            AccountRoles {
+                is_vendor: vendor.is_some(),
+                is_verified: vendor.is_some_and(|vendor| vendor.verified),
+                is_banned: self.is_blacklisted(account),
+                is_arbitrator: self.has_role(Role::Arbitrator, account),
+            }
+        }
 
-            Ok(())
+        // Pass next_after_id back in as after_id to carry on, see ScanPage
+        #[ink(message)]
+        pub fn active_listings(
+            &self,
+            after_id: Option<u32>,
+            limit: u16,
+            direction: Direction,
+        ) -> ScanPage<u32, Listing> {
+            self.listings
+                .index_where(after_id, limit, direction, |listing| {
+                    listing.is_active(self.env().block_timestamp())
+                })
         }
 
+        // Newest first
         #[ink(message)]
-        pub fn withdraw_from_listing(
-            &mut self,
-            id: u32,
-            amount: Balance,
-        ) -> Result<(), EscrowError> {
-            let listing_wrapped: Option<Listing> = self.listings.values.get(id);
-            if let Some(mut listing) = listing_wrapped {
-                if listing.vendor != Self::env().caller() {
-                    return Err(EscrowError::Unauthorised);
+        pub fn audit_log(&self, page: u64, size: u16) -> Page<AuditEntry> {
+            let length: u64 = self.audit_log_length.min(AUDIT_LOG_CAPACITY);
+            let mut entries: Vec<AuditEntry> = vec![];
+            let (start, end) = page_bounds(length, page, self.page_size(size));
+            for i in start..end {
+                let index: u64 = (self.audit_log_length - 1 - i) % AUDIT_LOG_CAPACITY;
+                entries.push(self.audit_log.get(index).unwrap())
+            }
+            Page::new(entries, length, start)
+        }
+
+        #[ink(message)]
+        pub fn buyer_summary(&self, account: AccountId) -> BuyerSummary {
+            self.buyer_summaries.get(account).unwrap_or_default()
+        }
+
+        // Walks every listing and order, so it's meant for off-chain monitoring and audits
+        #[ink(message)]
+        pub fn check_invariants(&self) -> InvariantReport {
+            let mut listed_amount: Balance = 0;
+            let mut listed_by_asset: Vec<(Option<AccountId>, Balance)> = vec![];
+            for id in 0..self.listings.length() {
+                if let Some(listing) = self.listings.get(id) {
+                    let exposure: ListingExposure =
+                        self.listings.exposures.get(id).unwrap_or_default();
+                    let amount: Balance = listing.available_amount.saturating_add(exposure.amount);
+                    listed_amount = listed_amount.saturating_add(amount);
+                    match listed_by_asset
+                        .iter_mut()
+                        .find(|(asset, _)| *asset == listing.asset)
+                    {
+                        Some((_, listed)) => *listed = listed.saturating_add(amount),
+                        None => listed_by_asset.push((listing.asset, amount)),
+                    }
                 }
-                if amount > listing.available_amount {
-                    return Err(EscrowError::InsufficientFunds);
+            }
+
+            // Holds are always in AZERO, whatever the listing's asset
+            if !listed_by_asset.iter().any(|(asset, _)| asset.is_none()) {
+                listed_by_asset.push((None, 0));
+            }
+            let mut underfunded_assets: Vec<(Option<AccountId>, Balance, Balance)> = vec![];
+            for (asset, listed) in listed_by_asset {
+                let mut required: Balance =
+                    listed.saturating_add(self.accrued_fees.get(asset).unwrap_or(0));
+                if asset.is_none() {
+                    required = required.saturating_add(self.held_amount);
+                }
+                let held: Balance = match asset {
+                    Some(token) => PSP22Ref::balance_of(&token, self.env().account_id()),
+                    None => self.env().balance().saturating_add(self.bonded_amount),
                 };
+                if required > held {
+                    underfunded_assets.push((asset, required, held));
+                }
+            }
 
-                listing.available_amount -= amount;
-                self.listings.update(&listing);
-                if self.env().transfer(listing.vendor, amount).is_err() {
-                    panic!(
-                        "requested transfer failed. this can be the case if the contract does not\
-                         have sufficient free funds or if the transfer would have brought the\
-                         contract's balance below minimum balance."
-                    )
+            let mut orphaned_order_ids: Vec<u64> = vec![];
+            for id in 0..self.orders.length() {
+                if let Some(order) = self.orders.values.get(id) {
+                    if !self.listings.contains(order.listing_id) {
+                        orphaned_order_ids.push(id);
+                    }
                 }
+            }
+
+            InvariantReport {
+                holds: listed_amount == self.escrowed_amount
+                    && underfunded_assets.is_empty()
+                    && orphaned_order_ids.is_empty(),
+                listed_amount,
+                escrowed_amount: self.escrowed_amount,
+                underfunded_assets,
+                orphaned_order_ids,
+            }
+        }
+
+        #[ink(message)]
+        pub fn config(&self) -> Config {
+            Config {
+                admin: self.ownable.owner(),
+                pending_admin: self.pending_owner,
+                signers: self.signers.clone(),
+                threshold: self.threshold,
+                limits: self.limits.get_or_default(),
+                timeouts: self.timeouts.get_or_default(),
+                fee_bps: self.fee_bps.get_or_default(),
+                treasury: self.treasury.get_or_default(),
+                timelock_delay: self.timelock_delay,
+                paused_operations: self.paused_operations.get_or_default(),
+                storage_version: self.storage_version,
+                event_version: self.event_version,
+                governor: self.governor,
+                maintenance_mode: self.maintenance_mode,
+                export_destination: self.export_destination,
+                curated: self.curated,
+                max_page_size: self.max_page_size.get().unwrap_or(MAX_PAGE_SIZE),
+                badge_collection: self.badge_collection,
+                order_size_schedule: self.order_size_schedule.get_or_default(),
+                dispute_weights: self.dispute_weights.get_or_default(),
+                yield_pool: self.yield_pool,
+                upkeep_bounty: self.upkeep_bounty.get_or_default(),
+            }
+        }
+
+        // The name of the error with the numeric code, see EscrowError::code
+        #[ink(message)]
+        pub fn decode_error(&self, code: u16) -> Option<String> {
+            errors::decode_error(code).map(String::from)
+        }
+
+        #[ink(message)]
+        pub fn disputes(&self, page: u64, size: u16) -> Page<Dispute> {
+            let size: u16 = self.page_size(size);
+            Page::new(
+                self.disputes.index(page, size),
+                self.disputes.length(),
+                page.saturating_mul(size.into()),
+            )
+        }
+
+        // Pages through listings, then orders along with their disputes and holds,
+        // MAX_BATCH_SIZE at a time. The cursor counts listing ids followed by order ids,
+        // starting from 0. Vendors without listings aren't exported. Only available once
+        // a queued StartExport has frozen the state.
+        #[ink(message)]
+        pub fn export_state(&self, cursor: u64) -> Result<StateBatch, EscrowError> {
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+            if self.export_destination.is_none() {
+                return Err(EscrowError::ExportNotStarted);
+            }
+
+            let listings_length: u64 = self.listings.length().into();
+            let end: u64 = if cursor < listings_length {
+                listings_length.min(cursor + MAX_BATCH_SIZE as u64)
             } else {
-                return Err(EscrowError::ListingNotFound);
+                (listings_length + self.orders.length()).min(cursor + MAX_BATCH_SIZE as u64)
+            };
+            let mut batch: StateBatch = StateBatch {
+                listings: vec![],
+                orders: vec![],
+                vendors: vec![],
+                disputes: vec![],
+                holds: vec![],
+                next_cursor: None,
+            };
+            for position in cursor..end {
+                if position < listings_length {
+                    if let Some(listing) = self.listings.get(position as u32) {
+                        if !batch
+                            .vendors
+                            .iter()
+                            .any(|(vendor, _)| *vendor == listing.vendor)
+                        {
+                            if let Some(vendor) = self.vendors.get(listing.vendor) {
+                                batch.vendors.push((listing.vendor, vendor));
+                            }
+                        }
+                        batch.listings.push(listing);
+                    }
+                } else if let Some(order) = self.orders.values.get(position - listings_length) {
+                    if let Some(id) = self.disputes.id_by_order.get(order.id) {
+                        batch.disputes.push(self.disputes.values.get(id).unwrap());
+                    }
+                    let hold: Balance = self.order_hold(order.id);
+                    if hold > 0 {
+                        batch.holds.push((order.id, hold));
+                    }
+                    batch.orders.push(order);
+                }
+            }
+            if end < listings_length + self.orders.length() {
+                batch.next_cursor = Some(end);
             }
+            Ok(batch)
+        }
 
-            Ok(())
+        #[ink(message)]
+        pub fn has_role(&self, role: Role, account: AccountId) -> bool {
+            account == self.ownable.owner() || self.roles.contains((role, account))
         }
-    }
 
-    // === TESTS ===
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-        use ink::env::{test::DefaultAccounts, DefaultEnvironment};
-        use openbrush::test_utils;
+        #[ink(message)]
+        pub fn is_blacklisted(&self, account: AccountId) -> bool {
+            self.blacklist.contains(account)
+        }
 
-        // === HELPERS ===
-        fn init() -> (DefaultAccounts<DefaultEnvironment>, Escrow) {
-            let accounts = test_utils::accounts();
+        #[ink(message)]
+        pub fn is_payment_processor(&self, public_key: [u8; 33]) -> bool {
+            self.payment_processors.contains(public_key)
+        }
+
+        #[ink(message)]
+        pub fn is_trade_manager(&self, buyer: AccountId, manager: AccountId) -> bool {
+            self.trade_managers.contains((buyer, manager))
+        }
+
+        // How much of the listing is bonded into the yield pool, None when it hasn't opted in
+        #[ink(message)]
+        pub fn listing_bonded_amount(&self, id: u32) -> Option<Balance> {
+            self.bonded_amounts.get(id)
+        }
+
+        #[ink(message)]
+        pub fn listing_exposure(&self, id: u32) -> Option<ListingExposure> {
+            if id >= self.listings.length() {
+                return None;
+            }
+
+            Some(self.listings.exposures.get(id).unwrap_or_default())
+        }
+
+        #[ink(message)]
+        pub fn listings(
+            &self,
+            after_id: Option<u32>,
+            limit: u16,
+            direction: Direction,
+        ) -> Page<ListingSummary> {
+            let listings: Vec<Listing> =
+                self.listings
+                    .index(after_id, self.page_size(limit), direction);
+            let has_more: bool = match (direction, listings.last()) {
+                (Direction::Ascending, Some(listing)) => listing.id + 1 < self.listings.length(),
+                (Direction::Descending, Some(listing)) => listing.id > 0,
+                (_, None) => false,
+            };
+            Page {
+                items: self.listing_summaries(&listings),
+                total: self.listings.length().into(),
+                has_more,
+            }
+        }
+
+        #[ink(message)]
+        pub fn listings_count(&self) -> u32 {
+            self.listings.length()
+        }
+
+        #[ink(message)]
+        pub fn listings_by_pair(
+            &self,
+            asset: Option<AccountId>,
+            currency: String,
+            page: u32,
+            size: u16,
+        ) -> Page<ListingSummary> {
+            let size: u16 = self.page_size(size);
+            let (listings, length) = self.listings.index_by_pair(asset, currency, page, size);
+            Page::new(
+                self.listing_summaries(&listings),
+                length.into(),
+                u64::from(page).saturating_mul(size.into()),
+            )
+        }
+
+        #[ink(message)]
+        pub fn nonce(&self, account: AccountId) -> u64 {
+            self.nonces.get(account).unwrap_or(0)
+        }
+
+        // Unresolved disputes, newest first as orders_by_status orders them
+        #[ink(message)]
+        pub fn open_disputes(&self, page: u64, size: u16) -> Page<Dispute> {
+            let size: u16 = self.page_size(size);
+            let (order_ids, length) =
+                self.orders
+                    .ids_by_status
+                    .page(OrderStatus::Disputed as u8, page, size);
+            Page::new(
+                order_ids
+                    .into_iter()
+                    .map(|order_id| self.open_dispute(order_id))
+                    .collect(),
+                length,
+                page.saturating_mul(size.into()),
+            )
+        }
+
+        // The hash buyers sign to have a relayer act on one of their orders
+        #[ink(message)]
+        pub fn order_action_intent_hash(&self, intent: OrderActionIntent) -> [u8; 32] {
+            self.env()
+                .hash_encoded::<Blake2x256, _>(&(self.env().account_id(), intent))
+        }
+
+        // The hash buyers sign with their ECDSA key. It includes this contract's address so
+        // that intents can't be replayed against another deployment.
+        #[ink(message)]
+        pub fn order_intent_hash(&self, intent: OrderIntent) -> [u8; 32] {
+            self.env()
+                .hash_encoded::<Blake2x256, _>(&(self.env().account_id(), intent))
+        }
+
+        // The contract that opened the order as a managed trade, if any
+        #[ink(message)]
+        pub fn order_manager(&self, order_id: u64) -> Option<AccountId> {
+            self.order_managers.get(order_id)
+        }
+
+        #[ink(message)]
+        pub fn order_hold(&self, order_id: u64) -> Balance {
+            self.holds.get(order_id).unwrap_or(0)
+        }
+
+        // Hash of the review the reviewer left with their rating of the order
+        #[ink(message)]
+        pub fn order_review(&self, order_id: u64, reviewer: AccountId) -> Option<[u8; 32]> {
+            self.reviews.get((order_id, reviewer))
+        }
+
+        #[ink(message)]
+        pub fn order_detail(&self, order_id: u64) -> Result<OrderDetail, EscrowError> {
+            let order: Order = self
+                .orders
+                .values
+                .get(order_id)
+                .ok_or(EscrowError::OrderNotFound)?;
+            Ok(OrderDetail {
+                listing: self
+                    .listings
+                    .get(order.listing_id)
+                    .ok_or(EscrowError::ListingNotFound)?,
+                buyer: self.buyer_summary(order.buyer),
+                vendor: self.vendor_profile(order.vendor),
+                order,
+            })
+        }
+
+        // Listings that can fill an order of at least min_amount. Like active_listings, pass
+        // next_after_id back in as after_id to carry on.
+        #[ink(message)]
+        pub fn listings_with_liquidity(
+            &self,
+            min_amount: Balance,
+            after_id: Option<u32>,
+            limit: u16,
+            direction: Direction,
+        ) -> ScanPage<u32, ListingSummary> {
+            let listings: ScanPage<u32, Listing> =
+                self.listings
+                    .index_where(after_id, self.page_size(limit), direction, |listing| {
+                        listing.is_active(self.env().block_timestamp())
+                            && listing.available_amount >= min_amount
+                    });
+            ScanPage {
+                items: self.listing_summaries(&listings.items),
+                next_after_id: listings.next_after_id,
+            }
+        }
+
+        #[ink(message)]
+        pub fn orders(
+            &self,
+            after_id: Option<u64>,
+            limit: u16,
+            direction: Direction,
+        ) -> Page<OrderSummary> {
+            let orders: Vec<Order> = self
+                .orders
+                .index(after_id, self.page_size(limit), direction);
+            let has_more: bool = match (direction, orders.last()) {
+                (Direction::Ascending, Some(order)) => order.id + 1 < self.orders.length(),
+                (Direction::Descending, Some(order)) => order.id > 0,
+                (_, None) => false,
+            };
+            Page {
+                items: orders.iter().map(Order::summary).collect(),
+                total: self.orders.length(),
+                has_more,
+            }
+        }
+
+        #[ink(message)]
+        pub fn orders_count(&self) -> u64 {
+            self.orders.length()
+        }
+
+        #[ink(message)]
+        pub fn orders_by_status(
+            &self,
+            status: u8,
+            page: u64,
+            size: u16,
+        ) -> Result<Page<OrderSummary>, EscrowError> {
+            OrderStatus::try_from(status)?;
+            let size: u16 = self.page_size(size);
+            let (orders, length) = self.orders.index_by_status(status, page, size);
+            Ok(Page::new(
+                orders.iter().map(Order::summary).collect(),
+                length,
+                page.saturating_mul(size.into()),
+            ))
+        }
+
+        #[ink(message)]
+        pub fn orders_created_between(
+            &self,
+            from: Timestamp,
+            to: Timestamp,
+            page: u64,
+            size: u16,
+        ) -> Page<OrderSummary> {
+            let size: u16 = self.page_size(size);
+            let (orders, length) = self.orders.index_created_between(from, to, page, size);
+            Page::new(
+                orders.iter().map(Order::summary).collect(),
+                length,
+                page.saturating_mul(size.into()),
+            )
+        }
+
+        #[ink(message)]
+        pub fn orders_for_buyer(
+            &self,
+            buyer: AccountId,
+            page: u64,
+            size: u16,
+        ) -> Page<OrderSummary> {
+            let size: u16 = self.page_size(size);
+            let (orders, length) = self.orders.index_by_buyer(buyer, page, size);
+            Page::new(
+                orders.iter().map(Order::summary).collect(),
+                length,
+                page.saturating_mul(size.into()),
+            )
+        }
+
+        #[ink(message)]
+        pub fn orders_for_vendor(
+            &self,
+            vendor: AccountId,
+            page: u64,
+            size: u16,
+        ) -> Page<OrderSummary> {
+            let size: u16 = self.page_size(size);
+            let (orders, length) = self.orders.index_by_vendor(vendor, page, size);
+            Page::new(
+                orders.iter().map(Order::summary).collect(),
+                length,
+                page.saturating_mul(size.into()),
+            )
+        }
+
+        // The hash payment processors sign to attest that an order has been paid
+        #[ink(message)]
+        pub fn payment_attestation_hash(
+            &self,
+            order_id: u64,
+            payment_verification: [u8; 32],
+        ) -> [u8; 32] {
+            self.env().hash_encoded::<Blake2x256, _>(&(
+                self.env().account_id(),
+                order_id,
+                payment_verification,
+            ))
+        }
+
+        // Orders awaiting the vendor's review of the buyer's payment, newest first
+        #[ink(message)]
+        pub fn pending_verifications_for_vendor(
+            &self,
+            vendor: AccountId,
+            page: u64,
+            size: u16,
+        ) -> Page<OrderSummary> {
+            let size: u16 = self.page_size(size);
+            let (orders, length) = self
+                .orders
+                .index_pending_verifications_by_vendor(vendor, page, size);
+            Page::new(
+                orders.iter().map(Order::summary).collect(),
+                length,
+                page.saturating_mul(size.into()),
+            )
+        }
+
+        #[ink(message)]
+        pub fn queued_action(&self, id: u32) -> Option<QueuedAction> {
+            self.queued_actions.get(id)
+        }
+
+        // Lifetime counts, with averages and rates weighted towards recent epochs so that old
+        // history fades out
+        #[ink(message)]
+        pub fn reputation(&self, account: AccountId) -> Reputation {
+            let mut reputation: Reputation = self.reputations.get(account).unwrap_or_default();
+            let current_epoch: u64 = self.env().block_timestamp() / REPUTATION_EPOCH;
+            let mut decayed: DecayedReputation = DecayedReputation::default();
+            let mut weight: u128 = 10_000;
+            for age in 0..REPUTATION_EPOCHS.min(current_epoch + 1) {
+                if let Some(bucket) = self.reputation_epochs.get((account, current_epoch - age)) {
+                    decayed.add(&bucket, weight);
+                }
+                weight = weight * REPUTATION_DECAY_BPS / 10_000;
+            }
+            decayed.apply(&mut reputation, &self.dispute_weights.get_or_default());
+            reputation
+        }
+
+        // Listings in the same order as the ids, with None for ids that don't exist
+        #[ink(message)]
+        pub fn show_listings(&self, ids: Vec<u32>) -> Result<Vec<Option<Listing>>, EscrowError> {
+            if ids.len() > MAX_BATCH_SIZE {
+                return Err(EscrowError::BatchTooLarge);
+            }
+
+            Ok(ids.iter().map(|id| self.listings.get(*id)).collect())
+        }
+
+        // Orders in the same order as the ids, with None for ids that don't exist
+        #[ink(message)]
+        pub fn show_orders(&self, ids: Vec<u64>) -> Result<Vec<Option<Order>>, EscrowError> {
+            if ids.len() > MAX_BATCH_SIZE {
+                return Err(EscrowError::BatchTooLarge);
+            }
+
+            Ok(ids.iter().map(|id| self.orders.values.get(*id)).collect())
+        }
+
+        #[ink(message)]
+        pub fn status_transitions(&self, page: u64, size: u16) -> Page<StatusTransition> {
+            let size: u16 = self.page_size(size);
+            let length: u64 = self
+                .orders
+                .transitions_length
+                .min(STATUS_TRANSITIONS_CAPACITY);
+            let mut transitions: Vec<StatusTransition> = vec![];
+            let (start, end) = page_bounds(length, page, size);
+            for i in start..end {
+                let index: u64 =
+                    (self.orders.transitions_length - 1 - i) % STATUS_TRANSITIONS_CAPACITY;
+                transitions.push(self.orders.transitions.get(index).unwrap())
+            }
+            Page::new(transitions, length, start)
+        }
+
+        #[ink(message)]
+        pub fn subscribers(&self) -> Vec<AccountId> {
+            self.subscribers.get_or_default()
+        }
+
+        // Vendors with the most volume finalised in the current reputation epoch
+        #[ink(message)]
+        pub fn top_vendors(&self, n: u16) -> Vec<VendorVolume> {
+            let top_vendors: TopVendors = self.top_vendors.get_or_default();
+            if top_vendors.epoch != self.env().block_timestamp() / REPUTATION_EPOCH {
+                return vec![];
+            }
+            top_vendors.vendors.into_iter().take(n.into()).collect()
+        }
+
+        // Available plus ordered amounts across all listings, summed over every asset
+        #[ink(message)]
+        pub fn total_value_locked(&self) -> Balance {
+            self.escrowed_amount
+        }
+
+        #[ink(message)]
+        pub fn vendor_profile(&self, account: AccountId) -> Option<VendorProfile> {
+            let vendor: Vendor = self.vendors.get(account)?;
+            Some(VendorProfile {
+                account,
+                ethereum_address: vendor.ethereum_address,
+                verified: vendor.verified,
+                tier: vendor.tier,
+                listings_length: vendor.listings_length,
+                orders_length: self.orders.ids_by_vendor.length(account),
+                completed_orders_length: vendor.completed_orders_length,
+                last_active_at: vendor.last_active_at,
+            })
+        }
+
+        // Vendors created since the release that added this count, which isn't backfilled as
+        // vendors can't be enumerated on-chain. Instances filled in with import_state only count
+        // the vendors exported along with their listings. Index the CreateVendor events for an
+        // exact count of older deployments.
+        #[ink(message)]
+        pub fn vendors_count(&self) -> u32 {
+            self.vendors_length
+        }
+
+        // === TXS ===
+        #[ink(message)]
+        pub fn accept_ownership(&mut self) -> Result<(), EscrowError> {
+            let caller: AccountId = Self::env().caller();
+            if self.pending_owner != Some(caller) {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            let previous_owner: AccountId = self.ownable.owner();
+            // openbrush only exposes setting the owner through its init helper
+            self._init_with_owner(caller);
+            self.pending_owner = None;
+
+            self.record_audit(AuditAction::AcceptOwnership, None);
+
+            // Emit event
+            self.env().emit_event(AcceptOwnership {
+                previous_owner,
+                new_owner: caller,
+            });
+
+            Ok(())
+        }
+
+        // Ends the import, taking the instance out of maintenance mode. The imported listings
+        // and orders have to be funded first.
+        #[ink(message)]
+        pub fn activate(&mut self) -> Result<(), EscrowError> {
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+            if !self.importing {
+                return Err(EscrowError::StateImportLocked);
+            }
+            if !self.check_invariants().holds {
+                return Err(EscrowError::InvalidImport);
+            }
+
+            self.importing = false;
+            self.maintenance_mode = false;
+
+            self.record_audit(AuditAction::Activate, None);
+
+            // Emit event
+            self.env().emit_event(Activate {
+                listings_length: self.listings.length(),
+                orders_length: self.orders.length(),
+            });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn add_payment_processor(&mut self, public_key: [u8; 33]) -> Result<(), EscrowError> {
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            self.payment_processors.insert(public_key, &());
+
+            self.record_audit(AuditAction::AddPaymentProcessor(public_key), None);
+
+            // Emit event
+            self.env().emit_event(AddPaymentProcessor { public_key });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn add_subscriber(&mut self, subscriber: AccountId) -> Result<(), EscrowError> {
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+            let mut subscribers: Vec<AccountId> = self.subscribers.get_or_default();
+            if subscribers.contains(&subscriber) {
+                return Ok(());
+            }
+            if subscribers.len() >= MAX_SUBSCRIBERS {
+                return Err(EscrowError::SubscriberLimitReached);
+            }
+
+            subscribers.push(subscriber);
+            self.subscribers.set(&subscribers);
+
+            self.record_audit(AuditAction::AddSubscriber(subscriber), None);
+
+            // Emit event
+            self.env().emit_event(AddSubscriber { subscriber });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn add_to_blacklist(&mut self, account: AccountId) -> Result<(), EscrowError> {
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            self.blacklist.insert(account, &());
+
+            self.record_audit(AuditAction::AddToBlacklist(account), None);
+
+            // Emit event
+            self.env().emit_event(AddToBlacklist { account });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn approve_action(&mut self, id: u32) -> Result<(), EscrowError> {
+            let caller: AccountId = Self::env().caller();
+            if !self.signers.contains(&caller) {
+                return Err(EscrowError::Unauthorised);
+            }
+            let queued_action_wrapped: Option<QueuedAction> = self.queued_actions.get(id);
+            if let Some(mut queued_action) = queued_action_wrapped {
+                if queued_action.approvals.contains(&caller) {
+                    return Err(EscrowError::AlreadyApproved);
+                }
+
+                queued_action.approvals.push(caller);
+                self.queued_actions.insert(id, &queued_action);
+
+                self.record_audit(AuditAction::ApproveAction, Some(id.into()));
+
+                // Emit event
+                self.env().emit_event(ApproveAction { id, signer: caller });
+            } else {
+                return Err(EscrowError::QueuedActionNotFound);
+            }
+
+            Ok(())
+        }
+
+        // Removes finalised and cancelled orders once ORDER_RETENTION_PERIOD has passed since
+        // they were settled, refunding the storage deposit they free to the caller. Orders that
+        // don't exist or can't be archived yet are skipped.
+        #[ink(message)]
+        pub fn archive_orders(&mut self, order_ids: Vec<u64>) -> Result<(), EscrowError> {
+            self.guard_export()?;
+            if order_ids.len() > MAX_BATCH_SIZE {
+                return Err(EscrowError::BatchTooLarge);
+            }
+
+            let caller: AccountId = Self::env().caller();
+            let block_timestamp: Timestamp = self.env().block_timestamp();
+            for order_id in order_ids {
+                if let Some(order) = self.orders.values.get(order_id) {
+                    if !matches!(order.status(), 2 | 3)
+                        || block_timestamp
+                            < order
+                                .status_updated_at
+                                .saturating_add(ORDER_RETENTION_PERIOD)
+                    {
+                        continue;
+                    }
+
+                    // Emit event
+                    self.env().emit_event(ArchiveOrder {
+                        id: order.id,
+                        caller,
+                        order: order.clone(),
+                    });
+
+                    self.orders.archive(&order);
+                }
+            }
+
+            Ok(())
+        }
+
+        // Restarts the dispute window so the arbitrator has the whole of it to resolve
+        #[ink(message)]
+        pub fn assign_arbitrator(
+            &mut self,
+            order_id: u64,
+            arbitrator: AccountId,
+        ) -> Result<(), EscrowError> {
+            if !self.has_role(Role::Moderator, Self::env().caller()) {
+                return Err(EscrowError::Unauthorised);
+            }
+            if !self.has_role(Role::Arbitrator, arbitrator) {
+                return Err(EscrowError::Unauthorised);
+            }
+            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
+            if let Some(mut order) = order_wrapped {
+                if order.status() != 4 {
+                    return Err(EscrowError::StatusCanNotBeChanged);
+                }
+
+                let mut dispute: Dispute = self.open_dispute(order_id);
+                dispute.arbitrator = Some(arbitrator);
+                self.disputes.update(&dispute);
+                order.status_updated_at = self.env().block_timestamp();
+                self.orders.update(&order);
+
+                self.record_audit(AuditAction::AssignArbitrator(arbitrator), Some(order_id));
+
+                // Emit event
+                self.env().emit_event(ArbitratorAssigned {
+                    order_id,
+                    arbitrator,
+                    deadline: order.status_updated_at
+                        + self.timeouts.get_or_default().dispute_window,
+                });
+            } else {
+                return Err(EscrowError::OrderNotFound);
+            }
+
+            Ok(())
+        }
+
+        // Once a threshold is set, cancelling needs as many signers as executing, so that a
+        // single admin or signer can't veto an action on their own. Until then each call
+        // counts as the caller's approval.
+        #[ink(message)]
+        pub fn cancel_action(&mut self, id: u32) -> Result<(), EscrowError> {
+            self.authorise_admin_action()?;
+            if !self.queued_actions.contains(id) {
+                return Err(EscrowError::QueuedActionNotFound);
+            }
+            if self.threshold > 0 {
+                let caller: AccountId = Self::env().caller();
+                if !self.signers.contains(&caller) {
+                    return Err(EscrowError::Unauthorised);
+                }
+                let mut cancellations: Vec<AccountId> =
+                    self.action_cancellations.get(id).unwrap_or_default();
+                if cancellations.contains(&caller) {
+                    return Err(EscrowError::AlreadyApproved);
+                }
+                cancellations.push(caller);
+                // Approvals from accounts that have since stopped being signers don't count
+                let approvals: usize = cancellations
+                    .iter()
+                    .filter(|signer| self.signers.contains(signer))
+                    .count();
+                if approvals < usize::from(self.threshold) {
+                    self.action_cancellations.insert(id, &cancellations);
+
+                    // Emit event
+                    self.env()
+                        .emit_event(ApproveCancelAction { id, signer: caller });
+
+                    return Ok(());
+                }
+            }
+
+            self.queued_actions.remove(id);
+            self.action_cancellations.remove(id);
+
+            self.record_audit(AuditAction::CancelAction, Some(id.into()));
+
+            // Emit event
+            self.env().emit_event(CancelAction { id });
+
+            Ok(())
+        }
+
+        // Sends the fees collected for an asset to the treasury
+        #[ink(message)]
+        pub fn claim_fees(&mut self, asset: Option<AccountId>) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            if !self.has_role(Role::Admin, Self::env().caller()) {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            let amount: Balance = self.accrued_fees.get(asset).unwrap_or(0);
+            if amount > 0 {
+                let treasury: AccountId = self.treasury.get_or_default();
+                self.accrued_fees.remove(asset);
+                self.transfer_asset(asset, treasury, amount);
+
+                // Emit event
+                self.env().emit_event(TreasuryTransfer {
+                    treasury,
+                    asset,
+                    amount,
+                });
+            }
+
+            self.record_audit(AuditAction::ClaimFees(asset), None);
+
+            Ok(())
+        }
+
+        // Returns the available amount to the vendor for good. Amounts from open orders
+        // that are later cancelled can still be withdrawn.
+        #[ink(message)]
+        pub fn close_listing(&mut self, id: u32) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            let listing_wrapped: Option<Listing> = self.listings.get(id);
+            if let Some(mut listing) = listing_wrapped {
+                if listing.vendor != Self::env().caller() {
+                    return Err(EscrowError::Unauthorised);
+                }
+                if listing.closed {
+                    return Err(EscrowError::ListingClosed);
+                }
+
+                let refunded: Balance = self.close(&mut listing)?;
+                self.record_vendor_activity(listing.vendor);
+
+                // Emit event
+                self.env().emit_event(ListingClosed {
+                    id,
+                    vendor: listing.vendor,
+                    drained: false,
+                    refunded,
+                });
+            } else {
+                return Err(EscrowError::ListingNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn create_listing(
+            &mut self,
+            asset: Option<AccountId>,
+            currency: String,
+            price_per_unit: Balance,
+        ) -> Result<(), EscrowError> {
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            if self.is_paused(PAUSE_CREATE_LISTING) {
+                return Err(EscrowError::Paused);
+            }
+            if self.is_blacklisted(Self::env().caller()) {
+                return Err(EscrowError::Blacklisted);
+            }
+            if self.listings.length() == u32::MAX {
+                return Err(EscrowError::ListingLimitReached);
+            }
+            let caller: AccountId = Self::env().caller();
+            let vendor_wrapped: Option<Vendor> = self.vendors.get(caller);
+            if vendor_wrapped.is_none() {
+                return Err(EscrowError::ListingCanOnlyBeCreatedByAVendor);
+            }
+            let mut vendor: Vendor = vendor_wrapped.unwrap();
+            if vendor.listings_length >= self.limits.get_or_default().max_listings_per_vendor {
+                return Err(EscrowError::VendorListingLimitReached);
+            }
+            if currency.len() != 3 {
+                return Err(EscrowError::InvalidCurrency);
+            }
+
+            let listing: Listing = Listing {
+                id: self.listings.length(),
+                vendor: caller,
+                asset,
+                currency,
+                available_amount: 0,
+                price_per_unit,
+                paused: false,
+                closed: false,
+                expires_at: None,
+            };
+            self.listings.create(&listing);
+            vendor.listings_length += 1;
+            vendor.last_active_at = self.env().block_timestamp();
+            self.vendors.insert(caller, &vendor);
+
+            // Emit event
+            self.env().emit_event(CreateListing {
+                id: listing.id,
+                vendor: listing.vendor,
+            });
+
+            Ok(())
+        }
+
+        // Lets another contract embed the escrow as its settlement layer, opening a trade for
+        // a buyer who has approved it with set_trade_manager. Orders above the hold threshold
+        // need the hold sent with the call, and it's refunded to the buyer like any other hold.
+        // While the buyer keeps it approved, the manager can cancel the order and submit payment
+        // verification for the buyer. It's called back through TradeManager::on_order_settled
+        // once the order is finalised or cancelled.
+        #[ink(message, payable)]
+        pub fn create_managed_order(
+            &mut self,
+            buyer: AccountId,
+            listing_id: u32,
+            amount: Balance,
+        ) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            let manager: AccountId = Self::env().caller();
+            if !self.is_trade_manager(buyer, manager) {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            let id: u64 = self.orders.length();
+            self.place_order(buyer, listing_id, amount, Self::env().transferred_value())?;
+            self.order_managers.insert(id, &manager);
+
+            // Emit event
+            self.env()
+                .emit_event(CreateManagedOrder { id, buyer, manager });
+
+            Ok(())
+        }
+
+        // For orders above the hold threshold, which must send the hold amount with the call
+        #[ink(message, payable)]
+        pub fn create_order_with_hold(
+            &mut self,
+            listing_id: u32,
+            amount: Balance,
+        ) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            self.place_order(
+                Self::env().caller(),
+                listing_id,
+                amount,
+                Self::env().transferred_value(),
+            )
+        }
+
+        // Lets a relayer pay the fees for a buyer who signed the intent off-chain
+        #[ink(message)]
+        pub fn create_order_with_sig(
+            &mut self,
+            buyer: AccountId,
+            intent: OrderIntent,
+            signature: [u8; 65],
+        ) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            self.authorise_relayed_intent(
+                buyer,
+                intent.nonce,
+                intent.deadline,
+                self.order_intent_hash(intent),
+                signature,
+            )?;
+
+            self.nonces.insert(buyer, &(intent.nonce + 1));
+            // Relayed orders can't post a hold, so they're limited to the hold threshold
+            self.place_order(buyer, intent.listing_id, intent.amount, 0)
+        }
+
+        #[ink(message)]
+        pub fn create_vendor(&mut self, ethereum_address: String) -> Result<(), EscrowError> {
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            if self.is_paused(PAUSE_CREATE_VENDOR) {
+                return Err(EscrowError::Paused);
+            }
+            if self.curated {
+                return Err(EscrowError::CuratedMarketplace);
+            }
+
+            self.register_vendor(Self::env().caller(), ethereum_address)
+        }
+
+        // Returning an error reverts the call, which sends the transferred value back to the
+        // caller. Nothing is written until every check has passed, so the off-chain tests,
+        // which don't revert, see the same state.
+        #[ink(message, payable)]
+        pub fn deposit_into_listing(&mut self, id: u32) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            if self.is_paused(PAUSE_DEPOSIT) {
+                return Err(EscrowError::Paused);
+            }
+            if self.is_blacklisted(Self::env().caller()) {
+                return Err(EscrowError::Blacklisted);
+            }
+            let listing_wrapped: Option<Listing> = self.listings.get(id);
+            if let Some(mut listing) = listing_wrapped {
+                if listing.vendor != Self::env().caller() {
+                    return Err(EscrowError::Unauthorised);
+                }
+                if listing.closed {
+                    return Err(EscrowError::ListingClosed);
+                }
+                if listing.asset.is_some() {
+                    return Err(EscrowError::AssetMismatch);
+                }
+
+                listing.available_amount = listing
+                    .available_amount
+                    .checked_add(self.env().transferred_value())
+                    .ok_or(EscrowError::Overflow)?;
+                self.escrowed_amount = self
+                    .escrowed_amount
+                    .checked_add(self.env().transferred_value())
+                    .ok_or(EscrowError::Overflow)?;
+                self.listings
+                    .update_available_amount(id, listing.available_amount);
+                self.sync_yield(id, listing.vendor)?;
+                self.record_vendor_activity(listing.vendor);
+
+                // Emit event
+                self.env().emit_event(DepositIntoListing {
+                    id,
+                    vendor: listing.vendor,
+                    amount: self.env().transferred_value(),
+                    new_balance: listing.available_amount,
+                });
+            } else {
+                return Err(EscrowError::ListingNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn deposit_tokens_into_listing(
+            &mut self,
+            id: u32,
+            amount: Balance,
+        ) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            if self.is_paused(PAUSE_DEPOSIT) {
+                return Err(EscrowError::Paused);
+            }
+            if self.is_blacklisted(Self::env().caller()) {
+                return Err(EscrowError::Blacklisted);
+            }
+            let listing_wrapped: Option<Listing> = self.listings.get(id);
+            if let Some(mut listing) = listing_wrapped {
+                let caller: AccountId = Self::env().caller();
+                if listing.vendor != caller {
+                    return Err(EscrowError::Unauthorised);
+                }
+                if listing.closed {
+                    return Err(EscrowError::ListingClosed);
+                }
+                if let Some(token) = listing.asset {
+                    self.reentrancy_lock.set(&true);
+                    let transferred: bool = PSP22Ref::transfer_from(
+                        &token,
+                        caller,
+                        self.env().account_id(),
+                        amount,
+                        Vec::new(),
+                    )
+                    .is_ok();
+                    self.reentrancy_lock.set(&false);
+                    if !transferred {
+                        return Err(EscrowError::TokenTransferFailed);
+                    }
+                } else {
+                    return Err(EscrowError::AssetMismatch);
+                }
+
+                listing.available_amount = listing
+                    .available_amount
+                    .checked_add(amount)
+                    .ok_or(EscrowError::Overflow)?;
+                self.escrowed_amount = self
+                    .escrowed_amount
+                    .checked_add(amount)
+                    .ok_or(EscrowError::Overflow)?;
+                self.listings
+                    .update_available_amount(id, listing.available_amount);
+                self.record_vendor_activity(listing.vendor);
+
+                // Emit event
+                self.env().emit_event(DepositIntoListing {
+                    id,
+                    vendor: listing.vendor,
+                    amount,
+                    new_balance: listing.available_amount,
+                });
+            } else {
+                return Err(EscrowError::ListingNotFound);
+            }
+
+            Ok(())
+        }
+
+        // Either party can take a dispute over the assigned arbitrator's head, leaving it
+        // to admins and restarting the dispute window
+        #[ink(message)]
+        pub fn escalate_dispute(&mut self, order_id: u64) -> Result<(), EscrowError> {
+            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
+            if let Some(mut order) = order_wrapped {
+                let caller: AccountId = Self::env().caller();
+                if order.buyer != caller && order.vendor != caller {
+                    return Err(EscrowError::Unauthorised);
+                }
+                if order.status() != 4 {
+                    return Err(EscrowError::StatusCanNotBeChanged);
+                }
+
+                self.escalate(&mut order, caller)?;
+            } else {
+                return Err(EscrowError::OrderNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn execute_action(&mut self, id: u32) -> Result<(), EscrowError> {
+            let queued_action_wrapped: Option<QueuedAction> = self.queued_actions.get(id);
+            match &queued_action_wrapped {
+                Some(queued_action) => self.authorise_queued_action(&queued_action.action)?,
+                None => self.authorise_admin_action()?,
+            }
+            if let Some(queued_action) = queued_action_wrapped {
+                if self.env().block_timestamp() < queued_action.executable_at {
+                    return Err(EscrowError::TimelockNotExpired);
+                }
+                // Approvals from accounts that have since stopped being signers don't count
+                let approvals: usize = queued_action
+                    .approvals
+                    .iter()
+                    .filter(|signer| self.signers.contains(signer))
+                    .count();
+                if approvals < usize::from(self.threshold) {
+                    return Err(EscrowError::InsufficientApprovals);
+                }
+                // There's no going back from an export: maintenance mode stays on and
+                // cancellations, withdrawals and closes are refused too
+                if let AdminAction::StartExport(_) = queued_action.action {
+                    self.guard_export()?;
+                    if self.bonded_amount > 0 {
+                        return Err(EscrowError::YieldPoolInUse);
+                    }
+                }
+
+                self.queued_actions.remove(id);
+                self.action_cancellations.remove(id);
+                match &queued_action.action {
+                    AdminAction::CodeHash(code_hash) => {
+                        if ink::env::set_code_hash(code_hash).is_err() {
+                            return Err(EscrowError::CodeHashInvalid);
+                        }
+                    }
+                    AdminAction::FeeBps(fee_bps) => self.fee_bps.set(fee_bps),
+                    AdminAction::Signers(signers, threshold) => {
+                        self.signers = signers.clone();
+                        self.threshold = *threshold;
+                    }
+                    AdminAction::StartExport(destination) => {
+                        self.export_destination = Some(*destination);
+                        self.maintenance_mode = true;
+
+                        // Emit event
+                        self.env().emit_event(StartExport {
+                            destination: *destination,
+                        });
+                    }
+                    AdminAction::TimelockDelay(delay) => self.timelock_delay = *delay,
+                    AdminAction::Treasury(treasury) => self.treasury.set(treasury),
+                }
+
+                self.record_audit(AuditAction::ExecuteAction, Some(id.into()));
+
+                // Emit event
+                self.env().emit_event(ExecuteAction {
+                    id,
+                    action: queued_action.action,
+                });
+            } else {
+                return Err(EscrowError::QueuedActionNotFound);
+            }
+
+            Ok(())
+        }
+
+        // Lets a relayer pay the fees for a buyer acting on one of their orders, with an intent
+        // they signed off-chain
+        #[ink(message)]
+        pub fn execute_order_action_with_sig(
+            &mut self,
+            buyer: AccountId,
+            intent: OrderActionIntent,
+            signature: [u8; 65],
+        ) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            self.authorise_relayed_intent(
+                buyer,
+                intent.nonce,
+                intent.deadline,
+                self.order_action_intent_hash(intent),
+                signature,
+            )?;
+
+            self.nonces.insert(buyer, &(intent.nonce + 1));
+            match intent.action {
+                OrderAction::Cancel => self.cancel(buyer, intent.order_id),
+                OrderAction::SubmitPaymentVerification(payment_verification) => self
+                    .submit_payment_verification(
+                        buyer,
+                        intent.order_id,
+                        payment_verification,
+                        None,
+                    ),
+            }
+        }
+
+        // Anyone can close a listing past its expiry, returning the available amount to the vendor
+        #[ink(message)]
+        pub fn expire_listing(&mut self, id: u32) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            let listing_wrapped: Option<Listing> = self.listings.get(id);
+            if let Some(mut listing) = listing_wrapped {
+                if listing.closed {
+                    return Err(EscrowError::ListingClosed);
+                }
+                if !listing.is_expired(self.env().block_timestamp()) {
+                    return Err(EscrowError::ListingNotExpired);
+                }
+
+                let refunded: Balance = self.close(&mut listing)?;
+
+                // Emit event
+                self.env().emit_event(ListingExpired {
+                    id,
+                    vendor: listing.vendor,
+                    expires_at: listing.expires_at.unwrap(),
+                    refunded,
+                });
+            } else {
+                return Err(EscrowError::ListingNotFound);
+            }
+
+            Ok(())
+        }
+
+        // Anyone can move an order on once the party it's waiting for has run out of time:
+        // unpaid orders go back to the listing, payments the vendor hasn't reviewed are disputed
+        // so that an arbitrator checks them before anything is released, and disputes the
+        // arbitrator hasn't resolved are escalated to admins.
+        #[ink(message)]
+        pub fn expire_order(&mut self, order_id: u64) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
+            if let Some(mut order) = order_wrapped {
+                self.expire(&mut order)?;
+
+                // Emit event
+                self.env().emit_event(ExpireOrder {
+                    id: order.id,
+                    buyer: order.buyer,
+                    vendor: order.vendor,
+                    caller: Self::env().caller(),
+                    status: OrderStatus::try_from(order.status())?,
+                });
+                self.notify_subscribers(&order);
+            } else {
+                return Err(EscrowError::OrderNotFound);
+            }
+
+            Ok(())
+        }
+
+        // For keepers sweeping many orders. Orders that don't exist or haven't expired
+        // are skipped, and one OrderChanged event covers every order that moved on. Subscribers
+        // aren't called, so that a full batch stays within the block's gas limit.
+        #[ink(message)]
+        pub fn expire_orders(&mut self, order_ids: Vec<u64>) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            if order_ids.len() > MAX_BATCH_SIZE {
+                return Err(EscrowError::BatchTooLarge);
+            }
+
+            let mut orders: Vec<(u64, u8)> = vec![];
+            for order_id in order_ids {
+                if let Some(mut order) = self.orders.values.get(order_id) {
+                    if self.expire(&mut order).is_ok() {
+                        orders.push((order.id, order.status()))
+                    }
+                }
+            }
+
+            // Emit event
+            if !orders.is_empty() {
+                self.env().emit_event(OrderChanged {
+                    caller: Self::env().caller(),
+                    orders,
+                });
+            }
+
+            Ok(())
+        }
+
+        // Sends what's escrowed for MAX_BATCH_SIZE listings at a time to the export destination,
+        // the available and ordered amounts in each listing's asset, followed by the holds in
+        // AZERO with the last listings. Returns the listing id the next call carries on from,
+        // None once everything has been sent. See Upgrades in the README.
+        #[ink(message)]
+        pub fn export_funds(&mut self) -> Result<Option<u32>, EscrowError> {
+            self.guard_reentrancy()?;
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+            let destination: AccountId = self
+                .export_destination
+                .ok_or(EscrowError::ExportNotStarted)?;
+            if self.funds_exported {
+                return Err(EscrowError::FundsExported);
+            }
+
+            let listings_length: u32 = self.listings.length();
+            let end: u32 = listings_length.min(
+                self.export_funds_cursor
+                    .saturating_add(MAX_BATCH_SIZE as u32),
+            );
+            let mut amounts: Vec<(Option<AccountId>, Balance)> = vec![];
+            for id in self.export_funds_cursor..end {
+                if let Some(listing) = self.listings.get(id) {
+                    let exposure: ListingExposure =
+                        self.listings.exposures.get(id).unwrap_or_default();
+                    let amount: Balance = listing.available_amount.saturating_add(exposure.amount);
+                    match amounts
+                        .iter_mut()
+                        .find(|(asset, _)| *asset == listing.asset)
+                    {
+                        Some((_, total)) => *total = total.saturating_add(amount),
+                        None => amounts.push((listing.asset, amount)),
+                    }
+                }
+            }
+            let next_cursor: Option<u32> = if end < listings_length {
+                Some(end)
+            } else {
+                // Holds are always in AZERO, whatever the listing's asset
+                match amounts.iter_mut().find(|(asset, _)| asset.is_none()) {
+                    Some((_, total)) => *total = total.saturating_add(self.held_amount),
+                    None => amounts.push((None, self.held_amount)),
+                }
+                None
+            };
+            self.export_funds_cursor = end;
+            self.funds_exported = next_cursor.is_none();
+
+            for (asset, amount) in amounts {
+                if amount == 0 {
+                    continue;
+                }
+                self.transfer_asset(asset, destination, amount);
+
+                self.record_audit(AuditAction::ExportFunds(asset), None);
+
+                // Emit event
+                self.env().emit_event(ExportFunds {
+                    destination,
+                    asset,
+                    amount,
+                });
+            }
+
+            Ok(next_cursor)
+        }
+
+        // Recovery for orders that are stuck in a state neither party can resolve.
+        #[ink(message)]
+        pub fn force_cancel_order(
+            &mut self,
+            order_id: u64,
+            reason: CancelReason,
+        ) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            let caller: AccountId = Self::env().caller();
+            if !self.has_role(Role::Moderator, caller) {
+                return Err(EscrowError::Unauthorised);
+            }
+            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
+            if let Some(mut order) = order_wrapped {
+                order.state.set_cancel_reason(reason);
+                self.return_order_to_listing(&mut order, false)?;
+
+                self.record_audit(AuditAction::ForceCancelOrder(reason), Some(order.id));
+
+                // Emit event
+                self.env().emit_event(ForceCancelOrder {
+                    id: order.id,
+                    buyer: order.buyer,
+                    vendor: order.vendor,
+                    caller,
+                    reason,
+                });
+                self.notify_subscribers(&order);
+            } else {
+                return Err(EscrowError::OrderNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn grant_role(&mut self, role: Role, account: AccountId) -> Result<(), EscrowError> {
+            self.authorise_role_management(role)?;
+
+            self.roles.insert((role, account), &());
+
+            self.record_audit(AuditAction::GrantRole(role, account), None);
+
+            // Emit event
+            self.env().emit_event(GrantRole { account, role });
+
+            Ok(())
+        }
+
+        // Takes the batches export_state returns from the old instance, in order. Only
+        // callable until the instance is activated.
+        #[ink(message)]
+        pub fn import_state(&mut self, batch: StateBatch) -> Result<(), EscrowError> {
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+            if !self.importing {
+                return Err(EscrowError::StateImportLocked);
+            }
+            if batch.listings.len() > MAX_BATCH_SIZE
+                || batch.orders.len() > MAX_BATCH_SIZE
+                || batch.vendors.len() > MAX_BATCH_SIZE
+                || batch.disputes.len() > MAX_BATCH_SIZE
+                || batch.holds.len() > MAX_BATCH_SIZE
+            {
+                return Err(EscrowError::BatchTooLarge);
+            }
+
+            for (account, vendor) in &batch.vendors {
+                if !self.vendors.contains(account) {
+                    self.vendors_length += 1;
+                }
+                self.vendors.insert(account, vendor);
+            }
+            for listing in &batch.listings {
+                if !self.listings.import(listing) {
+                    return Err(EscrowError::InvalidImport);
+                }
+                self.escrowed_amount = self
+                    .escrowed_amount
+                    .checked_add(listing.available_amount)
+                    .ok_or(EscrowError::Overflow)?;
+            }
+            for order in &batch.orders {
+                if !self.listings.contains(order.listing_id) || !self.orders.import(order) {
+                    return Err(EscrowError::InvalidImport);
+                }
+                if order.is_active() {
+                    self.listings.expose(order.listing_id, order.amount)?;
+                    self.escrowed_amount = self
+                        .escrowed_amount
+                        .checked_add(order.amount)
+                        .ok_or(EscrowError::Overflow)?;
+                }
+            }
+            // Disputes are numbered in the order they're imported, as only the latest of each
+            // order is exported
+            for dispute in &batch.disputes {
+                if !self.orders.values.contains(dispute.order_id)
+                    || self.disputes.id_by_order.contains(dispute.order_id)
+                {
+                    return Err(EscrowError::InvalidImport);
+                }
+                self.disputes.create(&Dispute {
+                    id: self.disputes.length(),
+                    ..dispute.clone()
+                });
+            }
+            for (order_id, hold) in &batch.holds {
+                if !self
+                    .orders
+                    .values
+                    .get(*order_id)
+                    .is_some_and(|order| order.is_active())
+                    || self.holds.contains(order_id)
+                {
+                    return Err(EscrowError::InvalidImport);
+                }
+                self.holds.insert(order_id, hold);
+                self.held_amount = self
+                    .held_amount
+                    .checked_add(*hold)
+                    .ok_or(EscrowError::Overflow)?;
+            }
+
+            self.record_audit(AuditAction::ImportState, None);
+
+            // Emit event
+            self.env().emit_event(ImportState {
+                listings_length: batch.listings.len() as u32,
+                orders_length: batch.orders.len() as u32,
+                vendors_length: batch.vendors.len() as u32,
+            });
+
+            Ok(())
+        }
+
+        // Brings the values stored by an older version of the contract up to STORAGE_VERSION
+        // after a set_code_hash upgrade, one step at a time. Each call migrates up to
+        // MAX_BATCH_SIZE records of the current step and the next call carries on from there,
+        // so it's called until it raises StorageUpToDate, with the contract in maintenance mode
+        // in the meantime. A Migrate event is emitted as each step finishes, and the last one
+        // announces the EVENT_VERSION emitted from then on.
+        //
+        // Records that don't decode with the layout the step expects raise StorageDecodeFailed,
+        // reverting the call. Vendors aren't enumerable, so those stored under storage version
+        // 1 are migrated separately with migrate_vendors.
+        //
+        // The new code still has to decode the old deployment's root, so this only works
+        // between versions that leave the fields stored in the root as they are. See Upgrades
+        // in the README for moving to a new instance instead.
+        #[ink(message)]
+        pub fn migrate(&mut self) -> Result<(), EscrowError> {
+            if !self.has_role(Role::Admin, Self::env().caller()) {
+                return Err(EscrowError::Unauthorised);
+            }
+            let from_version: u16 = self.storage_version;
+            if from_version >= STORAGE_VERSION {
+                if self.event_version >= EVENT_VERSION {
+                    return Err(EscrowError::StorageUpToDate);
+                }
+            } else {
+                let cursor: u64 = self.migration_cursor.get_or_default();
+                let length: u64 = self.migration_length(from_version)?;
+                let end: u64 = cursor.saturating_add(MAX_BATCH_SIZE as u64).min(length);
+                for index in cursor..end {
+                    self.migrate_record(from_version, index)?;
+                }
+                if end < length {
+                    self.migration_cursor.set(&end);
+                    return Ok(());
+                }
+                self.migration_cursor.set(&0);
+                self.storage_version = from_version + 1;
+            }
+            if self.storage_version == STORAGE_VERSION {
+                self.event_version = EVENT_VERSION;
+            }
+
+            self.record_audit(AuditAction::Migrate, None);
+
+            // Emit event
+            self.env().emit_event(Migrate {
+                from_version,
+                to_version: self.storage_version,
+                event_version: self.event_version,
+            });
+
+            Ok(())
+        }
+
+        // Brings vendors stored under storage version 1 up to the current layout, given their
+        // accounts from the CreateVendor events. Vendors already stored that way are skipped.
+        #[ink(message)]
+        pub fn migrate_vendors(&mut self, accounts: Vec<AccountId>) -> Result<(), EscrowError> {
+            if !self.has_role(Role::Admin, Self::env().caller()) {
+                return Err(EscrowError::Unauthorised);
+            }
+            if accounts.len() > MAX_BATCH_SIZE {
+                return Err(EscrowError::BatchTooLarge);
+            }
+
+            let mut vendors_length: u32 = 0;
+            for account in accounts {
+                if let Some(vendor) = vendors::get_v1(&self.vendors, account)? {
+                    self.vendors.insert(account, &vendor);
+                    vendors_length += 1;
+                }
+            }
+
+            self.record_audit(AuditAction::Migrate, None);
+
+            // Emit event
+            self.env().emit_event(MigrateVendors { vendors_length });
+
+            Ok(())
+        }
+
+        // Makes each call in order as the caller. If any call fails, its error is returned and
+        // the whole transaction is reverted, so either every call goes through or none do.
+        #[ink(message)]
+        pub fn multicall(&mut self, calls: Vec<Call>) -> Result<(), EscrowError> {
+            if calls.len() > MAX_BATCH_SIZE {
+                return Err(EscrowError::BatchTooLarge);
+            }
+
+            for call in calls {
+                match call {
+                    Call::Close(id) => self.close_listing(id)?,
+                    Call::Pause(id, paused) => self.pause_listing(id, paused)?,
+                    Call::Update(id, price_per_unit, expires_at) => {
+                        self.update_listing(id, price_per_unit, expires_at)?
+                    }
+                    Call::Withdraw(id, amount) => self.withdraw_from_listing(id, amount)?,
+                }
+            }
+
+            Ok(())
+        }
+
+        // Vendors have to be onboarded this way when the marketplace is curated
+        #[ink(message)]
+        pub fn onboard_vendor(
+            &mut self,
+            account: AccountId,
+            ethereum_address: String,
+        ) -> Result<(), EscrowError> {
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            if self.is_paused(PAUSE_CREATE_VENDOR) {
+                return Err(EscrowError::Paused);
+            }
+            if !self.has_role(Role::Moderator, Self::env().caller()) {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            self.register_vendor(account, ethereum_address)?;
+            self.record_audit(AuditAction::OnboardVendor(account), None);
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn pause(&mut self, operations: u8) -> Result<(), EscrowError> {
+            let caller: AccountId = Self::env().caller();
+            if !self.has_role(Role::Pauser, caller) {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            self.paused_operations
+                .set(&(self.paused_operations.get_or_default() | operations));
+
+            self.record_audit(AuditAction::Pause(operations), None);
+
+            // Emit event
+            self.env().emit_event(Pause { caller, operations });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn pause_listing(&mut self, id: u32, paused: bool) -> Result<(), EscrowError> {
+            self.guard_export()?;
+            let listing_wrapped: Option<Listing> = self.listings.get(id);
+            if let Some(mut listing) = listing_wrapped {
+                if listing.vendor != Self::env().caller() {
+                    return Err(EscrowError::Unauthorised);
+                }
+
+                listing.paused = paused;
+                self.listings.update(&listing);
+                self.record_vendor_activity(listing.vendor);
+
+                // Emit event
+                self.env().emit_event(PauseListing {
+                    id,
+                    vendor: listing.vendor,
+                    paused,
+                });
+            } else {
+                return Err(EscrowError::ListingNotFound);
+            }
+
+            Ok(())
+        }
+
+        // Lets anyone carry out the time-based duties, so they don't depend on the team's
+        // keepers: orders that are due are expired, and listings that have expired or only
+        // have dust left (less than the minimum order amount) are closed. Listings with only dust
+        // left are kept open while they have orders open against them, as cancellations return
+        // the amount to them. The rest are skipped.
+        // Pays the caller upkeep_bounty per duty out of the accrued AZERO fees, as far as they go.
+        // Like expire_orders, subscribers aren't called for the orders that are expired.
+        #[ink(message)]
+        pub fn perform_upkeep(
+            &mut self,
+            order_ids: Vec<u64>,
+            listing_ids: Vec<u32>,
+        ) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            if order_ids.len() > MAX_BATCH_SIZE || listing_ids.len() > MAX_BATCH_SIZE {
+                return Err(EscrowError::BatchTooLarge);
+            }
+
+            let caller: AccountId = Self::env().caller();
+            let mut orders: Vec<(u64, u8)> = vec![];
+            for order_id in order_ids {
+                if let Some(mut order) = self.orders.values.get(order_id) {
+                    if self.expire(&mut order).is_ok() {
+                        orders.push((order.id, order.status()))
+                    }
+                }
+            }
+            let min_order_amount: Balance = self.limits.get_or_default().min_order_amount;
+            let mut listings_length: u32 = 0;
+            for id in listing_ids {
+                if let Some(mut listing) = self.listings.get(id) {
+                    let expired: bool = listing.is_expired(self.env().block_timestamp());
+                    let dust: bool = listing.available_amount > 0
+                        && listing.available_amount < min_order_amount
+                        && self
+                            .listings
+                            .exposures
+                            .get(id)
+                            .unwrap_or_default()
+                            .orders_length
+                            == 0;
+                    if listing.closed || !(expired || dust) {
+                        continue;
+                    }
+
+                    let refunded: Balance = self.close(&mut listing)?;
+                    listings_length += 1;
+
+                    // Emit event
+                    if expired {
+                        self.env().emit_event(ListingExpired {
+                            id,
+                            vendor: listing.vendor,
+                            expires_at: listing.expires_at.unwrap(),
+                            refunded,
+                        });
+                    } else {
+                        self.env().emit_event(ListingClosed {
+                            id,
+                            vendor: listing.vendor,
+                            drained: false,
+                            refunded,
+                        });
+                    }
+                }
+            }
+
+            let duties_length: u32 = orders.len() as u32 + listings_length;
+            let asset: Option<AccountId> = None;
+            let accrued_fees: Balance = self.accrued_fees.get(asset).unwrap_or(0);
+            let bounty: Balance = self
+                .upkeep_bounty
+                .get_or_default()
+                .saturating_mul(duties_length.into())
+                .min(accrued_fees);
+            if bounty > 0 {
+                self.accrued_fees.insert(asset, &(accrued_fees - bounty));
+                self.transfer_asset(asset, caller, bounty);
+            }
+
+            // Emit event
+            if !orders.is_empty() {
+                self.env().emit_event(OrderChanged { caller, orders });
+            }
+            if duties_length > 0 {
+                self.env().emit_event(PerformUpkeep {
+                    caller,
+                    duties_length,
+                    bounty,
+                });
+            }
+
+            Ok(())
+        }
+
+        // Fee, treasury and code changes only take effect once the timelock
+        // delay has passed, giving users notice to exit beforehand.
+        #[ink(message)]
+        pub fn queue_action(&mut self, action: AdminAction) -> Result<(), EscrowError> {
+            self.authorise_queued_action(&action)?;
+            match &action {
+                AdminAction::FeeBps(fee_bps) if *fee_bps > MAX_FEE_BPS => {
+                    return Err(EscrowError::InvalidFee);
+                }
+                AdminAction::Signers(signers, threshold) => {
+                    let mut unique_signers: Vec<AccountId> = signers.clone();
+                    unique_signers.sort();
+                    unique_signers.dedup();
+                    if unique_signers.len() != signers.len()
+                        || usize::from(*threshold) > signers.len()
+                        || (*threshold == 0 && !signers.is_empty())
+                    {
+                        return Err(EscrowError::InvalidSigners);
+                    }
+                }
+                _ => {}
+            }
+
+            let caller: AccountId = Self::env().caller();
+            // Queueing counts as the caller's approval when they are a signer
+            let approvals: Vec<AccountId> = if self.signers.contains(&caller) {
+                vec![caller]
+            } else {
+                vec![]
+            };
+            let executable_at: Timestamp = self
+                .env()
+                .block_timestamp()
+                .checked_add(self.timelock_delay)
+                .ok_or(EscrowError::Overflow)?;
+            let queued_action: QueuedAction = QueuedAction {
+                id: self.queued_actions_length,
+                action,
+                executable_at,
+                approvals,
+            };
+            self.queued_actions.insert(queued_action.id, &queued_action);
+            self.queued_actions_length += 1;
+
+            self.record_audit(AuditAction::QueueAction, Some(queued_action.id.into()));
+
+            // Emit event
+            self.env().emit_event(QueueAction {
+                id: queued_action.id,
+                action: queued_action.action,
+                executable_at: queued_action.executable_at,
+            });
+
+            Ok(())
+        }
+
+        // Each party to a finalised order can rate the other once, from 1 to 5, optionally
+        // with the hash of a text review they've put on IPFS. Calling again within
+        // RATING_AMEND_WINDOW replaces the rating and review.
+        #[ink(message)]
+        pub fn rate_order(
+            &mut self,
+            order_id: u64,
+            rating: u8,
+            review: Option<[u8; 32]>,
+        ) -> Result<(), EscrowError> {
+            if !(1..=5).contains(&rating) {
+                return Err(EscrowError::InvalidRating);
+            }
+            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
+            if let Some(mut order) = order_wrapped {
+                if order.status() != OrderStatus::Finalised as u8 {
+                    return Err(EscrowError::OrderNotFinalised);
+                }
+                let caller: AccountId = Self::env().caller();
+                let (rated, previous_rating): (AccountId, Option<u8>) = if caller == order.buyer {
+                    (order.vendor, order.state.rating_by_buyer())
+                } else if caller == order.vendor {
+                    (order.buyer, order.state.rating_by_vendor())
+                } else {
+                    return Err(EscrowError::Unauthorised);
+                };
+                let rated_at: Timestamp = if previous_rating.is_some() {
+                    let rated_at: Timestamp =
+                        self.rated_at.get((order.id, caller)).unwrap_or_default();
+                    if self.env().block_timestamp() > rated_at.saturating_add(RATING_AMEND_WINDOW) {
+                        return Err(EscrowError::AlreadyRated);
+                    }
+                    rated_at
+                } else {
+                    self.env().block_timestamp()
+                };
+                if caller == order.buyer {
+                    order.state.set_rating_by_buyer(rating);
+                } else {
+                    order.state.set_rating_by_vendor(rating);
+                }
+
+                self.orders.update(&order);
+                let as_vendor: bool = rated == order.vendor;
+                // Amendments go to the epoch the rating was first given in
+                self.record_reputation_in(rated, rated_at / REPUTATION_EPOCH, |reputation| {
+                    let party: &mut PartyReputation = if as_vendor {
+                        &mut reputation.as_vendor
+                    } else {
+                        &mut reputation.as_buyer
+                    };
+                    match previous_rating {
+                        Some(previous_rating) => party.amend_rating(previous_rating, rating),
+                        None => party.add_rating(rating),
+                    }
+                });
+                self.rated_at.insert((order.id, caller), &rated_at);
+                if let Some(review) = review {
+                    self.reviews.insert((order.id, caller), &review);
+                } else {
+                    self.reviews.remove((order.id, caller));
+                }
+
+                // Emit event
+                self.env().emit_event(RateOrder {
+                    id: order.id,
+                    caller,
+                    rated,
+                    rating,
+                    review,
+                });
+            } else {
+                return Err(EscrowError::OrderNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn remove_from_blacklist(&mut self, account: AccountId) -> Result<(), EscrowError> {
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            self.blacklist.remove(account);
+
+            self.record_audit(AuditAction::RemoveFromBlacklist(account), None);
+
+            // Emit event
+            self.env().emit_event(RemoveFromBlacklist { account });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn remove_payment_processor(
+            &mut self,
+            public_key: [u8; 33],
+        ) -> Result<(), EscrowError> {
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            self.payment_processors.remove(public_key);
+
+            self.record_audit(AuditAction::RemovePaymentProcessor(public_key), None);
+
+            // Emit event
+            self.env().emit_event(RemovePaymentProcessor { public_key });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn remove_subscriber(&mut self, subscriber: AccountId) -> Result<(), EscrowError> {
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            let mut subscribers: Vec<AccountId> = self.subscribers.get_or_default();
+            subscribers.retain(|account| *account != subscriber);
+            self.subscribers.set(&subscribers);
+
+            self.record_audit(AuditAction::RemoveSubscriber(subscriber), None);
+
+            // Emit event
+            self.env().emit_event(RemoveSubscriber { subscriber });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_badge_collection(
+            &mut self,
+            badge_collection: Option<AccountId>,
+        ) -> Result<(), EscrowError> {
+            self.authorise_parameter_change()?;
+
+            self.badge_collection = badge_collection;
+
+            self.record_audit(AuditAction::SetBadgeCollection(badge_collection), None);
+
+            // Emit event
+            self.env()
+                .emit_event(SetBadgeCollection { badge_collection });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_curated(&mut self, curated: bool) -> Result<(), EscrowError> {
+            self.authorise_parameter_change()?;
+
+            self.curated = curated;
+
+            self.record_audit(AuditAction::SetCurated(curated), None);
+
+            // Emit event
+            self.env().emit_event(SetCurated { curated });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_dispute_weights(
+            &mut self,
+            dispute_weights: DisputeWeights,
+        ) -> Result<(), EscrowError> {
+            self.authorise_parameter_change()?;
+
+            self.dispute_weights.set(&dispute_weights);
+
+            self.record_audit(AuditAction::SetDisputeWeights, None);
+
+            // Emit event
+            self.env().emit_event(SetDisputeWeights { dispute_weights });
+
+            Ok(())
+        }
+
+        // Handing parameters over to a governor is one way: only the governor can replace
+        // itself or hand them back to the owner by clearing it.
+        // Once set, the governor also has to queue and execute fee and treasury changes.
+        #[ink(message)]
+        pub fn set_governor(&mut self, governor: Option<AccountId>) -> Result<(), EscrowError> {
+            self.authorise_parameter_change()?;
+
+            self.governor = governor;
+
+            self.record_audit(AuditAction::SetGovernor, None);
+
+            // Emit event
+            self.env().emit_event(SetGovernor { governor });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_limits(&mut self, limits: Limits) -> Result<(), EscrowError> {
+            self.authorise_parameter_change()?;
+            Self::validate_limits(&limits, self.timeouts.get_or_default().payment_window)?;
+
+            self.limits.set(&limits);
+
+            self.record_audit(AuditAction::SetLimits, None);
+
+            // Emit event
+            self.env().emit_event(SetLimits { limits });
+
+            Ok(())
+        }
+
+        // Vendors can have a listing's idle AZERO bonded into the yield pool so that it earns
+        // rewards while listed. It's unbonded as orders and withdrawals need it.
+        #[ink(message)]
+        pub fn set_listing_yield(&mut self, id: u32, enabled: bool) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            self.guard_export()?;
+            let listing_wrapped: Option<Listing> = self.listings.get(id);
+            if let Some(listing) = listing_wrapped {
+                if listing.vendor != Self::env().caller() {
+                    return Err(EscrowError::Unauthorised);
+                }
+                if listing.asset.is_some() {
+                    return Err(EscrowError::AssetMismatch);
+                }
+
+                if enabled {
+                    if listing.closed {
+                        return Err(EscrowError::ListingClosed);
+                    }
+                    if self.yield_pool.is_none() {
+                        return Err(EscrowError::YieldPoolNotSet);
+                    }
+                    if !self.bonded_amounts.contains(id) {
+                        self.bonded_amounts.insert(id, &0);
+                    }
+                    self.bond_listing(id, listing.vendor, listing.available_amount)?;
+                } else if self.bonded_amounts.contains(id) {
+                    self.bond_listing(id, listing.vendor, 0)?;
+                    self.bonded_amounts.remove(id);
+                }
+                self.record_vendor_activity(listing.vendor);
+
+                // Emit event
+                self.env().emit_event(SetListingYield {
+                    id,
+                    vendor: listing.vendor,
+                    enabled,
+                });
+            } else {
+                return Err(EscrowError::ListingNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_maintenance_mode(&mut self, enabled: bool) -> Result<(), EscrowError> {
+            let caller: AccountId = Self::env().caller();
+            if !self.has_role(Role::Pauser, caller) {
+                return Err(EscrowError::Unauthorised);
+            }
+            self.guard_export()?;
+
+            self.maintenance_mode = enabled;
+
+            self.record_audit(AuditAction::SetMaintenanceMode(enabled), None);
+
+            // Emit event
+            self.env()
+                .emit_event(SetMaintenanceMode { caller, enabled });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_max_page_size(&mut self, max_page_size: u16) -> Result<(), EscrowError> {
+            self.authorise_parameter_change()?;
+            if max_page_size == 0 || max_page_size > MAX_PAGE_SIZE {
+                return Err(EscrowError::InvalidLimits);
+            }
+
+            self.max_page_size.set(&max_page_size);
+
+            self.record_audit(AuditAction::SetMaxPageSize(max_page_size), None);
+
+            // Emit event
+            self.env().emit_event(SetMaxPageSize { max_page_size });
+
+            Ok(())
+        }
+
+        // Tiers need strictly increasing completed orders and increasing maximums, so that
+        // completing more orders never lowers the size a buyer can place
+        #[ink(message)]
+        pub fn set_order_size_schedule(
+            &mut self,
+            order_size_schedule: Vec<OrderSizeTier>,
+        ) -> Result<(), EscrowError> {
+            self.authorise_parameter_change()?;
+            if order_size_schedule.len() > MAX_BATCH_SIZE {
+                return Err(EscrowError::BatchTooLarge);
+            }
+            if order_size_schedule.windows(2).any(|tiers| {
+                tiers[0].completed_orders_length >= tiers[1].completed_orders_length
+                    || tiers[0].max_order_amount > tiers[1].max_order_amount
+            }) {
+                return Err(EscrowError::InvalidLimits);
+            }
+
+            self.order_size_schedule.set(&order_size_schedule);
+
+            self.record_audit(AuditAction::SetOrderSizeSchedule, None);
+
+            // Emit event
+            self.env().emit_event(SetOrderSizeSchedule {
+                order_size_schedule,
+            });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_timeouts(&mut self, timeouts: Timeouts) -> Result<(), EscrowError> {
+            self.authorise_parameter_change()?;
+            let limits: Limits = self.limits.get_or_default();
+            if timeouts.payment_window < limits.min_payment_window
+                || timeouts.payment_window > limits.max_payment_window
+                || timeouts.verification_window == 0
+                || timeouts.dispute_window == 0
+            {
+                return Err(EscrowError::InvalidTimeouts);
+            }
+
+            self.timeouts.set(&timeouts);
+
+            self.record_audit(AuditAction::SetTimeouts, None);
+
+            // Emit event
+            self.env().emit_event(SetTimeouts { timeouts });
+
+            Ok(())
+        }
+
+        // Buyers approve the contracts that can open managed trades for them. Revoking a manager
+        // also stops it acting on the trades it has already opened, which are left to the buyer.
+        #[ink(message)]
+        pub fn set_trade_manager(
+            &mut self,
+            manager: AccountId,
+            approved: bool,
+        ) -> Result<(), EscrowError> {
+            let buyer: AccountId = Self::env().caller();
+            if approved {
+                self.trade_managers.insert((buyer, manager), &());
+            } else {
+                self.trade_managers.remove((buyer, manager));
+            }
+
+            // Emit event
+            self.env().emit_event(SetTradeManager {
+                buyer,
+                manager,
+                approved,
+            });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_upkeep_bounty(&mut self, upkeep_bounty: Balance) -> Result<(), EscrowError> {
+            self.authorise_parameter_change()?;
+
+            self.upkeep_bounty.set(&upkeep_bounty);
+
+            self.record_audit(AuditAction::SetUpkeepBounty(upkeep_bounty), None);
+
+            // Emit event
+            self.env().emit_event(SetUpkeepBounty { upkeep_bounty });
+
+            Ok(())
+        }
+
+        // Can only be changed while nothing is bonded, so that bonded funds can always be
+        // unbonded from the pool they went into
+        #[ink(message)]
+        pub fn set_yield_pool(&mut self, yield_pool: Option<AccountId>) -> Result<(), EscrowError> {
+            self.authorise_parameter_change()?;
+            if self.bonded_amount > 0 {
+                return Err(EscrowError::YieldPoolInUse);
+            }
+
+            self.yield_pool = yield_pool;
+
+            self.record_audit(AuditAction::SetYieldPool(yield_pool), None);
+
+            // Emit event
+            self.env().emit_event(SetYieldPool { yield_pool });
+
+            Ok(())
+        }
+
+        // The owner arbitrates orders, so ownership can only be given up once
+        // no funds or open orders (including disputes) remain in the contract.
+        #[ink(message)]
+        pub fn renounce_ownership(&mut self) -> Result<(), EscrowError> {
+            let caller: AccountId = Self::env().caller();
+            if caller != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+            if !self.is_empty() {
+                return Err(EscrowError::EscrowNotEmpty);
+            }
+
+            self._init_with_owner(AccountId::from([0; 32]));
+            self.pending_owner = None;
+
+            self.record_audit(AuditAction::RenounceOwnership, None);
+
+            // Emit event
+            self.env().emit_event(RenounceOwnership {
+                previous_owner: caller,
+            });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn revoke_role(&mut self, role: Role, account: AccountId) -> Result<(), EscrowError> {
+            self.authorise_role_management(role)?;
+
+            self.roles.remove((role, account));
+
+            self.record_audit(AuditAction::RevokeRole(role, account), None);
+
+            // Emit event
+            self.env().emit_event(RevokeRole { account, role });
+
+            Ok(())
+        }
+
+        // Removes the contract once nothing is held for users, sending the
+        // remaining balance (storage deposit, dust) to the treasury.
+        #[ink(message)]
+        pub fn terminate(&mut self) -> Result<(), EscrowError> {
+            if Self::env().caller() != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+            if !self.is_empty() {
+                return Err(EscrowError::EscrowNotEmpty);
+            }
+
+            self.env()
+                .terminate_contract(self.treasury.get_or_default())
+        }
+
+        // Ownership only moves once the pending owner calls accept_ownership,
+        // so a mistyped address can be replaced by calling this again.
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), EscrowError> {
+            let caller: AccountId = Self::env().caller();
+            if caller != self.ownable.owner() {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            self.pending_owner = Some(new_owner);
+
+            self.record_audit(AuditAction::TransferOwnership(new_owner), None);
+
+            // Emit event
+            self.env().emit_event(TransferOwnership {
+                owner: caller,
+                pending_owner: new_owner,
+            });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn unpause(&mut self, operations: u8) -> Result<(), EscrowError> {
+            let caller: AccountId = Self::env().caller();
+            if !self.has_role(Role::Pauser, caller) {
+                return Err(EscrowError::Unauthorised);
+            }
+
+            self.paused_operations
+                .set(&(self.paused_operations.get_or_default() & !operations));
+
+            self.record_audit(AuditAction::Unpause(operations), None);
+
+            // Emit event
+            self.env().emit_event(Unpause { caller, operations });
+
+            Ok(())
+        }
+
+        // Only affects orders created afterwards
+        #[ink(message)]
+        pub fn update_listing(
+            &mut self,
+            id: u32,
+            price_per_unit: Balance,
+            expires_at: Option<Timestamp>,
+        ) -> Result<(), EscrowError> {
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            let listing_wrapped: Option<Listing> = self.listings.get(id);
+            if let Some(mut listing) = listing_wrapped {
+                if listing.vendor != Self::env().caller() {
+                    return Err(EscrowError::Unauthorised);
+                }
+
+                if listing.closed {
+                    return Err(EscrowError::ListingClosed);
+                }
+
+                listing.price_per_unit = price_per_unit;
+                listing.expires_at = expires_at;
+                self.listings.update(&listing);
+                self.record_vendor_activity(listing.vendor);
+
+                // Emit event
+                self.env().emit_event(UpdateListing {
+                    id,
+                    vendor: listing.vendor,
+                    price_per_unit,
+                    expires_at,
+                });
+            } else {
+                return Err(EscrowError::ListingNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn verify_vendor(
+            &mut self,
+            account: AccountId,
+            verified: bool,
+            tier: u8,
+        ) -> Result<(), EscrowError> {
+            if !self.has_role(Role::Moderator, Self::env().caller()) {
+                return Err(EscrowError::Unauthorised);
+            }
+            let vendor_wrapped: Option<Vendor> = self.vendors.get(account);
+            if let Some(mut vendor) = vendor_wrapped {
+                vendor.verified = verified;
+                vendor.tier = tier;
+                self.vendors.insert(account, &vendor);
+
+                self.record_audit(AuditAction::VerifyVendor(account), None);
+
+                // Emit event
+                self.env().emit_event(VerifyVendor {
+                    account,
+                    verified,
+                    tier,
+                });
+            } else {
+                return Err(EscrowError::VendorNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn update_vendor(&mut self, ethereum_address: String) -> Result<(), EscrowError> {
+            if ethereum_address.len() > MAX_ETHEREUM_ADDRESS_LENGTH {
+                return Err(EscrowError::EthereumAddressTooLong);
+            }
+            let caller: AccountId = Self::env().caller();
+            let vendor_wrapped: Option<Vendor> = self.vendors.get(caller);
+            if let Some(mut vendor) = vendor_wrapped {
+                vendor.ethereum_address = ethereum_address.clone();
+                vendor.last_active_at = self.env().block_timestamp();
+                self.vendors.insert(caller, &vendor);
+
+                // Emit event
+                self.env().emit_event(UpdateVendor {
+                    account: caller,
+                    ethereum_address,
+                });
+            } else {
+                return Err(EscrowError::VendorNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn withdraw_from_listing(
+            &mut self,
+            id: u32,
+            amount: Balance,
+        ) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            self.guard_export()?;
+            if self.is_paused(PAUSE_WITHDRAW) {
+                return Err(EscrowError::Paused);
+            }
+            let listing_wrapped: Option<Listing> = self.listings.get(id);
+            if let Some(mut listing) = listing_wrapped {
+                if listing.vendor != Self::env().caller() {
+                    return Err(EscrowError::Unauthorised);
+                }
+                if amount > listing.available_amount {
+                    return Err(EscrowError::InsufficientFunds(listing.available_amount));
+                };
+
+                listing.available_amount = listing
+                    .available_amount
+                    .checked_sub(amount)
+                    .ok_or(EscrowError::Underflow)?;
+                self.escrowed_amount = self
+                    .escrowed_amount
+                    .checked_sub(amount)
+                    .ok_or(EscrowError::Underflow)?;
+                self.listings
+                    .update_available_amount(listing.id, listing.available_amount);
+                self.sync_yield(listing.id, listing.vendor)?;
+                self.record_vendor_activity(listing.vendor);
+                self.transfer_asset(listing.asset, listing.vendor, amount);
+
+                // Emit event
+                self.env().emit_event(WithdrawFromListing {
+                    id,
+                    vendor: listing.vendor,
+                    amount,
+                    new_balance: listing.available_amount,
+                });
+                if listing.available_amount == 0 && amount > 0 && !listing.closed {
+                    self.env().emit_event(ListingClosed {
+                        id,
+                        vendor: listing.vendor,
+                        drained: true,
+                        refunded: 0,
+                    });
+                }
+            } else {
+                return Err(EscrowError::ListingNotFound);
+            }
+
+            Ok(())
+        }
+
+        // === PRIVATE ===
+        // Records the dispute of an order that has just been disputed. Disputes raised by
+        // expire_order are raised by the contract itself.
+        fn raise_dispute(&mut self, order: &Order, raised_by: AccountId) {
+            self.disputes.create(&Dispute {
+                id: self.disputes.length(),
+                order_id: order.id,
+                raised_by,
+                raised_at: order.status_updated_at,
+                resolved_at: None,
+                arbitrator: None,
+                escalated: false,
+            });
+        }
+
+        // Dispute record of an order that's currently disputed
+        // Leaves a dispute to admins over the assigned arbitrator's head, restarting the
+        // dispute window
+        fn escalate(&mut self, order: &mut Order, caller: AccountId) -> Result<(), EscrowError> {
+            let mut dispute: Dispute = self.open_dispute(order.id);
+            if dispute.escalated {
+                return Err(EscrowError::DisputeAlreadyEscalated);
+            }
+
+            dispute.escalated = true;
+            self.disputes.update(&dispute);
+            order.status_updated_at = self.env().block_timestamp();
+            self.orders.update(order);
+
+            // Emit event
+            self.env().emit_event(DisputeEscalated {
+                order_id: order.id,
+                caller,
+                arbitrator: dispute.arbitrator,
+                deadline: order.status_updated_at + self.timeouts.get_or_default().dispute_window,
+            });
+
+            Ok(())
+        }
+
+        fn open_dispute(&self, order_id: u64) -> Dispute {
+            let id: u64 = self.disputes.id_by_order.get(order_id).unwrap();
+            self.disputes.values.get(id).unwrap()
+        }
+
+        fn listing_summaries(&self, listings: &[Listing]) -> Vec<ListingSummary> {
+            let now: Timestamp = self.env().block_timestamp();
+            listings
+                .iter()
+                .map(|listing| listing.summary(now))
+                .collect()
+        }
+
+        // Limits have to be consistent with each other and with the current payment window
+        fn validate_limits(limits: &Limits, payment_window: Timestamp) -> Result<(), EscrowError> {
+            if limits.min_order_amount > limits.max_order_amount
+                || limits.min_payment_window > limits.max_payment_window
+                || payment_window < limits.min_payment_window
+                || payment_window > limits.max_payment_window
+            {
+                return Err(EscrowError::InvalidLimits);
+            }
+
+            Ok(())
+        }
+
+        // Maximum of the highest tier the buyer has reached. Buyers below every tier, or when
+        // there's no schedule, are only held to limits.
+        fn order_size_limit(&self, buyer: AccountId) -> Balance {
+            let completed_orders_length: u64 = self.buyer_summary(buyer).completed_orders_length;
+            self.order_size_schedule
+                .get_or_default()
+                .iter()
+                .rev()
+                .find(|tier| tier.completed_orders_length <= completed_orders_length)
+                .map_or(Balance::MAX, |tier| tier.max_order_amount)
+        }
+
+        // Number of items an index query returns when asked for size
+        fn page_size(&self, size: u16) -> u16 {
+            size.min(self.max_page_size.get().unwrap_or(MAX_PAGE_SIZE))
+        }
+
+        fn cancel(&mut self, caller: AccountId, order_id: u64) -> Result<(), EscrowError> {
+            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
+            if let Some(mut order) = order_wrapped {
+                let for_buyer: bool = order.buyer == caller || self.manages_order(&order, caller);
+                if !for_buyer && !self.has_role(Role::Arbitrator, caller) {
+                    return Err(EscrowError::Unauthorised);
+                }
+
+                self.return_order_to_listing(&mut order, false)?;
+
+                // Record cancellations made by an arbitrator rather than the buyer
+                if !for_buyer {
+                    self.record_audit(AuditAction::CancelOrder, Some(order.id));
+                }
+
+                // Emit event
+                self.env().emit_event(CancelOrder {
+                    id: order.id,
+                    buyer: order.buyer,
+                    vendor: order.vendor,
+                    caller,
+                });
+                self.notify_subscribers(&order);
+            } else {
+                return Err(EscrowError::OrderNotFound);
+            }
+
+            Ok(())
+        }
+
+        // Bonds or unbonds so that the listing has the target amount in the yield pool, with
+        // rewards going to the vendor. Bonding is best effort, the funds just stay liquid if it
+        // fails, but unbonding has to get the funds back.
+        fn bond_listing(
+            &mut self,
+            id: u32,
+            vendor: AccountId,
+            target: Balance,
+        ) -> Result<(), EscrowError> {
+            let bonded: Balance = self.bonded_amounts.get(id).unwrap_or(0);
+            let yield_pool: AccountId = match self.yield_pool {
+                Some(yield_pool) if target != bonded => yield_pool,
+                _ => return Ok(()),
+            };
+            self.reentrancy_lock.set(&true);
+            if target > bonded {
+                let amount: Balance = target - bonded;
+                let result = build_call::<DefaultEnvironment>()
+                    .call(yield_pool)
+                    .transferred_value(amount)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(YIELD_POOL_BOND_SELECTOR))
+                            .push_arg(vendor),
+                    )
+                    .returns::<()>()
+                    .try_invoke();
+                if matches!(result, Ok(Ok(()))) {
+                    self.bonded_amounts.insert(id, &target);
+                    self.bonded_amount = self.bonded_amount.saturating_add(amount);
+                }
+            } else {
+                let amount: Balance = bonded - target;
+                let expected_balance: Balance = self
+                    .env()
+                    .balance()
+                    .checked_add(amount)
+                    .ok_or(EscrowError::YieldPoolCallFailed)?;
+                let result = build_call::<DefaultEnvironment>()
+                    .call(yield_pool)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(YIELD_POOL_UNBOND_SELECTOR))
+                            .push_arg(vendor)
+                            .push_arg(amount),
+                    )
+                    .returns::<()>()
+                    .try_invoke();
+                if !matches!(result, Ok(Ok(()))) || self.env().balance() < expected_balance {
+                    self.reentrancy_lock.set(&false);
+                    return Err(EscrowError::YieldPoolCallFailed);
+                }
+                self.bonded_amounts.insert(id, &target);
+                self.bonded_amount = self.bonded_amount.saturating_sub(amount);
+            }
+            self.reentrancy_lock.set(&false);
+            Ok(())
+        }
+
+        // Bonds an opted-in listing's whole available amount into the yield pool
+        fn sync_yield(&mut self, id: u32, vendor: AccountId) -> Result<(), EscrowError> {
+            if !self.bonded_amounts.contains(id) {
+                return Ok(());
+            }
+            let available_amount: Balance = self.listings.available_amount(id).unwrap_or(0);
+            self.bond_listing(id, vendor, available_amount)
+        }
+
+        // Returns the amount refunded to the vendor
+        fn close(&mut self, listing: &mut Listing) -> Result<Balance, EscrowError> {
+            self.guard_export()?;
+            let refunded: Balance = listing.available_amount;
+            self.escrowed_amount = self
+                .escrowed_amount
+                .checked_sub(refunded)
+                .ok_or(EscrowError::Underflow)?;
+            listing.closed = true;
+            listing.available_amount = 0;
+            self.listings.update(listing);
+            self.sync_yield(listing.id, listing.vendor)?;
+            if refunded > 0 {
+                self.transfer_asset(listing.asset, listing.vendor, refunded);
+            }
+            Ok(refunded)
+        }
+
+        // No funds are held for users and there are no open orders or disputes
+        fn is_empty(&self) -> bool {
+            self.escrowed_amount == 0 && self.held_amount == 0 && self.orders.active_length == 0
+        }
+
+        fn is_paused(&self, operation: u8) -> bool {
+            self.paused_operations.get_or_default() & operation != 0
+        }
+
+        // Mints a badge when the account's finalised orders reach a milestone. Its id is the
+        // encoded (account, milestone) so that other dapps can look it up. A failed mint
+        // doesn't hold up the order.
+        fn mint_milestone_badge(&mut self, account: AccountId) {
+            if let Some(badge_collection) = self.badge_collection {
+                let reputation: Reputation = self.reputations.get(account).unwrap_or_default();
+                let milestone: u64 = reputation.as_buyer.finalised_orders_length
+                    + reputation.as_vendor.finalised_orders_length;
+                if BADGE_MILESTONES.contains(&milestone) {
+                    let id: Id = Id::Bytes(scale::Encode::encode(&(account, milestone)));
+                    self.reentrancy_lock.set(&true);
+                    let minted: bool =
+                        PSP34MintableRef::mint(&badge_collection, account, id).is_ok();
+                    self.reentrancy_lock.set(&false);
+                    if minted {
+                        // Emit event
+                        self.env().emit_event(MintBadge { account, milestone });
+                    }
+                }
+            }
+        }
+
+        // Best effort: each subscriber gets SUBSCRIBER_GAS_LIMIT and its failures are ignored,
+        // so a broken subscriber can't hold up orders. The reentrancy lock stays on meanwhile.
+        fn notify_subscribers(&mut self, order: &Order) {
+            let subscribers: Vec<AccountId> = self.subscribers.get_or_default();
+            if subscribers.is_empty() {
+                return;
+            }
+            self.reentrancy_lock.set(&true);
+            for subscriber in subscribers {
+                let _ = build_call::<DefaultEnvironment>()
+                    .call(subscriber)
+                    .gas_limit(SUBSCRIBER_GAS_LIMIT)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(ON_ORDER_EVENT_SELECTOR))
+                            .push_arg(order.id)
+                            .push_arg(order.status()),
+                    )
+                    .returns::<()>()
+                    .try_invoke();
+            }
+            self.reentrancy_lock.set(&false);
+        }
+
+        // Best effort like notify_subscribers, so a broken manager can't hold up settlement
+        fn notify_trade_manager(&mut self, order: &Order) {
+            if let Some(manager) = self.order_managers.get(order.id) {
+                self.reentrancy_lock.set(&true);
+                let _ = build_call::<DefaultEnvironment>()
+                    .call(manager)
+                    .gas_limit(TRADE_MANAGER_GAS_LIMIT)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(ON_ORDER_SETTLED_SELECTOR))
+                            .push_arg(order.id)
+                            .push_arg(order.status()),
+                    )
+                    .returns::<()>()
+                    .try_invoke();
+                self.reentrancy_lock.set(&false);
+            }
+        }
+
+        // Whether the account opened the order as a managed trade and the buyer still approves it
+        fn manages_order(&self, order: &Order, account: AccountId) -> bool {
+            self.order_managers.get(order.id) == Some(account)
+                && self.is_trade_manager(order.buyer, account)
+        }
+
+        // Number of records the step from version to version + 1 goes through
+        fn migration_length(&self, version: u16) -> Result<u64, EscrowError> {
+            match version {
+                // Deployments from before versioning have a different root, see migrate
+                0 => Err(EscrowError::StorageVersionUnsupported),
+                1 => Ok(self.orders.length() + u64::from(self.listings.length())),
+                2..=4 | 6 => Ok(self.orders.length()),
+                5 => Ok(self.listings.length().into()),
+                // There's no step from versions this code doesn't know, so the stored version
+                // itself can't be right
+                _ => Err(EscrowError::StorageDecodeFailed),
+            }
+        }
+
+        // Migrates the record at index of the step from version to version + 1
+        fn migrate_record(&mut self, version: u16, index: u64) -> Result<(), EscrowError> {
+            match version {
+                // Payment verifications are replaced by their hashes, after the orders, and the
+                // listings, are brought up to the last layout stored under this version
+                1 => {
+                    if index < self.orders.length() {
+                        let now: Timestamp = self.env().block_timestamp();
+                        if let Some(legacy) = self.orders.get_v1(index, now)? {
+                            self.orders
+                                .values
+                                .update_as(index, &UnpackedOrder::from(legacy));
+                        }
+                    } else {
+                        let id: u32 = (index - self.orders.length()) as u32;
+                        if let Some(listing) = self.listings.get_v1(id)? {
+                            self.listings.values.update(id, &listing);
+                        }
+                    }
+                }
+                // Buyer and vendor order ids move from one list per account into chunks
+                2 => {
+                    if let Some(order) = self.orders.values.get_as::<UnpackedOrder>(index)? {
+                        self.orders.ids_by_buyer.push(order.buyer, index);
+                        self.orders.ids_by_vendor.push(order.vendor, index);
+                    }
+                }
+                // Order status is packed into OrderState along with the disputed flag
+                3 => {
+                    if let Some(unpacked) = self.orders.values.get_as::<UnpackedOrder>(index)? {
+                        let mut order: Order = Order::from(unpacked);
+                        if self.disputes.id_by_order.contains(index) {
+                            order.state.set_disputed();
+                        }
+                        self.orders.values.update(index, &order);
+                    }
+                }
+                // Order ids by status move from one list per status into chunks
+                4 => {
+                    if let Some(order) = self.orders.values.get(index) {
+                        self.orders.ids_by_status.insert(order.status(), index);
+                    }
+                }
+                // Listing ids by asset and currency move from one list per pair into chunks
+                5 => {
+                    if let Some(listing) = self.listings.values.get(index as u32) {
+                        self.listings
+                            .ids_by_pair
+                            .push((listing.asset, listing.currency), index);
+                    }
+                }
+                // Ids of orders awaiting payment verification move from one list per vendor
+                // into chunks
+                6 => {
+                    if let Some(order) = self.orders.values.get(index) {
+                        if order.status() == OrderStatus::PendingVerification as u8 {
+                            self.orders
+                                .pending_verification_ids_by_vendor
+                                .insert(order.vendor, index);
+                        }
+                    }
+                }
+                _ => return Err(EscrowError::StorageDecodeFailed),
+            }
+
+            Ok(())
+        }
+
+        // Nothing that changes listings or orders, or pays funds out, once an export has started
+        fn guard_export(&self) -> Result<(), EscrowError> {
+            if self.export_destination.is_some() {
+                return Err(EscrowError::ExportStarted);
+            }
+
+            Ok(())
+        }
+
+        // Messages that move funds can't be entered while a token call is in progress
+        fn guard_reentrancy(&self) -> Result<(), EscrowError> {
+            if self.reentrancy_lock.get_or_default() {
+                return Err(EscrowError::ReentrantCall);
+            }
+
+            Ok(())
+        }
+
+        // Admin actions can be queued, executed and cancelled by admins and signers
+        fn authorise_admin_action(&self) -> Result<(), EscrowError> {
+            let caller: AccountId = Self::env().caller();
+            if self.has_role(Role::Admin, caller) || self.signers.contains(&caller) {
+                Ok(())
+            } else {
+                Err(EscrowError::Unauthorised)
+            }
+        }
+
+        // Fee and treasury changes are parameters, so they belong to the governor while one is
+        // set. The rest stay with admins and signers.
+        fn authorise_queued_action(&self, action: &AdminAction) -> Result<(), EscrowError> {
+            match action {
+                AdminAction::FeeBps(_) | AdminAction::Treasury(_) if self.governor.is_some() => {
+                    self.authorise_parameter_change()
+                }
+                _ => self.authorise_admin_action(),
+            }
+        }
+
+        fn authorise_parameter_change(&self) -> Result<(), EscrowError> {
+            let caller: AccountId = Self::env().caller();
+            let authorised: bool = if let Some(governor) = self.governor {
+                caller == governor
+            } else {
+                caller == self.ownable.owner()
+            };
+            if authorised {
+                Ok(())
+            } else {
+                Err(EscrowError::Unauthorised)
+            }
+        }
+
+        fn authorise_role_management(&self, role: Role) -> Result<(), EscrowError> {
+            let caller: AccountId = Self::env().caller();
+            if caller == self.ownable.owner()
+                || (role != Role::Admin && self.roles.contains((Role::Admin, caller)))
+            {
+                Ok(())
+            } else {
+                Err(EscrowError::Unauthorised)
+            }
+        }
+
+        fn record_audit(&mut self, action: AuditAction, target_id: Option<u64>) {
+            let audit_entry: AuditEntry = AuditEntry {
+                caller: Self::env().caller(),
+                action,
+                target_id,
+                timestamp: self.env().block_timestamp(),
+            };
+            self.audit_log
+                .insert(self.audit_log_length % AUDIT_LOG_CAPACITY, &audit_entry);
+            self.audit_log_length += 1;
+        }
+
+        // Moves the vendor to its place on the leaderboard by its volume in the current epoch,
+        // starting the leaderboard afresh when the epoch has moved on
+        fn rank_vendor(&mut self, vendor: AccountId) {
+            let epoch: u64 = self.env().block_timestamp() / REPUTATION_EPOCH;
+            let volume: Balance = self
+                .reputation_epochs
+                .get((vendor, epoch))
+                .unwrap_or_default()
+                .as_vendor
+                .volume;
+            let mut top_vendors: TopVendors = self.top_vendors.get_or_default();
+            if top_vendors.epoch != epoch {
+                top_vendors = TopVendors {
+                    epoch,
+                    vendors: vec![],
+                };
+            }
+            top_vendors.vendors.retain(|ranked| ranked.vendor != vendor);
+            let position: usize = top_vendors
+                .vendors
+                .iter()
+                .position(|ranked| ranked.volume < volume)
+                .unwrap_or(top_vendors.vendors.len());
+            if position < TOP_VENDORS_CAPACITY {
+                top_vendors
+                    .vendors
+                    .insert(position, VendorVolume { vendor, volume });
+                top_vendors.vendors.truncate(TOP_VENDORS_CAPACITY);
+                self.top_vendors.set(&top_vendors);
+            }
+        }
+
+        // Intents can only be relayed by relayers, before their deadline, with the buyer's
+        // current nonce and signature
+        fn authorise_relayed_intent(
+            &self,
+            buyer: AccountId,
+            nonce: u64,
+            deadline: Timestamp,
+            hash: [u8; 32],
+            signature: [u8; 65],
+        ) -> Result<(), EscrowError> {
+            if !self.has_role(Role::Relayer, Self::env().caller()) {
+                return Err(EscrowError::Unauthorised);
+            }
+            if self.env().block_timestamp() > deadline {
+                return Err(EscrowError::SignatureExpired);
+            }
+            if nonce != self.nonce(buyer) {
+                return Err(EscrowError::InvalidNonce);
+            }
+            let public_key: [u8; 33] = self
+                .env()
+                .ecdsa_recover(&signature, &hash)
+                .map_err(|_| EscrowError::InvalidSignature)?;
+            let signer: AccountId =
+                AccountId::from(self.env().hash_bytes::<Blake2x256>(&public_key));
+            if signer != buyer {
+                return Err(EscrowError::InvalidSignature);
+            }
+            Ok(())
+        }
+
+        // Applies the update to the account's lifetime reputation and to its current epoch
+        fn record_reputation(&mut self, account: AccountId, update: impl Fn(&mut Reputation)) {
+            let epoch: u64 = self.env().block_timestamp() / REPUTATION_EPOCH;
+            self.record_reputation_in(account, epoch, update);
+        }
+
+        fn record_reputation_in(
+            &mut self,
+            account: AccountId,
+            epoch: u64,
+            update: impl Fn(&mut Reputation),
+        ) {
+            let mut reputation: Reputation = self.reputations.get(account).unwrap_or_default();
+            update(&mut reputation);
+            self.reputations.insert(account, &reputation);
+            let mut bucket: Reputation = self
+                .reputation_epochs
+                .get((account, epoch))
+                .unwrap_or_default();
+            update(&mut bucket);
+            self.reputation_epochs.insert((account, epoch), &bucket);
+        }
+
+        fn record_vendor_activity(&mut self, account: AccountId) {
+            if let Some(mut vendor) = self.vendors.get(account) {
+                vendor.last_active_at = self.env().block_timestamp();
+                self.vendors.insert(account, &vendor);
+            }
+        }
+
+        fn register_vendor(
+            &mut self,
+            account: AccountId,
+            ethereum_address: String,
+        ) -> Result<(), EscrowError> {
+            if self.is_blacklisted(account) {
+                return Err(EscrowError::Blacklisted);
+            }
+            if self.vendors.get(account).is_some() {
+                return Err(EscrowError::VendorAlreadyExists);
+            }
+            if ethereum_address.len() > MAX_ETHEREUM_ADDRESS_LENGTH {
+                return Err(EscrowError::EthereumAddressTooLong);
+            }
+
+            // Create vendor for account
+            let vendor: Vendor = Vendor {
+                ethereum_address,
+                listings_length: 0,
+                verified: false,
+                tier: 0,
+                completed_orders_length: 0,
+                last_active_at: self.env().block_timestamp(),
+            };
+            self.vendors.insert(account, &vendor);
+            self.vendors_length += 1;
+
+            // Emit event
+            self.env().emit_event(CreateVendor { caller: account });
+
+            Ok(())
+        }
+
+        fn place_order(
+            &mut self,
+            buyer: AccountId,
+            listing_id: u32,
+            amount: Balance,
+            hold: Balance,
+        ) -> Result<(), EscrowError> {
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            if self.is_paused(PAUSE_CREATE_ORDER) {
+                return Err(EscrowError::Paused);
+            }
+            if self.is_blacklisted(buyer) {
+                return Err(EscrowError::Blacklisted);
+            }
+            if self.orders.length() == u64::MAX {
+                return Err(EscrowError::OrderLimitReached);
+            }
+            let listing_wrapped: Option<Listing> = self.listings.get(listing_id);
+            if let Some(mut listing) = listing_wrapped {
+                if listing.vendor == buyer {
+                    return Err(EscrowError::Unauthorised);
+                }
+                if !self.vendors.contains(listing.vendor) || self.is_blacklisted(listing.vendor) {
+                    return Err(EscrowError::VendorInactive);
+                }
+                if listing.closed {
+                    return Err(EscrowError::ListingClosed);
+                }
+                if listing.is_expired(self.env().block_timestamp()) {
+                    return Err(EscrowError::ListingExpired);
+                }
+                if listing.paused {
+                    return Err(EscrowError::ListingPaused);
+                }
+                if amount > listing.available_amount {
+                    return Err(EscrowError::AmountUnavailable(listing.available_amount));
+                }
+                let limits: Limits = self.limits.get_or_default();
+                // Orders for nothing are refused even while no minimum is set
+                let min_order_amount: Balance = limits.min_order_amount.max(1);
+                if amount < min_order_amount {
+                    return Err(EscrowError::OrderAmountBelowLimit(min_order_amount));
+                }
+                let max_order_amount: Balance =
+                    limits.max_order_amount.min(self.order_size_limit(buyer));
+                if amount > max_order_amount {
+                    return Err(EscrowError::OrderAmountAboveLimit(max_order_amount));
+                }
+                let required_hold: Balance = if amount > limits.hold_threshold {
+                    limits.hold_amount
+                } else {
+                    0
+                };
+                if hold != required_hold {
+                    return Err(EscrowError::IncorrectHold(required_hold));
+                }
+                let held_amount: Balance = self
+                    .held_amount
+                    .checked_add(hold)
+                    .ok_or(EscrowError::Overflow)?;
+                if self
+                    .listings
+                    .exposures
+                    .get(listing.id)
+                    .unwrap_or_default()
+                    .orders_length
+                    >= limits.max_open_orders_per_listing
+                {
+                    return Err(EscrowError::ListingOrderLimitReached);
+                }
+                if self.orders.active_length_by_buyer.get(buyer).unwrap_or(0)
+                    >= limits.max_open_orders_per_buyer
+                {
+                    return Err(EscrowError::OpenOrderLimitReached);
+                }
+                let block_number: BlockNumber = self.env().block_number();
+                let orders_created: u32 = match self.orders_created_in_block.get(buyer) {
+                    Some((block, orders_created)) if block == block_number => orders_created,
+                    _ => 0,
+                };
+                if orders_created >= limits.max_orders_per_block_per_buyer {
+                    return Err(EscrowError::OrderRateLimitReached);
+                }
+                self.orders_created_in_block
+                    .insert(buyer, &(block_number, orders_created + 1));
+
+                let total_price: Balance = listing
+                    .price_per_unit
+                    .checked_mul(amount)
+                    .ok_or(EscrowError::Overflow)?;
+                listing.available_amount = listing
+                    .available_amount
+                    .checked_sub(amount)
+                    .ok_or(EscrowError::Underflow)?;
+                self.listings
+                    .update_available_amount(listing.id, listing.available_amount);
+                self.sync_yield(listing.id, listing.vendor)?;
+                if listing.available_amount == 0 {
+                    // Emit event
+                    self.env().emit_event(ListingClosed {
+                        id: listing.id,
+                        vendor: listing.vendor,
+                        drained: true,
+                        refunded: 0,
+                    });
+                }
+
+                let order: Order = Order {
+                    id: self.orders.length(),
+                    buyer,
+                    vendor: listing.vendor,
+                    amount,
+                    payment_verification: None,
+                    state: OrderState::new(0),
+                    listing_id: listing.id,
+                    total_price,
+                    created_at: self.env().block_timestamp(),
+                    status_updated_at: self.env().block_timestamp(),
+                };
+                self.orders.create(&order);
+                self.listings.expose(listing.id, amount)?;
+                if hold > 0 {
+                    self.holds.insert(order.id, &hold);
+                    self.held_amount = held_amount;
+                }
+                let mut buyer_summary: BuyerSummary = self.buyer_summary(buyer);
+                if buyer_summary.orders_length == 0 {
+                    buyer_summary.first_order_at = order.created_at;
+                }
+                buyer_summary.orders_length += 1;
+                buyer_summary.last_order_at = order.created_at;
+                self.buyer_summaries.insert(buyer, &buyer_summary);
+
+                // Emit event
+                self.env().emit_event(CreateOrder {
+                    id: order.id,
+                    buyer: order.buyer,
+                    vendor: order.vendor,
+                    listing_id: order.listing_id,
+                    amount: order.amount,
+                    price_per_unit: listing.price_per_unit,
+                    currency: listing.currency,
+                });
+                // Emit event
+                self.env().emit_event(AcceptOrder {
+                    id: order.id,
+                    vendor: order.vendor,
+                    listing_id: order.listing_id,
+                    amount: order.amount,
+                });
+                self.notify_subscribers(&order);
+            } else {
+                return Err(EscrowError::ListingNotFound);
+            }
+
+            Ok(())
+        }
+
+        fn expire(&mut self, order: &mut Order) -> Result<(), EscrowError> {
+            let timeouts: Timeouts = self.timeouts.get_or_default();
+            let window: Timestamp = match order.status() {
+                0 => timeouts.payment_window,
+                1 => timeouts.verification_window,
+                4 => timeouts.dispute_window,
+                _ => return Err(EscrowError::StatusCanNotBeChanged),
+            };
+            if self.env().block_timestamp() < order.status_updated_at.saturating_add(window) {
+                return Err(EscrowError::OrderNotExpired);
+            }
+
+            let contract: AccountId = self.env().account_id();
+            if order.status() == OrderStatus::PendingVerification as u8 {
+                // A submitted payment verification isn't proof of payment, so it's left to an
+                // arbitrator rather than released to the buyer
+                order.try_transition(OrderStatus::Disputed)?;
+                order.status_updated_at = self.env().block_timestamp();
+                self.orders.update(order);
+                self.raise_dispute(order, contract);
+                Ok(())
+            } else if order.status() == OrderStatus::Disputed as u8 {
+                // Running out the clock doesn't settle a dispute for either party, it's taken to
+                // admins instead. Escalated disputes wait for them however long it takes.
+                self.escalate(order, contract)
+            } else {
+                // Buyers who never paid forfeit their hold to the vendor they kept waiting
+                let unpaid: bool = order.status() == 0;
+                self.return_order_to_listing(order, unpaid)
+            }
+        }
+
+        // Everything that can fail is checked before anything is written, as expire_orders
+        // carries on past orders that fail
+        fn release_order(&mut self, order: &mut Order) -> Result<(), EscrowError> {
+            self.guard_export()?;
+            let disputed: bool = order.status() == OrderStatus::Disputed as u8;
+            order.try_transition(OrderStatus::Finalised)?;
+            let listing: Listing = self.listings.get(order.listing_id).unwrap();
+            let fee_bps: u16 = self.fee_bps.get_or_default();
+            let fee: Balance = order
+                .amount
+                .checked_mul(Balance::from(fee_bps))
+                .ok_or(EscrowError::Overflow)?
+                / 10_000;
+            let accrued_fees: Balance = self
+                .accrued_fees
+                .get(listing.asset)
+                .unwrap_or(0)
+                .checked_add(fee)
+                .ok_or(EscrowError::Overflow)?;
+            let escrowed_amount: Balance = self
+                .escrowed_amount
+                .checked_sub(order.amount)
+                .ok_or(EscrowError::Underflow)?;
+            let mut buyer_summary: BuyerSummary = self.buyer_summary(order.buyer);
+            buyer_summary.volume = buyer_summary
+                .volume
+                .checked_add(order.amount)
+                .ok_or(EscrowError::Overflow)?;
+            let hold: Balance = self.order_hold(order.id);
+            let held_amount: Balance = self
+                .held_amount
+                .checked_sub(hold)
+                .ok_or(EscrowError::Underflow)?;
+            self.listings.unexpose(order.listing_id, order.amount)?;
+            self.orders.deactivate(order)?;
+
+            order.status_updated_at = self.env().block_timestamp();
+            if disputed {
+                self.disputes.resolve(order.id, order.status_updated_at);
+                // Releasing a disputed order settles it in the buyer's favour
+                self.record_reputation(order.buyer, |reputation| {
+                    reputation.as_buyer.add_dispute(true)
+                });
+                self.record_reputation(order.vendor, |reputation| {
+                    reputation.as_vendor.add_dispute(false)
+                });
+            }
+            self.orders.update(order);
+            if let Some(mut vendor) = self.vendors.get(order.vendor) {
+                vendor.completed_orders_length += 1;
+                self.vendors.insert(order.vendor, &vendor);
+            }
+            let amount: Balance = order.amount;
+            self.record_reputation(order.vendor, |reputation| {
+                reputation.as_vendor.add_settled_order(true);
+                reputation.as_vendor.add_volume(amount);
+            });
+            self.record_reputation(order.buyer, |reputation| {
+                reputation.as_buyer.add_settled_order(true);
+                reputation.as_buyer.add_volume(amount);
+            });
+            self.rank_vendor(order.vendor);
+            self.mint_milestone_badge(order.vendor);
+            self.mint_milestone_badge(order.buyer);
+            buyer_summary.completed_orders_length += 1;
+            self.buyer_summaries.insert(order.buyer, &buyer_summary);
+
+            // Transfer funds to buyer minus the fee
+            if fee > 0 {
+                self.accrued_fees.insert(listing.asset, &accrued_fees);
+
+                // Emit event
+                self.env().emit_event(FeeCollected {
+                    order_id: order.id,
+                    asset: listing.asset,
+                    amount: order.amount,
+                    fee_bps,
+                    fee,
+                    payout: order.amount - fee,
+                });
+            }
+            self.escrowed_amount = escrowed_amount;
+            self.transfer_asset(listing.asset, order.buyer, order.amount - fee);
+            self.settle_hold(order, hold, held_amount, false);
+            self.notify_subscribers(order);
+            self.notify_trade_manager(order);
+
+            Ok(())
+        }
+
+        // Everything that can fail is checked before anything is written, as expire_orders
+        // carries on past orders that fail
+        fn return_order_to_listing(
+            &mut self,
+            order: &mut Order,
+            forfeit_hold: bool,
+        ) -> Result<(), EscrowError> {
+            self.guard_export()?;
+            let disputed: bool = order.status() == OrderStatus::Disputed as u8;
+            order.try_transition(OrderStatus::Cancelled)?;
+            let available_amount: Balance = self
+                .listings
+                .available_amount(order.listing_id)
+                .unwrap()
+                .checked_add(order.amount)
+                .ok_or(EscrowError::Overflow)?;
+            let hold: Balance = self.order_hold(order.id);
+            let held_amount: Balance = self
+                .held_amount
+                .checked_sub(hold)
+                .ok_or(EscrowError::Underflow)?;
+            self.listings.unexpose(order.listing_id, order.amount)?;
+            self.orders.deactivate(order)?;
+
+            order.status_updated_at = self.env().block_timestamp();
+            if disputed {
+                self.disputes.resolve(order.id, order.status_updated_at);
+                // Returning a disputed order to its listing settles it in the vendor's favour
+                self.record_reputation(order.vendor, |reputation| {
+                    reputation.as_vendor.add_dispute(true)
+                });
+                self.record_reputation(order.buyer, |reputation| {
+                    reputation.as_buyer.add_dispute(false)
+                });
+            }
+            self.orders.update(order);
+            let mut buyer_summary: BuyerSummary = self.buyer_summary(order.buyer);
+            buyer_summary.cancelled_orders_length += 1;
+            self.buyer_summaries.insert(order.buyer, &buyer_summary);
+            self.record_reputation(order.vendor, |reputation| {
+                reputation.as_vendor.add_settled_order(false)
+            });
+            self.record_reputation(order.buyer, |reputation| {
+                reputation.as_buyer.add_settled_order(false)
+            });
+
+            // Increase associated listing's availabe_amount
+            self.listings
+                .update_available_amount(order.listing_id, available_amount);
+            self.sync_yield(order.listing_id, order.vendor)?;
+            self.settle_hold(order, hold, held_amount, forfeit_hold);
+            self.notify_trade_manager(order);
+
+            Ok(())
+        }
+
+        fn submit_payment_verification(
+            &mut self,
+            caller: AccountId,
+            order_id: u64,
+            payment_verification: [u8; 32],
+            attestation: Option<[u8; 65]>,
+        ) -> Result<(), EscrowError> {
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
+            if let Some(mut order) = order_wrapped {
+                if order.buyer != caller && !self.manages_order(&order, caller) {
+                    return Err(EscrowError::Unauthorised);
+                } else if order.status() == 2 {
+                    return Err(EscrowError::OrderFinalised);
+                } else if order.status() == 3 {
+                    return Err(EscrowError::OrderCancelled);
+                }
+                if let Some(signature) = attestation {
+                    let message_hash: [u8; 32] =
+                        self.payment_attestation_hash(order.id, payment_verification);
+                    let public_key: [u8; 33] = self
+                        .env()
+                        .ecdsa_recover(&signature, &message_hash)
+                        .map_err(|_| EscrowError::InvalidSignature)?;
+                    if !self.is_payment_processor(public_key) {
+                        return Err(EscrowError::InvalidSignature);
+                    }
+                }
+                // Only the first payment verification counts towards the buyer's time to pay
+                let first_payment: bool = order.status() == OrderStatus::Open as u8;
+                order.try_transition(OrderStatus::PendingVerification)?;
+                order.payment_verification = Some(payment_verification);
+                order.status_updated_at = self.env().block_timestamp();
+                self.orders.update(&order);
+                if first_payment {
+                    let time_to_pay: Timestamp = order.status_updated_at - order.created_at;
+                    self.record_reputation(order.buyer, |reputation| {
+                        reputation.add_payment(time_to_pay)
+                    });
+                }
+
+                // Emit event
+                self.env().emit_event(UpdateOrderPaymentVerification {
+                    id: order.id,
+                    buyer: order.buyer,
+                    vendor: order.vendor,
+                    caller,
+                });
+                self.notify_subscribers(&order);
+
+                if attestation.is_some() {
+                    self.release_order(&mut order)?;
+
+                    // Emit event
+                    self.env().emit_event(FinaliseOrder {
+                        id: order.id,
+                        buyer: order.buyer,
+                        vendor: order.vendor,
+                        caller,
+                    });
+                }
+            } else {
+                return Err(EscrowError::OrderNotFound);
+            }
+
+            Ok(())
+        }
+
+        // Refunds the buyer's hold, or pays it to the vendor when forfeited
+        fn settle_hold(
+            &mut self,
+            order: &Order,
+            hold: Balance,
+            held_amount: Balance,
+            forfeited: bool,
+        ) {
+            if hold == 0 {
+                return;
+            }
+
+            self.holds.remove(order.id);
+            self.held_amount = held_amount;
+            let recipient: AccountId = if forfeited { order.vendor } else { order.buyer };
+            self.transfer_asset(None, recipient, hold);
+
+            // Emit event
+            self.env().emit_event(HoldSettled {
+                order_id: order.id,
+                recipient,
+                amount: hold,
+                forfeited,
+            });
+        }
+
+        fn transfer_asset(&mut self, asset: Option<AccountId>, to: AccountId, amount: Balance) {
+            let transferred: bool = if let Some(token) = asset {
+                self.reentrancy_lock.set(&true);
+                let transferred: bool = PSP22Ref::transfer(&token, to, amount, Vec::new()).is_ok();
+                self.reentrancy_lock.set(&false);
+                transferred
+            } else {
+                self.env().transfer(to, amount).is_ok()
+            };
+            if !transferred {
+                panic!(
+                    "requested transfer failed. this can be the case if the contract does not\
+                     have sufficient free funds or if the transfer would have brought the\
+                     contract's balance below minimum balance."
+                )
+            }
+        }
+    }
+
+    impl traits::Escrow for Escrow {
+        #[ink(message)]
+        fn cancel_order(&mut self, order_id: u64) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            self.cancel(Self::env().caller(), order_id)
+        }
+
+        #[ink(message)]
+        fn create_order(&mut self, listing_id: u32, amount: Balance) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            self.place_order(Self::env().caller(), listing_id, amount, 0)
+        }
+
+        #[ink(message)]
+        fn dispute_order(&mut self, order_id: u64) -> Result<(), EscrowError> {
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
+            if let Some(mut order) = order_wrapped {
+                let caller: AccountId = Self::env().caller();
+                if order.vendor != caller {
+                    return Err(EscrowError::Unauthorised);
+                }
+
+                order.try_transition(OrderStatus::Disputed)?;
+                order.status_updated_at = self.env().block_timestamp();
+                self.orders.update(&order);
+                self.raise_dispute(&order, caller);
+                self.record_vendor_activity(order.vendor);
+
+                // Emit event
+                self.env().emit_event(DisputeOrder {
+                    id: order.id,
+                    buyer: order.buyer,
+                    vendor: order.vendor,
+                    caller,
+                });
+                self.notify_subscribers(&order);
+            } else {
+                return Err(EscrowError::OrderNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn finalise_order(&mut self, order_id: u64) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            if self.maintenance_mode {
+                return Err(EscrowError::MaintenanceMode);
+            }
+            let order_wrapped: Option<Order> = self.orders.values.get(order_id);
+            if let Some(mut order) = order_wrapped {
+                let caller: AccountId = Self::env().caller();
+                if order.vendor != caller && !self.has_role(Role::Arbitrator, caller) {
+                    return Err(EscrowError::Unauthorised);
+                }
+
+                self.release_order(&mut order)?;
+
+                // Record releases made by an arbitrator rather than the vendor
+                if order.vendor != caller {
+                    self.record_audit(AuditAction::FinaliseOrder, Some(order.id));
+                } else {
+                    self.record_vendor_activity(order.vendor);
+                }
+
+                // Emit event
+                self.env().emit_event(FinaliseOrder {
+                    id: order.id,
+                    buyer: order.buyer,
+                    vendor: order.vendor,
+                    caller,
+                });
+            } else {
+                return Err(EscrowError::OrderNotFound);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn show_listing(&self, id: u32) -> Option<Listing> {
+            self.listings.get(id)
+        }
+
+        #[ink(message)]
+        fn show_order(&self, id: u64) -> Option<Order> {
+            self.orders.values.get(id)
+        }
+
+        // An attestation signed by a registered payment processor releases the order
+        // straight away instead of waiting for the vendor
+        #[ink(message)]
+        fn update_order_payment_verification(
+            &mut self,
+            order_id: u64,
+            payment_verification: [u8; 32],
+            attestation: Option<[u8; 65]>,
+        ) -> Result<(), EscrowError> {
+            self.guard_reentrancy()?;
+            self.submit_payment_verification(
+                Self::env().caller(),
+                order_id,
+                payment_verification,
+                attestation,
+            )
+        }
+    }
+
+    // === TESTS ===
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::listings::LegacyListingWithoutPause;
+        use crate::orders::LegacyOrderWithoutTimestamps;
+        use crate::pagination::ChunkedIds;
+        use crate::traits::Escrow as _;
+        use crate::vendors::LegacyVendorWithoutListings;
+        use crate::Key;
+        use ink::env::{test::DefaultAccounts, DefaultEnvironment};
+        use ink::storage::traits::StorageKey;
+        use openbrush::test_utils;
+
+        // === HELPERS ===
+        fn init() -> (DefaultAccounts<DefaultEnvironment>, Escrow) {
+            let accounts = test_utils::accounts();
+            test_utils::change_caller(accounts.bob);
+            let escrow = Escrow::new(InstantiateArgs::default()).unwrap();
+            (accounts, escrow)
+        }
+
+        fn get_balance(account_id: AccountId) -> Balance {
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account_id)
+                .expect("Cannot get account balance")
+        }
+
+        fn set_balance(account_id: AccountId, balance: Balance) {
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(account_id, balance)
+        }
+
+        fn update_limits(escrow: &mut Escrow, update: impl FnOnce(&mut Limits)) {
+            let mut limits: Limits = escrow.limits.get_or_default();
+            update(&mut limits);
+            escrow.limits.set(&limits);
+        }
+
+        fn start_export(escrow: &mut Escrow, destination: AccountId) -> Result<(), EscrowError> {
+            let id: u32 = escrow.queued_actions_length;
+            escrow.queue_action(AdminAction::StartExport(destination))?;
+            ink::env::test::set_block_timestamp::<DefaultEnvironment>(
+                escrow.queued_actions.get(id).unwrap().executable_at,
+            );
+            escrow.execute_action(id)
+        }
+
+        // === TESTS ===
+        #[ink::test]
+        fn test_accept_ownership() {
+            let (accounts, mut escrow) = init();
+            // when there is no pending owner
+            // * it raises an error
+            let mut result = escrow.accept_ownership();
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when there is a pending owner
+            escrow.transfer_ownership(accounts.alice).unwrap();
+            // = when called by someone other than the pending owner
+            // = * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.accept_ownership();
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when called by the pending owner
+            test_utils::change_caller(accounts.alice);
+            result = escrow.accept_ownership();
+            assert!(result.is_ok());
+            // = * it sets the pending owner as the owner
+            assert_eq!(escrow.ownable.owner(), accounts.alice);
+            // = * it clears the pending owner
+            assert_eq!(escrow.pending_owner, None);
+        }
+
+        #[ink::test]
+        fn test_activate() {
+            let accounts = test_utils::accounts();
+            test_utils::change_caller(accounts.bob);
+            let mut escrow: Escrow = Escrow::new(InstantiateArgs {
+                importing: true,
+                ..InstantiateArgs::default()
+            })
+            .unwrap();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.activate();
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            // = when the invariants don't hold for the imported state
+            // = * it raises an error
+            escrow.escrowed_amount = 1;
+            result = escrow.activate();
+            assert_eq!(result, Err(EscrowError::InvalidImport));
+            // = when the invariants hold
+            escrow.escrowed_amount = 0;
+            result = escrow.activate();
+            assert!(result.is_ok());
+            // = * it ends the import and maintenance mode
+            assert!(!escrow.importing);
+            assert!(!escrow.config().maintenance_mode);
+            // = when already activated
+            // = * it raises an error
+            result = escrow.activate();
+            assert_eq!(result, Err(EscrowError::StateImportLocked));
+        }
+
+        #[ink::test]
+        fn test_account_roles() {
+            let (accounts, mut escrow) = init();
+            // when account has no roles
+            // * it returns no flags
+            assert_eq!(
+                escrow.account_roles(accounts.charlie),
+                AccountRoles::default()
+            );
+            // when account is a vendor
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            // * it flags the account as a vendor
+            let mut result: AccountRoles = escrow.account_roles(accounts.charlie);
+            assert!(result.is_vendor);
+            assert!(!result.is_verified);
+            // when account is verified, banned and an arbitrator
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.verify_vendor(accounts.charlie, true, 1);
+            let _ = escrow.add_to_blacklist(accounts.charlie);
+            let _ = escrow.grant_role(Role::Arbitrator, accounts.charlie);
+            // * it sets every flag
+            result = escrow.account_roles(accounts.charlie);
+            assert!(result.is_vendor);
+            assert!(result.is_verified);
+            assert!(result.is_banned);
+            assert!(result.is_arbitrator);
+        }
+
+        #[ink::test]
+        fn test_active_listings() {
+            let (_accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            for _ in 0..4 {
+                let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            }
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            let _ = escrow.deposit_into_listing(2);
+            let _ = escrow.deposit_into_listing(3);
+            // * it skips listings with nothing available
+            // = when descending
+            let mut result: ScanPage<u32, Listing> =
+                escrow.active_listings(None, 2, Direction::Descending);
+            assert_eq!(result.items.len(), 2);
+            assert_eq!(result.items[0].id, 3);
+            assert_eq!(result.items[1].id, 2);
+            // = * it returns the cursor to carry on from
+            assert_eq!(result.next_after_id, Some(2));
+            result = escrow.active_listings(result.next_after_id, 2, Direction::Descending);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].id, 0);
+            assert_eq!(result.next_after_id, None);
+            // = when ascending
+            result = escrow.active_listings(Some(0), 2, Direction::Ascending);
+            assert_eq!(result.items.len(), 2);
+            assert_eq!(result.items[0].id, 2);
+            assert_eq!(result.items[1].id, 3);
+            // when cursor is past the last listing
+            // * it returns an empty page
+            result = escrow.active_listings(Some(u32::MAX), 2, Direction::Ascending);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.next_after_id, None);
+        }
+
+        #[ink::test]
+        fn test_add_payment_processor() {
+            let (accounts, mut escrow) = init();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.add_payment_processor([2; 33]);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.add_payment_processor([2; 33]);
+            assert!(result.is_ok());
+            // * it registers the public key
+            assert!(escrow.is_payment_processor([2; 33]));
+        }
+
+        #[ink::test]
+        fn test_add_subscriber() {
+            let (accounts, mut escrow) = init();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.add_subscriber(accounts.django);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.add_subscriber(accounts.django);
+            assert!(result.is_ok());
+            // * it registers the subscriber
+            assert_eq!(escrow.subscribers(), vec![accounts.django]);
+            // = when the subscriber is already registered
+            // = * it doesn't register it twice
+            result = escrow.add_subscriber(accounts.django);
+            assert!(result.is_ok());
+            assert_eq!(escrow.subscribers(), vec![accounts.django]);
+            // = when the maximum number of subscribers is registered
+            // = * it raises an error
+            escrow.subscribers.set(&vec![accounts.eve; MAX_SUBSCRIBERS]);
+            result = escrow.add_subscriber(accounts.django);
+            assert_eq!(result, Err(EscrowError::SubscriberLimitReached));
+        }
+
+        #[ink::test]
+        fn test_add_to_blacklist() {
+            let (accounts, mut escrow) = init();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.add_to_blacklist(accounts.django);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.add_to_blacklist(accounts.django);
+            assert!(result.is_ok());
+            // * it blacklists the account
+            assert!(escrow.is_blacklisted(accounts.django));
+            // * it blocks the account from creating a vendor
+            test_utils::change_caller(accounts.django);
+            result = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            assert_eq!(result, Err(EscrowError::Blacklisted));
+            // * it blocks the account from creating a listing
+            result = escrow.create_listing(None, "USD".to_string(), 1);
+            assert_eq!(result, Err(EscrowError::Blacklisted));
+            // * it blocks the account from creating an order
+            result = escrow.create_order(0, 1);
+            assert_eq!(result, Err(EscrowError::Blacklisted));
+            // * it blocks the account from depositing
+            result = escrow.deposit_into_listing(0);
+            assert_eq!(result, Err(EscrowError::Blacklisted));
+            result = escrow.deposit_tokens_into_listing(0, 1);
+            assert_eq!(result, Err(EscrowError::Blacklisted));
+        }
+
+        #[ink::test]
+        fn test_approve_action() {
+            let (accounts, mut escrow) = init();
+            escrow.signers = vec![accounts.charlie, accounts.django];
+            escrow.threshold = 2;
+            // when called by someone who is not a signer
+            // * it raises an error
+            let mut result = escrow.approve_action(0);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by a signer
+            test_utils::change_caller(accounts.charlie);
+            // = when queued action does not exist
+            // = * it raises an error
+            result = escrow.approve_action(0);
+            assert_eq!(result, Err(EscrowError::QueuedActionNotFound));
+            // = when queued action exists
+            escrow.queue_action(AdminAction::FeeBps(50)).unwrap();
+            // == when signer has already approved
+            // == * it raises an error
+            result = escrow.approve_action(0);
+            assert_eq!(result, Err(EscrowError::AlreadyApproved));
+            // == when signer has not approved yet
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_TIMELOCK_DELAY,
+            );
+            assert_eq!(
+                escrow.execute_action(0),
+                Err(EscrowError::InsufficientApprovals)
+            );
+            test_utils::change_caller(accounts.django);
+            result = escrow.approve_action(0);
+            assert!(result.is_ok());
+            // == * it records the approval
+            assert_eq!(
+                escrow.queued_action(0).unwrap().approvals,
+                vec![accounts.charlie, accounts.django]
+            );
+            // == * it allows the action to be executed once the threshold is met
+            assert!(escrow.execute_action(0).is_ok());
+            assert_eq!(escrow.fee_bps.get_or_default(), 50);
+        }
+
+        #[ink::test]
+        fn test_archive_orders() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            for _ in 0..2 {
+                let _ = escrow.create_order(0, 1);
+            }
+            let _ = escrow.update_order_payment_verification(1, [1; 32], None);
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.finalise_order(1);
+            test_utils::change_caller(accounts.django);
+            // when too many orders are given
+            // * it raises an error
+            let mut result = escrow.archive_orders(vec![0; MAX_BATCH_SIZE + 1]);
+            assert_eq!(result, Err(EscrowError::BatchTooLarge));
+            // when the retention period hasn't passed
+            // * it leaves the orders as they are
+            result = escrow.archive_orders(vec![0, 1]);
+            assert!(result.is_ok());
+            assert!(escrow.show_order(1).is_some());
+            // when the retention period has passed
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                ORDER_RETENTION_PERIOD,
+            );
+            result = escrow.archive_orders(vec![0, 1, 5]);
+            assert!(result.is_ok());
+            // * it removes finalised and cancelled orders
+            assert!(escrow.show_order(1).is_none());
+            assert_eq!(escrow.orders_for_buyer(accounts.charlie, 0, 10).total, 1);
+            assert_eq!(escrow.orders_for_vendor(accounts.bob, 0, 10).total, 1);
+            assert_eq!(escrow.orders_by_status(2, 0, 10).unwrap().total, 0);
+            // * it skips the rest
+            assert_eq!(escrow.show_order(0).unwrap().status(), 0);
+            // * it doesn't reuse the archived ids
+            assert_eq!(escrow.orders_count(), 2);
+            // * it leaves the archived id out of indexes
+            assert_eq!(
+                escrow.orders(None, 10, Direction::Descending).items.len(),
+                1
+            );
+            assert_eq!(
+                escrow
+                    .orders_created_between(0, ORDER_RETENTION_PERIOD, 0, 10)
+                    .items
+                    .len(),
+                1
+            );
+        }
+
+        #[ink::test]
+        fn test_assign_arbitrator() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.update_order_payment_verification(0, [1; 32], None);
+            test_utils::change_caller(accounts.bob);
+            escrow.grant_role(Role::Arbitrator, accounts.eve).unwrap();
+            // when called by someone without the moderator role
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.assign_arbitrator(0, accounts.eve);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by a moderator
+            test_utils::change_caller(accounts.bob);
+            // = when account is not an arbitrator
+            // = * it raises an error
+            result = escrow.assign_arbitrator(0, accounts.django);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when order does not exist
+            // = * it raises an error
+            result = escrow.assign_arbitrator(1, accounts.eve);
+            assert_eq!(result, Err(EscrowError::OrderNotFound));
+            // = when order is not disputed
+            // = * it raises an error
+            result = escrow.assign_arbitrator(0, accounts.eve);
+            assert_eq!(result, Err(EscrowError::StatusCanNotBeChanged));
+            // = when order is disputed
+            let _ = escrow.dispute_order(0);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5);
+            result = escrow.assign_arbitrator(0, accounts.eve);
+            assert!(result.is_ok());
+            // = * it records the arbitrator
+            assert_eq!(
+                escrow.open_disputes(0, 10).items[0].arbitrator,
+                Some(accounts.eve)
+            );
+            // = * it restarts the dispute window
+            assert_eq!(escrow.show_order(0).unwrap().status_updated_at, 5);
+        }
+
+        #[ink::test]
+        fn test_audit_log() {
+            let (accounts, mut escrow) = init();
+            // when nothing has been recorded
+            // * it returns an empty page
+            let mut audit_log: Page<AuditEntry> = escrow.audit_log(0, 10);
+            assert_eq!(audit_log.items.len(), 0);
+            assert_eq!(audit_log.total, 0);
+            // when privileged actions have been taken
+            escrow.grant_role(Role::Pauser, accounts.charlie).unwrap();
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5);
+            escrow.add_to_blacklist(accounts.django).unwrap();
+            escrow.pause(PAUSE_DEPOSIT).unwrap();
+            // * it returns them newest first
+            audit_log = escrow.audit_log(0, 2);
+            assert_eq!(audit_log.total, 3);
+            assert_eq!(audit_log.items.len(), 2);
+            assert_eq!(audit_log.items[0].action, AuditAction::Pause(PAUSE_DEPOSIT));
+            assert_eq!(
+                audit_log.items[1].action,
+                AuditAction::AddToBlacklist(accounts.django)
+            );
+            assert_eq!(audit_log.items[1].caller, accounts.bob);
+            assert_eq!(audit_log.items[1].timestamp, 5);
+            // * it pages through older entries
+            audit_log = escrow.audit_log(1, 2);
+            assert_eq!(audit_log.items.len(), 1);
+            assert_eq!(
+                audit_log.items[0].action,
+                AuditAction::GrantRole(Role::Pauser, accounts.charlie)
+            );
+            // when more actions are recorded than the capacity
+            for _ in 0..AUDIT_LOG_CAPACITY {
+                escrow.unpause(PAUSE_DEPOSIT).unwrap();
+            }
+            // * it only keeps the latest entries
+            audit_log = escrow.audit_log(0, u16::MAX);
+            assert_eq!(audit_log.total, AUDIT_LOG_CAPACITY);
+            // * it returns at most the max page size at once
+            assert_eq!(audit_log.items.len(), usize::from(MAX_PAGE_SIZE));
+            assert!(audit_log
+                .items
+                .iter()
+                .all(|entry| entry.action == AuditAction::Unpause(PAUSE_DEPOSIT)));
+        }
+
+        #[ink::test]
+        fn test_buyer_summary() {
+            let (accounts, mut escrow) = init();
+            // when account has no orders
+            // * it returns an empty summary
+            assert_eq!(
+                escrow.buyer_summary(accounts.charlie),
+                BuyerSummary::default()
+            );
+            // when account has orders
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5);
+            let _ = escrow.create_order(0, 2);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(8);
+            let _ = escrow.create_order(0, 3);
+            let _ = escrow.create_order(0, 4);
+            let _ = escrow.cancel_order(2);
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.finalise_order(0);
+            let _ = escrow.finalise_order(1);
+            // * it returns the lifetime stats
+            let buyer_summary: BuyerSummary = escrow.buyer_summary(accounts.charlie);
+            assert_eq!(buyer_summary.orders_length, 3);
+            assert_eq!(buyer_summary.completed_orders_length, 2);
+            assert_eq!(buyer_summary.cancelled_orders_length, 1);
+            assert_eq!(buyer_summary.volume, 5);
+            assert_eq!(buyer_summary.first_order_at, 5);
+            assert_eq!(buyer_summary.last_order_at, 8);
+        }
+
+        #[ink::test]
+        fn test_cancel_action() {
+            let (accounts, mut escrow) = init();
+            // when called by someone without the admin role
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.cancel_action(0);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by an admin
+            test_utils::change_caller(accounts.bob);
+            // = when queued action does not exist
+            // = * it raises an error
+            result = escrow.cancel_action(0);
+            assert_eq!(result, Err(EscrowError::QueuedActionNotFound));
+            // = when queued action exists
+            escrow
+                .queue_action(AdminAction::Treasury(accounts.charlie))
+                .unwrap();
+            result = escrow.cancel_action(0);
+            assert!(result.is_ok());
+            // = * it removes the queued action
+            assert!(escrow.queued_action(0).is_none());
+            // = * it can no longer be executed
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_TIMELOCK_DELAY,
+            );
+            result = escrow.execute_action(0);
+            assert_eq!(result, Err(EscrowError::QueuedActionNotFound));
+            // = when a threshold is set
+            escrow.signers = vec![accounts.charlie, accounts.django];
+            escrow.threshold = 2;
+            escrow
+                .queue_action(AdminAction::Treasury(accounts.charlie))
+                .unwrap();
+            // == when called by an admin who isn't a signer
+            // == * it raises an error
+            result = escrow.cancel_action(1);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // == when called by a signer
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.cancel_action(1);
+            assert!(result.is_ok());
+            // == * it keeps the action until the threshold is met
+            assert!(escrow.queued_action(1).is_some());
+            // == when the signer has already approved the cancellation
+            // == * it raises an error
+            result = escrow.cancel_action(1);
+            assert_eq!(result, Err(EscrowError::AlreadyApproved));
+            // == when the threshold is met
+            test_utils::change_caller(accounts.django);
+            result = escrow.cancel_action(1);
+            assert!(result.is_ok());
+            // == * it removes the queued action
+            assert!(escrow.queued_action(1).is_none());
+            assert_eq!(escrow.action_cancellations.get(1), None);
+        }
+
+        #[ink::test]
+        fn test_cancel_order() {
+            let price_per_unit: Balance = 1_000_000;
+            let (accounts, mut escrow) = init();
+
+            // when order does not exist
+            // * it raises an error
+            let mut result = escrow.cancel_order(0);
+            assert_eq!(result, Err(EscrowError::OrderNotFound));
+            // when order exists
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.alice);
+            let _ = escrow.create_order(0, 5);
+            // = when called by someone other than the buyer or an arbitrator
+            // = * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.cancel_order(0);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when called by the buyer
+            test_utils::change_caller(accounts.alice);
+            // == when order has status finalised
+            let mut order: Order = escrow.orders.values.get(0).unwrap();
+            order.state.set_status(2);
+            escrow.orders.update(&order);
+            // == * it raises an error
+            result = escrow.cancel_order(0);
+            assert_eq!(result, Err(EscrowError::StatusCanNotBeChanged));
+            // == when order has status open
+            order.state.set_status(0);
+            escrow.orders.update(&order);
+            result = escrow.cancel_order(0);
+            assert!(result.is_ok());
+            // == * it sets the status to cancelled
+            assert_eq!(escrow.orders.values.get(0).unwrap().status(), 3);
+            // == * it returns the amount to the listing
+            assert_eq!(escrow.listings.get(0).unwrap().available_amount, 10);
+            // == * it only rewrites the listing's available amount
+            assert_eq!(escrow.listings.available_amounts.get(0), Some(10));
+            assert_ne!(escrow.listings.values.get(0).unwrap().available_amount, 10);
+            // = when called by an arbitrator
+            let _ = escrow.create_order(0, 5);
+            test_utils::change_caller(accounts.bob);
+            escrow
+                .grant_role(Role::Arbitrator, accounts.charlie)
+                .unwrap();
+            test_utils::change_caller(accounts.charlie);
+            // = * it cancels the order
+            result = escrow.cancel_order(1);
+            assert!(result.is_ok());
+            assert_eq!(escrow.orders.values.get(1).unwrap().status(), 3);
+        }
+
+        #[ink::test]
+        fn test_escalate_dispute() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.update_order_payment_verification(0, [1; 32], None);
+            test_utils::change_caller(accounts.bob);
+            // when order does not exist
+            // * it raises an error
+            let mut result = escrow.escalate_dispute(1);
+            assert_eq!(result, Err(EscrowError::OrderNotFound));
+            // when called by someone other than the buyer or vendor
+            // * it raises an error
+            test_utils::change_caller(accounts.django);
+            result = escrow.escalate_dispute(0);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when order is not disputed
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.escalate_dispute(0);
+            assert_eq!(result, Err(EscrowError::StatusCanNotBeChanged));
+            // when order is disputed
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.dispute_order(0);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5);
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.escalate_dispute(0);
+            assert!(result.is_ok());
+            // * it marks the dispute as escalated
+            assert!(escrow.open_disputes(0, 10).items[0].escalated);
+            // * it restarts the dispute window
+            assert_eq!(escrow.show_order(0).unwrap().status_updated_at, 5);
+            // when dispute is already escalated
+            // * it raises an error
+            test_utils::change_caller(accounts.bob);
+            result = escrow.escalate_dispute(0);
+            assert_eq!(result, Err(EscrowError::DisputeAlreadyEscalated));
+        }
+
+        #[ink::test]
+        fn test_execute_action() {
+            let (accounts, mut escrow) = init();
+            // when called by someone without the admin role
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.execute_action(0);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by an admin
+            test_utils::change_caller(accounts.bob);
+            // = when queued action does not exist
+            // = * it raises an error
+            result = escrow.execute_action(0);
+            assert_eq!(result, Err(EscrowError::QueuedActionNotFound));
+            // = when queued action exists
+            escrow.queue_action(AdminAction::FeeBps(50)).unwrap();
+            escrow
+                .queue_action(AdminAction::Treasury(accounts.charlie))
+                .unwrap();
+            escrow
+                .queue_action(AdminAction::TimelockDelay(1_000))
+                .unwrap();
+            // == when the timelock delay has not passed
+            // == * it raises an error
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_TIMELOCK_DELAY - 1,
+            );
+            result = escrow.execute_action(0);
+            assert_eq!(result, Err(EscrowError::TimelockNotExpired));
+            // == when the timelock delay has passed
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_TIMELOCK_DELAY,
+            );
+            // == * it applies the action
+            assert!(escrow.execute_action(0).is_ok());
+            assert_eq!(escrow.fee_bps.get_or_default(), 50);
+            assert!(escrow.execute_action(1).is_ok());
+            assert_eq!(escrow.treasury.get_or_default(), accounts.charlie);
+            assert!(escrow.execute_action(2).is_ok());
+            assert_eq!(escrow.timelock_delay, 1_000);
+            escrow
+                .queue_action(AdminAction::Signers(
+                    vec![accounts.charlie, accounts.django],
+                    2,
+                ))
+                .unwrap();
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_TIMELOCK_DELAY * 2,
+            );
+            assert!(escrow.execute_action(3).is_ok());
+            assert_eq!(escrow.signers, vec![accounts.charlie, accounts.django]);
+            assert_eq!(escrow.threshold, 2);
+            // == * it removes the queued action
+            assert!(escrow.queued_action(0).is_none());
+            result = escrow.execute_action(0);
+            assert_eq!(result, Err(EscrowError::QueuedActionNotFound));
+            // = when signers are set
+            // == when approvals from signers are below the threshold
+            // == * it raises an error
+            escrow.queue_action(AdminAction::FeeBps(10)).unwrap();
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_TIMELOCK_DELAY * 3,
+            );
+            result = escrow.execute_action(4);
+            assert_eq!(result, Err(EscrowError::InsufficientApprovals));
+            // == when approvals come from accounts that are no longer signers
+            // == * they are not counted
+            escrow.signers = vec![accounts.django, accounts.eve];
+            test_utils::change_caller(accounts.django);
+            escrow.approve_action(4).unwrap();
+            result = escrow.execute_action(4);
+            assert_eq!(result, Err(EscrowError::InsufficientApprovals));
+        }
+
+        #[ink::test]
+        fn test_execute_order_action_with_sig() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(5);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.alice);
+            let _ = escrow.create_order(0, 5);
+            test_utils::change_caller(accounts.bob);
+            escrow.grant_role(Role::Relayer, accounts.charlie).unwrap();
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(10);
+            let mut intent: OrderActionIntent = OrderActionIntent {
+                order_id: 0,
+                action: OrderAction::SubmitPaymentVerification([1; 32]),
+                nonce: 0,
+                deadline: 9,
+            };
+            // when called by someone other than a relayer
+            // * it raises an error
+            test_utils::change_caller(accounts.django);
+            let mut result = escrow.execute_order_action_with_sig(accounts.alice, intent, [1; 65]);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by a relayer
+            test_utils::change_caller(accounts.charlie);
+            // = when the deadline has passed
+            // = * it raises an error
+            result = escrow.execute_order_action_with_sig(accounts.alice, intent, [1; 65]);
+            assert_eq!(result, Err(EscrowError::SignatureExpired));
+            intent.deadline = 10;
+            // = when the nonce isn't the buyer's current nonce
+            // = * it raises an error
+            intent.nonce = 1;
+            result = escrow.execute_order_action_with_sig(accounts.alice, intent, [1; 65]);
+            assert_eq!(result, Err(EscrowError::InvalidNonce));
+            intent.nonce = 0;
+            // = when the signature is not the buyer's
+            // = * it raises an error
+            intent.action = OrderAction::Cancel;
+            result = escrow.execute_order_action_with_sig(accounts.alice, intent, [1; 65]);
+            assert_eq!(result, Err(EscrowError::InvalidSignature));
+            // = * it does not use up the nonce or act on the order
+            assert_eq!(escrow.nonce(accounts.alice), 0);
+            assert_eq!(escrow.orders.values.get(0).unwrap().status(), 0);
+        }
+
+        #[ink::test]
+        fn test_expire_listing() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            set_balance(accounts.bob, 0);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 3);
+            // when listing does not exist
+            // * it raises an error
+            let mut result = escrow.expire_listing(1);
+            assert_eq!(result, Err(EscrowError::ListingNotFound));
+            // when listing has no expiry
+            // * it raises an error
+            result = escrow.expire_listing(0);
+            assert_eq!(result, Err(EscrowError::ListingNotExpired));
+            // when listing hasn't expired yet
+            // * it raises an error
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.update_listing(0, 1, Some(5));
+            result = escrow.expire_listing(0);
+            assert_eq!(result, Err(EscrowError::ListingNotExpired));
+            // when listing has expired
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5);
+            test_utils::change_caller(accounts.django);
+            result = escrow.expire_listing(0);
+            assert!(result.is_ok());
+            // * it refunds the available amount to the vendor
+            assert_eq!(get_balance(accounts.bob), 7);
+            assert_eq!(escrow.escrowed_amount, 3);
+            // * it closes the listing
+            assert!(escrow.show_listing(0).unwrap().closed);
+            result = escrow.expire_listing(0);
+            assert_eq!(result, Err(EscrowError::ListingClosed));
+        }
+
+        #[ink::test]
+        fn test_expire_order() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 3);
+            let _ = escrow.create_order(0, 3);
+            let _ = escrow.create_order(0, 3);
+            let _ = escrow.update_order_payment_verification(1, [1; 32], None);
+            let _ = escrow.update_order_payment_verification(2, [1; 32], None);
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.dispute_order(2);
+            test_utils::change_caller(accounts.django);
+
+            // when order does not exist
+            // * it raises an error
+            let mut result = escrow.expire_order(3);
+            assert_eq!(result, Err(EscrowError::OrderNotFound));
+            // when order exists
+            // = when the window for its status hasn't passed
+            // = * it raises an error
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_PAYMENT_WINDOW - 1,
+            );
+            result = escrow.expire_order(0);
+            assert_eq!(result, Err(EscrowError::OrderNotExpired));
+            // = when order is open and the payment window has passed
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_PAYMENT_WINDOW,
+            );
+            result = escrow.expire_order(0);
+            assert!(result.is_ok());
+            // = * it cancels the order and returns the amount to the listing
+            assert_eq!(escrow.orders.values.get(0).unwrap().status(), 3);
+            assert_eq!(escrow.listings.get(0).unwrap().available_amount, 4);
+            // = when order is pending verification and the verification window has passed
+            result = escrow.expire_order(1);
+            assert_eq!(result, Err(EscrowError::OrderNotExpired));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_VERIFICATION_WINDOW,
+            );
+            let charlie_balance: Balance = get_balance(accounts.charlie);
+            result = escrow.expire_order(1);
+            assert!(result.is_ok());
+            // = * it disputes the order on the vendor's behalf without releasing anything
+            assert_eq!(escrow.orders.values.get(1).unwrap().status(), 4);
+            assert_eq!(escrow.open_dispute(1).raised_by, accounts.alice);
+            assert_eq!(
+                escrow.open_dispute(1).raised_at,
+                DEFAULT_VERIFICATION_WINDOW
+            );
+            assert_eq!(get_balance(accounts.charlie), charlie_balance);
+            // = when order is disputed and the dispute window has passed
+            result = escrow.expire_order(2);
+            assert_eq!(result, Err(EscrowError::OrderNotExpired));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_DISPUTE_WINDOW,
+            );
+            result = escrow.expire_order(2);
+            assert!(result.is_ok());
+            // = * it escalates the dispute without settling it
+            assert_eq!(escrow.orders.values.get(2).unwrap().status(), 4);
+            assert!(escrow.open_dispute(2).escalated);
+            assert_eq!(escrow.listings.get(0).unwrap().available_amount, 4);
+            assert_eq!(escrow.orders.active_length, 2);
+            assert_eq!(
+                escrow
+                    .reputation(accounts.charlie)
+                    .as_buyer
+                    .disputes_lost_length,
+                0
+            );
+            // = when order is disputed, escalated and the dispute window has passed again
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_DISPUTE_WINDOW * 2,
+            );
+            // = * it raises an error
+            result = escrow.expire_order(2);
+            assert_eq!(result, Err(EscrowError::DisputeAlreadyEscalated));
+            // = when order is finalised or cancelled
+            // = * it raises an error
+            result = escrow.expire_order(0);
+            assert_eq!(result, Err(EscrowError::StatusCanNotBeChanged));
+        }
+
+        #[ink::test]
+        fn test_expire_orders() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            for _ in 0..3 {
+                let _ = escrow.create_order(0, 1);
+            }
+            let _ = escrow.update_order_payment_verification(2, [1; 32], None);
+            // when too many orders are given
+            // * it raises an error
+            let mut result = escrow.expire_orders(vec![0; MAX_BATCH_SIZE + 1]);
+            assert_eq!(result, Err(EscrowError::BatchTooLarge));
+            // when orders haven't expired
+            // * it leaves them as they are
+            result = escrow.expire_orders(vec![0, 1, 2]);
+            assert!(result.is_ok());
+            assert_eq!(escrow.show_order(0).unwrap().status(), 0);
+            // when some orders have expired
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_PAYMENT_WINDOW,
+            );
+            let _ = escrow.cancel_order(1);
+            test_utils::change_caller(accounts.django);
+            result = escrow.expire_orders(vec![0, 1, 2, 5]);
+            assert!(result.is_ok());
+            // * it moves on the expired orders
+            assert_eq!(escrow.show_order(0).unwrap().status(), 3);
+            // * it skips the rest
+            assert_eq!(escrow.show_order(1).unwrap().status(), 3);
+            assert_eq!(escrow.show_order(2).unwrap().status(), 1);
+            assert_eq!(escrow.show_listing(0).unwrap().available_amount, 9);
+            // when a full batch expires while the most subscribers are registered
+            test_utils::change_caller(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                MAX_BATCH_SIZE as Balance,
+            );
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            for _ in 0..MAX_BATCH_SIZE {
+                let _ = escrow.create_order(0, 1);
+            }
+            escrow.subscribers.set(&vec![accounts.eve; MAX_SUBSCRIBERS]);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_PAYMENT_WINDOW * 2,
+            );
+            test_utils::change_caller(accounts.django);
+            result = escrow.expire_orders((3..3 + MAX_BATCH_SIZE as u64).collect());
+            // * it moves every order on without calling the subscribers
+            assert!(result.is_ok());
+            assert_eq!(escrow.orders.active_length, 1);
+            assert_eq!(
+                escrow.show_listing(0).unwrap().available_amount,
+                9 + MAX_BATCH_SIZE as Balance
+            );
+        }
+
+        #[ink::test]
+        fn test_finalise_order() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10_000);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1_000);
+
+            // when order does not exist
+            // * it raises an error
+            let mut result = escrow.finalise_order(1);
+            assert_eq!(result, Err(EscrowError::OrderNotFound));
+            // when order exists
+            // = when called by someone other than the vendor or an arbitrator
+            // = * it raises an error
+            result = escrow.finalise_order(0);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when called during a token transfer
+            // = * it raises an error
+            test_utils::change_caller(accounts.bob);
+            escrow.reentrancy_lock.set(&true);
+            result = escrow.finalise_order(0);
+            assert_eq!(result, Err(EscrowError::ReentrantCall));
+            escrow.reentrancy_lock.set(&false);
+            // = when called by the vendor
+            escrow.fee_bps.set(&100);
+            result = escrow.finalise_order(0);
+            assert!(result.is_ok());
+            // = * it sets the status to finalised
+            assert_eq!(escrow.orders.values.get(0).unwrap().status(), 2);
+            // = * it sends the amount minus the fee to the buyer
+            assert_eq!(get_balance(accounts.charlie), 1_000 + 990);
+            // = * it accrues the fee for the asset
+            assert_eq!(escrow.accrued_fees.get(None), Some(10));
+            // = * it reduces the escrowed amount
+            assert_eq!(escrow.escrowed_amount, 9_000);
+            // = when order is already finalised
+            // = * it raises an error
+            result = escrow.finalise_order(0);
+            assert_eq!(result, Err(EscrowError::StatusCanNotBeChanged));
+        }
+
+        #[ink::test]
+        fn test_export_funds() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(5);
+            let _ = escrow.deposit_into_listing(1);
+            update_limits(&mut escrow, |limits| {
+                limits.hold_threshold = 3;
+                limits.hold_amount = 1;
+            });
+            test_utils::change_caller(accounts.charlie);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1);
+            let _ = escrow.create_order_with_hold(0, 4);
+            // when called by someone other than the owner
+            // * it raises an error
+            let mut result = escrow.export_funds();
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            // = when the export hasn't started
+            // = * it raises an error
+            result = escrow.export_funds();
+            assert_eq!(result, Err(EscrowError::ExportNotStarted));
+            // = when the export has started
+            start_export(&mut escrow, accounts.frank).unwrap();
+            let frank_balance: Balance = get_balance(accounts.frank);
+            result = escrow.export_funds();
+            // = * it sends the available and ordered amounts of the listings, and the holds
+            assert_eq!(result, Ok(None));
+            assert_eq!(get_balance(accounts.frank), frank_balance + 16);
+            // = when everything has been sent
+            // = * it raises an error
+            result = escrow.export_funds();
+            assert_eq!(result, Err(EscrowError::FundsExported));
+            assert_eq!(get_balance(accounts.frank), frank_balance + 16);
+        }
+
+        #[ink::test]
+        fn test_export_state() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            update_limits(&mut escrow, |limits| {
+                limits.hold_threshold = 3;
+                limits.hold_amount = 1;
+            });
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 2);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1);
+            let _ = escrow.create_order_with_hold(0, 4);
+            let _ = escrow.update_order_payment_verification(0, [1; 32], None);
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.dispute_order(0);
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.export_state(0);
+            assert_eq!(result.err(), Some(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            // = when the export hasn't started
+            // = * it raises an error
+            result = escrow.export_state(0);
+            assert_eq!(result.err(), Some(EscrowError::ExportNotStarted));
+            // = when the export has started
+            start_export(&mut escrow, accounts.frank).unwrap();
+            // == when the cursor is on the listings
+            // == * it returns the listings with their vendors
+            result = escrow.export_state(0);
+            let mut batch: StateBatch = result.unwrap();
+            assert_eq!(batch.listings.len(), 2);
+            assert_eq!(batch.listings[0].available_amount, 4);
+            assert_eq!(batch.vendors.len(), 1);
+            assert_eq!(batch.vendors[0].0, accounts.bob);
+            assert!(batch.orders.is_empty());
+            // == * it points to the orders next
+            assert_eq!(batch.next_cursor, Some(2));
+            // == when the cursor is on the orders
+            // == * it returns the orders with their disputes and holds
+            batch = escrow.export_state(2).unwrap();
+            assert!(batch.listings.is_empty());
+            assert_eq!(batch.orders.len(), 2);
+            assert_eq!(batch.orders[1].amount, 4);
+            assert_eq!(batch.disputes.len(), 1);
+            assert_eq!(batch.disputes[0].order_id, 0);
+            assert_eq!(batch.holds, vec![(1, 1)]);
+            // == * it marks the end of the export
+            assert_eq!(batch.next_cursor, None);
+        }
+
+        #[ink::test]
+        fn test_force_cancel_order() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 5);
+            let _ = escrow.update_order_payment_verification(0, [1; 32], None);
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.dispute_order(0);
+            assert_eq!(escrow.orders.values.get(0).unwrap().status(), 4);
+            test_utils::change_caller(accounts.charlie);
+
+            // when called by someone without the moderator role
+            // * it raises an error
+            let mut result = escrow.force_cancel_order(0, CancelReason::Other);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by a moderator
+            test_utils::change_caller(accounts.bob);
+            escrow.grant_role(Role::Moderator, accounts.django).unwrap();
+            test_utils::change_caller(accounts.django);
+            // = when order does not exist
+            // = * it raises an error
+            result = escrow.force_cancel_order(1, CancelReason::Other);
+            assert_eq!(result, Err(EscrowError::OrderNotFound));
+            // = when order exists
+            // == when order is disputed
+            result = escrow.force_cancel_order(0, CancelReason::VendorUnresponsive);
+            assert!(result.is_ok());
+            // == * it sets the status to cancelled
+            assert_eq!(escrow.orders.values.get(0).unwrap().status(), 3);
+            // == * it records the reason alongside the status
+            let state: OrderState = escrow.orders.values.get(0).unwrap().state;
+            assert_eq!(
+                state.cancel_reason(),
+                Some(CancelReason::VendorUnresponsive)
+            );
+            assert!(state.disputed());
+            // == * it returns the amount to the listing
+            assert_eq!(escrow.listings.get(0).unwrap().available_amount, 10);
+            assert_eq!(escrow.orders.active_length, 0);
+            // == when order is already cancelled
+            // == * it raises an error
+            result = escrow.force_cancel_order(0, CancelReason::Other);
+            assert_eq!(result, Err(EscrowError::StatusCanNotBeChanged));
+            // == when the open order counts are already at zero
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 5);
+            escrow.orders.active_length = 0;
+            test_utils::change_caller(accounts.django);
+            result = escrow.force_cancel_order(1, CancelReason::Other);
+            // == * it raises an error instead of trapping
+            assert_eq!(result, Err(EscrowError::Underflow));
+            assert_eq!(escrow.orders.values.get(1).unwrap().status(), 0);
+        }
+
+        #[ink::test]
+        fn test_grant_role() {
+            let (accounts, mut escrow) = init();
+            // when called by someone without the admin role
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.grant_role(Role::Arbitrator, accounts.alice);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by owner
+            test_utils::change_caller(accounts.bob);
+            // * it grants the role to the account
+            result = escrow.grant_role(Role::Admin, accounts.alice);
+            assert!(result.is_ok());
+            assert!(escrow.has_role(Role::Admin, accounts.alice));
+            // when called by an admin
+            test_utils::change_caller(accounts.alice);
+            // = when granting the admin role
+            // = * it raises an error
+            result = escrow.grant_role(Role::Admin, accounts.charlie);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when granting any other role
+            // = * it grants the role to the account
+            result = escrow.grant_role(Role::Pauser, accounts.charlie);
+            assert!(result.is_ok());
+            assert!(escrow.has_role(Role::Pauser, accounts.charlie));
+            assert!(!escrow.has_role(Role::Moderator, accounts.charlie));
+        }
+
+        #[ink::test]
+        fn test_has_role() {
+            let (accounts, mut escrow) = init();
+            // when account is the owner
+            // * it returns true for every role
+            assert!(escrow.has_role(Role::Admin, accounts.bob));
+            assert!(escrow.has_role(Role::Arbitrator, accounts.bob));
+            assert!(escrow.has_role(Role::Moderator, accounts.bob));
+            assert!(escrow.has_role(Role::Pauser, accounts.bob));
+            // when account is not the owner
+            // * it returns whether the role was granted
+            assert!(!escrow.has_role(Role::Moderator, accounts.alice));
+            escrow.grant_role(Role::Moderator, accounts.alice).unwrap();
+            assert!(escrow.has_role(Role::Moderator, accounts.alice));
+        }
+
+        #[ink::test]
+        fn test_import_state() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            update_limits(&mut escrow, |limits| {
+                limits.hold_threshold = 3;
+                limits.hold_amount = 1;
+            });
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 2);
+            let _ = escrow.update_order_payment_verification(0, [1; 32], None);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1);
+            let _ = escrow.create_order_with_hold(0, 4);
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.dispute_order(0);
+            start_export(&mut escrow, accounts.frank).unwrap();
+            let listings_batch: StateBatch = escrow.export_state(0).unwrap();
+            let orders_batch: StateBatch = escrow.export_state(1).unwrap();
+            // Deploy the new instance at its own address
+            ink::env::test::set_callee::<DefaultEnvironment>(accounts.frank);
+            escrow = Escrow::new(InstantiateArgs {
+                importing: true,
+                ..InstantiateArgs::default()
+            })
+            .unwrap();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.import_state(listings_batch.clone());
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            // = when the instance isn't importing
+            // = * it raises an error
+            escrow.importing = false;
+            result = escrow.import_state(listings_batch.clone());
+            assert_eq!(result, Err(EscrowError::StateImportLocked));
+            escrow.importing = true;
+            // = when the instance is importing
+            result = escrow.import_state(listings_batch.clone());
+            assert!(result.is_ok());
+            // = * it stores the listings and vendors under their ids
+            assert_eq!(escrow.listings.get(0).unwrap().available_amount, 4);
+            assert_eq!(escrow.vendors_count(), 1);
+            assert!(escrow.vendor_profile(accounts.bob).is_some());
+            assert_eq!(escrow.escrowed_amount, 4);
+            // == when the batch has already been imported
+            // == * it raises an error
+            result = escrow.import_state(listings_batch);
+            assert_eq!(result, Err(EscrowError::InvalidImport));
+            // == when importing orders
+            result = escrow.import_state(orders_batch.clone());
+            assert!(result.is_ok());
+            // == * it stores the orders and holds their amounts for them
+            assert_eq!(escrow.orders_count(), 2);
+            assert_eq!(escrow.orders.active_length, 2);
+            assert_eq!(escrow.listing_exposure(0).unwrap().amount, 6);
+            assert_eq!(escrow.escrowed_amount, 10);
+            // == * it stores their disputes and holds
+            assert_eq!(escrow.open_dispute(0).raised_by, accounts.bob);
+            assert_eq!(escrow.open_disputes(0, 10).total, 1);
+            assert_eq!(escrow.order_hold(1), 1);
+            assert_eq!(escrow.held_amount, 1);
+            // == * it stays in maintenance mode
+            assert!(escrow.config().maintenance_mode);
+            // == when a dispute or hold is for an order that hasn't been imported
+            // == * it raises an error
+            let mut batch: StateBatch = StateBatch {
+                orders: vec![],
+                holds: vec![],
+                ..orders_batch.clone()
+            };
+            batch.disputes[0].order_id = 5;
+            result = escrow.import_state(batch);
+            assert_eq!(result, Err(EscrowError::InvalidImport));
+            batch = StateBatch {
+                orders: vec![],
+                disputes: vec![],
+                holds: vec![(5, 1)],
+                ..orders_batch
+            };
+            result = escrow.import_state(batch);
+            assert_eq!(result, Err(EscrowError::InvalidImport));
+        }
+
+        #[ink::test]
+        fn test_migrate() {
+            let (accounts, mut escrow) = init();
+            // when called by someone without the admin role
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.migrate();
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by an admin
+            test_utils::change_caller(accounts.bob);
+            // = when storage is already at the current version
+            // = * it raises an error
+            result = escrow.migrate();
+            assert_eq!(result, Err(EscrowError::StorageUpToDate));
+            // = when storage is from before versioning
+            // = * it raises an error
+            escrow.storage_version = 0;
+            result = escrow.migrate();
+            assert_eq!(result, Err(EscrowError::StorageVersionUnsupported));
+            // = when there's no step from the stored version
+            // = * it raises an error rather than trapping
+            assert_eq!(
+                escrow.migration_length(STORAGE_VERSION),
+                Err(EscrowError::StorageDecodeFailed)
+            );
+            assert_eq!(
+                escrow.migrate_record(STORAGE_VERSION, 0),
+                Err(EscrowError::StorageDecodeFailed)
+            );
+            // = when a step has more records than fit in one call
+            escrow.storage_version = 4;
+            escrow.event_version = 0;
+            escrow.orders.values.length = MAX_BATCH_SIZE as u64 + 1;
+            result = escrow.migrate();
+            assert!(result.is_ok());
+            // = * it migrates the first batch and records where to carry on from
+            assert_eq!(escrow.storage_version, 4);
+            assert_eq!(escrow.migration_cursor.get(), Some(MAX_BATCH_SIZE as u64));
+            // = * it finishes the step on the next call
+            result = escrow.migrate();
+            assert!(result.is_ok());
+            assert_eq!(escrow.migration_cursor.get(), Some(0));
+            assert_eq!(escrow.storage_version, 5);
+            assert_eq!(escrow.event_version, 0);
+            // = * it sets the storage and event versions to the current versions after the last
+            while escrow.storage_version < STORAGE_VERSION {
+                result = escrow.migrate();
+                assert!(result.is_ok());
+            }
+            assert_eq!(escrow.event_version, EVENT_VERSION);
+            escrow.orders.values.length = 0;
+            // = when only the event schema is older
+            escrow.event_version = 0;
+            result = escrow.migrate();
+            assert!(result.is_ok());
+            // = * it sets the event version to the current version
+            assert_eq!(escrow.event_version, EVENT_VERSION);
+            // = when orders were stored under version 1 before timeouts
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.alice);
+            let _ = escrow.create_order(0, 5);
+            test_utils::change_caller(accounts.bob);
+            let order: Order = escrow.orders.values.get(0).unwrap();
+            let order_key: (Key, u64) = (escrow.orders.values.values.key(), 0);
+            // and the buyer and vendor order ids were kept in one list per account
+            escrow.orders.ids_by_buyer = ChunkedIds::default();
+            escrow.orders.ids_by_vendor = ChunkedIds::default();
+            escrow.storage_version = 1;
+            // == when an order doesn't decode with any version 1 layout
+            // == * it raises an error and leaves the version as it is
+            ink::env::set_contract_storage(&order_key, &7u8);
+            result = escrow.migrate();
+            assert_eq!(result, Err(EscrowError::StorageDecodeFailed));
+            assert_eq!(escrow.storage_version, 1);
+            // == when every order decodes
+            ink::env::set_contract_storage(
+                &order_key,
+                &LegacyOrderWithoutTimestamps {
+                    id: order.id,
+                    buyer: order.buyer,
+                    vendor: order.vendor,
+                    amount: order.amount,
+                    payment_verification: Some("proof".to_string()),
+                    status: order.status(),
+                    listing_id: order.listing_id,
+                    total_price: order.total_price,
+                },
+            );
+            // and the listing was stored before listings could be paused
+            let listing: Listing = escrow.listings.get(0).unwrap();
+            ink::env::set_contract_storage(
+                &(escrow.listings.values.values.key(), 0u32),
+                &LegacyListingWithoutPause {
+                    id: listing.id,
+                    vendor: listing.vendor,
+                    asset: listing.asset,
+                    currency: listing.currency,
+                    available_amount: listing.available_amount,
+                    price_per_unit: 3,
+                },
+            );
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(7);
+            while escrow.storage_version < STORAGE_VERSION {
+                result = escrow.migrate();
+                assert!(result.is_ok());
+            }
+            // == * it replaces each payment verification with its hash
+            let mut hash: [u8; 32] = [0; 32];
+            ink::env::hash_bytes::<Blake2x256>(b"proof", &mut hash);
+            let order: Order = escrow.orders.values.get(0).unwrap();
+            assert_eq!(order.payment_verification, Some(hash));
+            assert_eq!(order.amount, 5);
+            // == * it starts timeouts of orders from before them at the migration
+            assert_eq!(order.created_at, 0);
+            assert_eq!(order.status_updated_at, 7);
+            // == * it brings listings up to the current layout
+            let listing: Listing = escrow.listings.get(0).unwrap();
+            assert_eq!(listing.price_per_unit, 3);
+            assert!(!listing.paused && !listing.closed);
+            assert_eq!(listing.expires_at, None);
+            // == * it packs the status into the order state
+            assert_eq!(order.state, OrderState::new(0));
+            // == * it rebuilds the buyer and vendor order indexes
+            assert_eq!(escrow.orders_for_buyer(accounts.alice, 0, 10).total, 1);
+            assert_eq!(escrow.orders_for_vendor(accounts.bob, 0, 10).total, 1);
+        }
+
+        #[ink::test]
+        fn test_migrate_vendors() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            // when called by someone without the admin role
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.migrate_vendors(vec![accounts.bob]);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by an admin
+            test_utils::change_caller(accounts.bob);
+            // = when too many accounts are given
+            // = * it raises an error
+            result = escrow.migrate_vendors(vec![accounts.bob; MAX_BATCH_SIZE + 1]);
+            assert_eq!(result, Err(EscrowError::BatchTooLarge));
+            // = when a vendor was stored under version 1 before listings were counted
+            ink::env::set_contract_storage(
+                &(escrow.vendors.key(), accounts.bob),
+                &LegacyVendorWithoutListings {
+                    ethereum_address: "0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string(),
+                },
+            );
+            result = escrow.migrate_vendors(vec![accounts.bob, accounts.charlie]);
+            assert!(result.is_ok());
+            // = * it brings the vendor up to the current layout
+            let vendor: Vendor = escrow.vendors.get(accounts.bob).unwrap();
+            assert_eq!(vendor.listings_length, 0);
+            assert!(!vendor.verified);
+            // = * it skips accounts that aren't vendors
+            assert!(!escrow.vendors.contains(accounts.charlie));
+        }
+
+        #[ink::test]
+        fn test_multicall() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            for _ in 0..4 {
+                let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            }
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(5);
+            set_balance(accounts.bob, 10);
+            let _ = escrow.deposit_into_listing(3);
+            // when too many calls are given
+            // * it raises an error
+            let mut result = escrow.multicall(vec![Call::Close(0); MAX_BATCH_SIZE + 1]);
+            assert_eq!(result, Err(EscrowError::BatchTooLarge));
+            // when a call fails
+            // * it raises that call's error
+            result = escrow.multicall(vec![Call::Pause(9, true)]);
+            assert_eq!(result, Err(EscrowError::ListingNotFound));
+            test_utils::change_caller(accounts.alice);
+            result = escrow.multicall(vec![Call::Pause(0, true)]);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when every call succeeds
+            test_utils::change_caller(accounts.bob);
+            result = escrow.multicall(vec![
+                Call::Pause(0, true),
+                Call::Pause(1, true),
+                Call::Update(2, 7, None),
+                Call::Withdraw(3, 1),
+            ]);
+            assert!(result.is_ok());
+            // * it makes each call as the caller
+            assert!(escrow.show_listing(0).unwrap().paused);
+            assert!(escrow.show_listing(1).unwrap().paused);
+            assert_eq!(escrow.show_listing(2).unwrap().price_per_unit, 7);
+            assert_eq!(escrow.show_listing(3).unwrap().available_amount, 4);
+            assert_eq!(get_balance(accounts.bob), 11);
+        }
+
+        #[ink::test]
+        fn test_new() {
+            let (accounts, escrow) = init();
+            // * it sets owner as caller
+            assert_eq!(escrow.ownable.owner(), accounts.bob);
+            // * it sets listings
+            // assert_eq!(escrow.listings.values, Mapping::default());
+            assert_eq!(escrow.listings.length(), 0);
+            // * it sets the storage and event versions
+            assert_eq!(escrow.storage_version, STORAGE_VERSION);
+            assert_eq!(escrow.event_version, EVENT_VERSION);
+            // * it sets vendors
+            // assert_eq!(escrow.vendors, Mapping::default());
+            // * it defaults to no fees, paid to the deployer
+            assert_eq!(escrow.config().fee_bps, 0);
+            assert_eq!(escrow.config().treasury, accounts.bob);
+            // * it leaves parameters that match their defaults unwritten
+            assert_eq!(escrow.fee_bps.get(), None);
+            assert_eq!(escrow.paused_operations.get(), None);
+            assert_eq!(escrow.max_page_size.get(), None);
+            assert_eq!(escrow.config().max_page_size, MAX_PAGE_SIZE);
+            // when initial parameters are given
+            let mut args: InstantiateArgs = InstantiateArgs {
+                fee_bps: MAX_FEE_BPS + 1,
+                treasury: Some(accounts.django),
+                limits: InstantiateArgs::default().limits,
+                arbitrators: vec![accounts.charlie, accounts.eve],
+                importing: false,
+            };
+            // = when the fee is above MAX_FEE_BPS
+            // = * it raises an error
+            assert_eq!(
+                Escrow::new(args.clone()).err(),
+                Some(EscrowError::InvalidFee)
+            );
+            // = when the limits are invalid
+            // = * it raises an error
+            args.fee_bps = 250;
+            args.limits.min_order_amount = 2;
+            args.limits.max_order_amount = 1;
+            assert_eq!(
+                Escrow::new(args.clone()).err(),
+                Some(EscrowError::InvalidLimits)
+            );
+            // = when there are more arbitrators than MAX_BATCH_SIZE
+            // = * it raises an error
+            args.limits.max_order_amount = 1_000;
+            let arbitrators: Vec<AccountId> = args.arbitrators.clone();
+            args.arbitrators = vec![accounts.charlie; MAX_BATCH_SIZE + 1];
+            assert_eq!(
+                Escrow::new(args.clone()).err(),
+                Some(EscrowError::BatchTooLarge)
+            );
+            // = when the parameters are valid
+            args.arbitrators = arbitrators;
+            let escrow: Escrow = Escrow::new(args).unwrap();
+            // = * it starts with them
+            let config: Config = escrow.config();
+            assert_eq!(config.fee_bps, 250);
+            assert_eq!(config.treasury, accounts.django);
+            assert_eq!(config.limits.min_order_amount, 2);
+            assert_eq!(config.limits.max_order_amount, 1_000);
+            assert!(escrow.has_role(Role::Arbitrator, accounts.charlie));
+            assert!(escrow.has_role(Role::Arbitrator, accounts.eve));
+            assert!(!escrow.has_role(Role::Arbitrator, accounts.django));
+        }
+
+        #[ink::test]
+        fn test_check_invariants() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 4);
+            set_balance(accounts.alice, 10);
+            // when every invariant holds
+            // * it reports that they hold
+            let mut report: InvariantReport = escrow.check_invariants();
+            assert!(report.holds);
+            assert_eq!(report.listed_amount, 10);
+            assert_eq!(report.escrowed_amount, 10);
+            // when the contract holds less than is listed
+            set_balance(accounts.alice, 9);
+            // * it reports the underfunded asset
+            report = escrow.check_invariants();
+            assert!(!report.holds);
+            assert_eq!(report.underfunded_assets, vec![(None, 10, 9)]);
+            // = when the rest is bonded into the yield pool
+            // = * it counts it as held
+            escrow.bonded_amount = 1;
+            assert!(escrow.check_invariants().holds);
+            escrow.bonded_amount = 0;
+            set_balance(accounts.alice, 10);
+            // when the escrowed amount doesn't match the listings
+            escrow.escrowed_amount = 11;
+            // * it reports that they don't hold
+            report = escrow.check_invariants();
+            assert!(!report.holds);
+            assert_eq!(report.escrowed_amount, 11);
+            escrow.escrowed_amount = 10;
+            // when an order's listing is missing
+            let mut order: Order = escrow.orders.values.get(0).unwrap();
+            order.listing_id = 1;
+            escrow.orders.values.update(0, &order);
+            // * it reports the orphaned order
+            report = escrow.check_invariants();
+            assert!(!report.holds);
+            assert_eq!(report.orphaned_order_ids, vec![0]);
+        }
+
+        #[ink::test]
+        fn test_claim_fees() {
+            let (accounts, mut escrow) = init();
+            escrow.treasury.set(&accounts.django);
+            // when called by someone without the admin role
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.claim_fees(None);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by an admin
+            test_utils::change_caller(accounts.bob);
+            // = when there are no fees for the asset
+            // = * it does nothing
+            result = escrow.claim_fees(None);
+            assert!(result.is_ok());
+            assert_eq!(get_balance(accounts.django), 0);
+            // = when there are fees for the asset
+            escrow.accrued_fees.insert(None, &10);
+            result = escrow.claim_fees(None);
+            assert!(result.is_ok());
+            // = * it sends the fees to the treasury
+            assert_eq!(get_balance(accounts.django), 10);
+            // = * it resets the accrued fees
+            assert_eq!(escrow.accrued_fees.get(None), None);
+        }
+
+        #[ink::test]
+        fn test_close_listing() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            set_balance(accounts.bob, 0);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 3);
+            // when listing does not exist
+            // * it raises an error
+            let mut result = escrow.close_listing(1);
+            assert_eq!(result, Err(EscrowError::ListingNotFound));
+            // when listing exists
+            // = when called by someone other than the vendor
+            // = * it raises an error
+            result = escrow.close_listing(0);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when called by the vendor
+            test_utils::change_caller(accounts.bob);
+            result = escrow.close_listing(0);
+            assert!(result.is_ok());
+            // = * it refunds the available amount to the vendor
+            assert_eq!(get_balance(accounts.bob), 7);
+            assert_eq!(escrow.escrowed_amount, 3);
+            let listing: Listing = escrow.show_listing(0).unwrap();
+            assert_eq!(listing.available_amount, 0);
+            assert!(listing.closed);
+            // = * it stops deposits and orders
+            assert_eq!(
+                escrow.deposit_into_listing(0),
+                Err(EscrowError::ListingClosed)
+            );
+            test_utils::change_caller(accounts.django);
+            assert_eq!(escrow.create_order(0, 1), Err(EscrowError::ListingClosed));
+            // = * open orders can still be cancelled and withdrawn
+            test_utils::change_caller(accounts.charlie);
+            assert!(escrow.cancel_order(0).is_ok());
+            test_utils::change_caller(accounts.bob);
+            assert!(escrow.withdraw_from_listing(0, 3).is_ok());
+            // = when listing is already closed
+            // = * it raises an error
+            result = escrow.close_listing(0);
+            assert_eq!(result, Err(EscrowError::ListingClosed));
+        }
+
+        #[ink::test]
+        fn test_config() {
+            let (accounts, escrow) = init();
+            let config = escrow.config();
+            // * it returns the config
+            assert_eq!(config.admin, accounts.bob);
+            assert_eq!(config.pending_admin, None);
+            assert_eq!(config.signers, vec![]);
+            assert_eq!(config.threshold, 0);
+            assert_eq!(config.limits.max_order_amount, Balance::MAX);
+            assert_eq!(config.limits.max_payment_window, Timestamp::MAX);
+            assert_eq!(config.max_page_size, MAX_PAGE_SIZE);
+            assert_eq!(config.timeouts.payment_window, DEFAULT_PAYMENT_WINDOW);
+            assert_eq!(config.fee_bps, 0);
+            assert_eq!(config.treasury, accounts.bob);
+            assert_eq!(config.timelock_delay, DEFAULT_TIMELOCK_DELAY);
+            assert_eq!(config.paused_operations, 0);
+            assert_eq!(config.storage_version, STORAGE_VERSION);
+            assert_eq!(config.event_version, EVENT_VERSION);
+        }
+
+        #[ink::test]
+        fn test_create_listing() {
+            let price_per_unit: Balance = 1_000_000;
+            let (accounts, mut escrow) = init();
+            // when the maximum number of listings has been reached
+            escrow.listings.values.length = u32::MAX;
+            // * it raises an error
+            let mut result = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+            assert_eq!(result, Err(EscrowError::ListingLimitReached));
+            // when the maximum number of listings hasn't been reached
+            escrow.listings.values.length = u32::MAX - 1;
+            // = when caller isn't a vendor
+            // = * it raises an error
+            result = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+            assert_eq!(result, Err(EscrowError::ListingCanOnlyBeCreatedByAVendor));
+            // = when caller is a vendor
+            escrow.vendors.insert(
+                accounts.bob,
+                &Vendor {
+                    ethereum_address: "0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string(),
+                    listings_length: 0,
+                    verified: false,
+                    tier: 0,
+                    completed_orders_length: 0,
+                    last_active_at: 0,
+                },
+            );
+            // == when vendor has reached the maximum number of listings
+            // == * it raises an error
+            update_limits(&mut escrow, |limits| limits.max_listings_per_vendor = 0);
+            result = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+            assert_eq!(result, Err(EscrowError::VendorListingLimitReached));
+            update_limits(&mut escrow, |limits| limits.max_listings_per_vendor = 1);
+            // == when currency is not a three letter code
+            // == * it raises an error
+            result = escrow.create_listing(None, "USDT".to_string(), price_per_unit);
+            assert_eq!(result, Err(EscrowError::InvalidCurrency));
+            // == when currency is a three letter code
+            // == * it creates a listing at the listings length index
+            result = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+            assert!(result.is_ok());
+            let listing: Listing = escrow.listings.get(u32::MAX - 1).unwrap();
+            assert_eq!(listing.vendor, accounts.bob);
+            assert_eq!(listing.asset, None);
+            assert_eq!(listing.currency, "USD".to_string());
+            // = * it increases the listings length by one
+            assert_eq!(escrow.listings.length(), u32::MAX);
+            // = * it increases the vendor's listings length by one
+            assert_eq!(escrow.vendors.get(accounts.bob).unwrap().listings_length, 1);
+        }
+
+        #[ink::test]
+        fn test_create_order() {
+            let price_per_unit: Balance = 1_000_000;
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+
+            // when the maximum number of orders has been reached
+            escrow.orders.values.length = u64::MAX;
+            // * it raises an error
+            let mut result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::OrderLimitReached));
+            escrow.orders.values.length = 0;
+            // when listing does not exist
+            // * it raises an error
+            result = escrow.create_order(1, 5);
+            assert_eq!(result, Err(EscrowError::ListingNotFound));
+            // when listing exists
+            // = when caller is vendor
+            // = * it raises an error
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when caller is not vendor
+            test_utils::change_caller(accounts.alice);
+            // == when vendor is banned
+            escrow.blacklist.insert(accounts.bob, &());
+            // == * it raises an error
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::VendorInactive));
+            escrow.blacklist.remove(accounts.bob);
+            // == when vendor is no longer registered
+            let vendor: Vendor = escrow.vendors.get(accounts.bob).unwrap();
+            escrow.vendors.remove(accounts.bob);
+            // == * it raises an error
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::VendorInactive));
+            escrow.vendors.insert(accounts.bob, &vendor);
+            // == when amount to purchase is not available
+            // == * it raises an error
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::AmountUnavailable(0)));
+            // == when amount to purchase is available
+            test_utils::change_caller(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(5);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.alice);
+            // === when amount is below the minimum order amount
+            // === * it raises an error
+            update_limits(&mut escrow, |limits| limits.min_order_amount = 6);
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::OrderAmountBelowLimit(6)));
+            // === when amount is zero and no minimum order amount is set
+            // === * it raises an error
+            update_limits(&mut escrow, |limits| limits.min_order_amount = 0);
+            result = escrow.create_order(0, 0);
+            assert_eq!(result, Err(EscrowError::OrderAmountBelowLimit(1)));
+            update_limits(&mut escrow, |limits| limits.min_order_amount = 5);
+            // === when amount is above the maximum order amount
+            // === * it raises an error
+            update_limits(&mut escrow, |limits| limits.max_order_amount = 4);
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::OrderAmountAboveLimit(4)));
+            update_limits(&mut escrow, |limits| limits.max_order_amount = 5);
+            // === when amount is above the maximum for the buyer's completed orders
+            // === * it raises an error
+            escrow.order_size_schedule.set(&vec![OrderSizeTier {
+                completed_orders_length: 0,
+                max_order_amount: 4,
+            }]);
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::OrderAmountAboveLimit(4)));
+            escrow.order_size_schedule.set(&vec![]);
+            // === when the listing has reached the maximum number of open orders
+            // === * it raises an error
+            update_limits(&mut escrow, |limits| limits.max_open_orders_per_listing = 0);
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::ListingOrderLimitReached));
+            update_limits(&mut escrow, |limits| limits.max_open_orders_per_listing = 1);
+            // === when buyer has reached the maximum number of open orders
+            // === * it raises an error
+            update_limits(&mut escrow, |limits| limits.max_open_orders_per_buyer = 0);
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::OpenOrderLimitReached));
+            update_limits(&mut escrow, |limits| limits.max_open_orders_per_buyer = 1);
+            // === when buyer has created the maximum number of orders in this block
+            // === * it raises an error
+            update_limits(&mut escrow, |limits| {
+                limits.max_orders_per_block_per_buyer = 0
+            });
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::OrderRateLimitReached));
+            update_limits(&mut escrow, |limits| {
+                limits.max_orders_per_block_per_buyer = 1
+            });
+            // === when within the limits
+            result = escrow.create_order(0, 5);
+            assert!(result.is_ok());
+            // == * it reduces the amount_availabe by the amount
+            assert_eq!(escrow.listings.get(0).unwrap().available_amount, 0);
+            // == * it create an order
+            let order: Order = escrow.orders.values.get(0).unwrap();
+            assert_eq!(order.amount, 5);
+            assert_eq!(order.buyer, accounts.alice);
+            assert_eq!(order.vendor, accounts.bob);
+            assert_eq!(order.id, 0);
+            assert_eq!(escrow.orders.length(), 1);
+            assert_eq!(order.status(), 0);
+            assert_eq!(
+                escrow.orders.active_length_by_buyer.get(accounts.alice),
+                Some(1)
+            );
+            // == when buyer has already created the maximum number of orders in this block
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.alice);
+            update_limits(&mut escrow, |limits| {
+                limits.max_open_orders_per_buyer = 2;
+                limits.max_open_orders_per_listing = 2;
+            });
+            // == * it raises an error
+            result = escrow.create_order(0, 5);
+            assert_eq!(result, Err(EscrowError::OrderRateLimitReached));
+            // == when a new block has started
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            // == * it creates the order
+            result = escrow.create_order(0, 5);
+            assert!(result.is_ok());
+        }
+
+        #[ink::test]
+        fn test_create_managed_order() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            // when the buyer hasn't approved the manager
+            // * it raises an error
+            test_utils::change_caller(accounts.django);
+            let mut result = escrow.create_managed_order(accounts.charlie, 0, 3);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when the buyer has approved the manager
+            test_utils::change_caller(accounts.charlie);
+            escrow.set_trade_manager(accounts.django, true).unwrap();
+            test_utils::change_caller(accounts.django);
+            result = escrow.create_managed_order(accounts.charlie, 0, 3);
+            assert!(result.is_ok());
+            // * it creates the order for the buyer
+            let order: Order = escrow.show_order(0).unwrap();
+            assert_eq!(order.buyer, accounts.charlie);
+            assert_eq!(order.amount, 3);
+            // * it records the manager
+            assert_eq!(escrow.order_manager(0), Some(accounts.django));
+            // * it lets the manager submit payment verification for the buyer
+            result = escrow.update_order_payment_verification(0, [1; 32], None);
+            assert!(result.is_ok());
+            assert_eq!(escrow.show_order(0).unwrap().status(), 1);
+            // * it doesn't let other contracts act on the order
+            test_utils::change_caller(accounts.eve);
+            result = escrow.update_order_payment_verification(0, [1; 32], None);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when the buyer revokes the manager
+            test_utils::change_caller(accounts.charlie);
+            escrow.set_trade_manager(accounts.django, false).unwrap();
+            // * it stops the manager acting on the trades it opened
+            test_utils::change_caller(accounts.django);
+            result = escrow.cancel_order(0);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when the order is above the hold threshold
+            test_utils::change_caller(accounts.charlie);
+            escrow.set_trade_manager(accounts.django, true).unwrap();
+            update_limits(&mut escrow, |limits| {
+                limits.hold_threshold = 2;
+                limits.hold_amount = 1;
+            });
+            test_utils::change_caller(accounts.django);
+            // = when the hold isn't sent
+            // = * it raises an error
+            result = escrow.create_managed_order(accounts.charlie, 0, 3);
+            assert_eq!(result, Err(EscrowError::IncorrectHold(1)));
+            // = when the hold is sent
+            // = * it holds it for the order
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1);
+            result = escrow.create_managed_order(accounts.charlie, 0, 3);
+            assert!(result.is_ok());
+            assert_eq!(escrow.order_hold(1), 1);
+        }
+
+        #[ink::test]
+        fn test_create_order_with_hold() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            update_limits(&mut escrow, |limits| {
+                limits.hold_threshold = 2;
+                limits.hold_amount = 1;
+            });
+            test_utils::change_caller(accounts.charlie);
+            // when the order is above the hold threshold
+            // = when the hold isn't sent
+            // = * it raises an error
+            let mut result = escrow.create_order(0, 3);
+            assert_eq!(result, Err(EscrowError::IncorrectHold(1)));
+            // = when the wrong amount is sent
+            // = * it raises an error
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(2);
+            result = escrow.create_order_with_hold(0, 3);
+            assert_eq!(result, Err(EscrowError::IncorrectHold(1)));
+            // = when the hold amount is sent
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1);
+            result = escrow.create_order_with_hold(0, 3);
+            assert!(result.is_ok());
+            // = * it holds it for the order
+            assert_eq!(escrow.order_hold(0), 1);
+            assert_eq!(escrow.held_amount, 1);
+            // when the order is at or below the hold threshold
+            // * it doesn't take a hold
+            result = escrow.create_order_with_hold(0, 2);
+            assert_eq!(result, Err(EscrowError::IncorrectHold(0)));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            result = escrow.create_order(0, 2);
+            assert!(result.is_ok());
+            assert_eq!(escrow.order_hold(1), 0);
+            // when an order with a hold is cancelled
+            let charlie_balance: Balance = get_balance(accounts.charlie);
+            result = escrow.cancel_order(0);
+            assert!(result.is_ok());
+            // * it refunds the hold to the buyer
+            assert_eq!(get_balance(accounts.charlie), charlie_balance + 1);
+            assert_eq!(escrow.order_hold(0), 0);
+            assert_eq!(escrow.held_amount, 0);
+            // when an order with a hold expires before being paid
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1);
+            let _ = escrow.create_order_with_hold(0, 3);
+            let bob_balance: Balance = get_balance(accounts.bob);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_PAYMENT_WINDOW,
+            );
+            result = escrow.expire_order(2);
+            assert!(result.is_ok());
+            // * it pays the hold to the vendor
+            assert_eq!(get_balance(accounts.bob), bob_balance + 1);
+            assert_eq!(escrow.held_amount, 0);
+        }
+
+        #[ink::test]
+        fn test_create_order_with_sig() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(5);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(10);
+            let mut intent: OrderIntent = OrderIntent {
+                listing_id: 0,
+                amount: 5,
+                nonce: 0,
+                deadline: 9,
+            };
+            // when called by someone other than a relayer
+            // * it raises an error
+            let mut result = escrow.create_order_with_sig(accounts.alice, intent, [1; 65]);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when the deadline has passed
+            // * it raises an error
+            test_utils::change_caller(accounts.bob);
+            escrow.grant_role(Role::Relayer, accounts.charlie).unwrap();
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.create_order_with_sig(accounts.alice, intent, [1; 65]);
+            assert_eq!(result, Err(EscrowError::SignatureExpired));
+            // when the deadline has not passed
+            intent.deadline = 10;
+            // = when the nonce isn't the buyer's current nonce
+            intent.nonce = 1;
+            // = * it raises an error
+            result = escrow.create_order_with_sig(accounts.alice, intent, [1; 65]);
+            assert_eq!(result, Err(EscrowError::InvalidNonce));
+            // = when the nonce is the buyer's current nonce
+            intent.nonce = 0;
+            // == when the signature can't be recovered
+            // == * it raises an error
+            result = escrow.create_order_with_sig(accounts.alice, intent, [0; 65]);
+            assert_eq!(result, Err(EscrowError::InvalidSignature));
+            // == when the signature is not the buyer's
+            // == * it raises an error
+            result = escrow.create_order_with_sig(accounts.alice, intent, [1; 65]);
+            assert_eq!(result, Err(EscrowError::InvalidSignature));
+            // == * it does not use up the nonce or create an order
+            assert_eq!(escrow.nonce(accounts.alice), 0);
+            assert_eq!(escrow.orders.length(), 0);
+        }
+
+        #[ink::test]
+        fn test_create_vendor() {
+            let (accounts, mut escrow) = init();
+            // when the ethereum address is too long
+            // * it raises an error
+            let mut result = escrow.create_vendor("0".repeat(MAX_ETHEREUM_ADDRESS_LENGTH + 1));
+            assert_eq!(result, Err(EscrowError::EthereumAddressTooLong));
+            // when account is not a vendor
+            // * it creates a vendor profile for account
+            // * it emits a CreateVendor event (TO DO AFTER HACKATHON)
+            result = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            assert!(result.is_ok());
+            assert!(escrow.vendors.get(accounts.bob).is_some());
+
+            // when account is already a vendor
+            // * it raises an error
+            result = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            assert_eq!(result, Err(EscrowError::VendorAlreadyExists));
+
+            // when the marketplace is curated
+            // * it raises an error
+            escrow.curated = true;
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            assert_eq!(result, Err(EscrowError::CuratedMarketplace));
+        }
+
+        #[ink::test]
+        fn test_decode_error() {
+            let (_accounts, escrow) = init();
+            // when the code belongs to an error
+            // * it returns the error's name
+            assert_eq!(
+                escrow.decode_error(EscrowError::OrderAmountAboveLimit(5).code()),
+                Some("OrderAmountAboveLimit".to_string())
+            );
+            assert_eq!(
+                escrow.decode_error(EscrowError::Unauthorised.code()),
+                Some("Unauthorised".to_string())
+            );
+            // when the code doesn't belong to an error
+            // * it returns None
+            assert_eq!(escrow.decode_error(0), None);
+            assert_eq!(escrow.decode_error(u16::MAX), None);
+        }
+
+        #[ink::test]
+        fn test_deposit_into_listing() {
+            let price_per_unit: Balance = 1_000_000;
+            let (accounts, mut escrow) = init();
+
+            // when listing does not exist
+            // * it raises an error
+            let mut result = escrow.deposit_into_listing(0);
+            assert_eq!(result, Err(EscrowError::ListingNotFound));
+
+            // when listing exists
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+            // = when listing does not belong to caller
+            test_utils::change_caller(accounts.alice);
+            // = * it raises an error
+            result = escrow.deposit_into_listing(0);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when listing belongs to caller
+            test_utils::change_caller(accounts.bob);
+            set_balance(accounts.bob, 10);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1);
+            // == when listing is for a PSP22 token
+            let _ = escrow.create_listing(Some(accounts.django), "USD".to_string(), price_per_unit);
+            // == * it raises an error
+            result = escrow.deposit_into_listing(1);
+            assert_eq!(result, Err(EscrowError::AssetMismatch));
+            // == * it doesn't take the transferred value
+            assert_eq!(escrow.listings.get(1).unwrap().available_amount, 0);
+            assert_eq!(escrow.escrowed_amount, 0);
+            // == when listing is for AZERO
+            // == * it increases the listing available_amount
+            result = escrow.deposit_into_listing(0);
+            assert!(result.is_ok());
+            assert_eq!(escrow.listings.get(0).unwrap().available_amount, 1);
+            // == * it increases the escrowed amount
+            assert_eq!(escrow.escrowed_amount, 1);
+            // == when the deposit would overflow the escrowed amount
+            escrow.escrowed_amount = Balance::MAX;
+            // == * it raises an error
+            result = escrow.deposit_into_listing(0);
+            assert_eq!(result, Err(EscrowError::Overflow));
+            // == * it doesn't take the transferred value
+            assert_eq!(escrow.listings.get(0).unwrap().available_amount, 1);
+            assert_eq!(escrow.escrowed_amount, Balance::MAX);
+        }
+
+        #[ink::test]
+        fn test_deposit_tokens_into_listing() {
+            let price_per_unit: Balance = 1_000_000;
+            let (accounts, mut escrow) = init();
+
+            // when listing does not exist
+            // * it raises an error
+            let mut result = escrow.deposit_tokens_into_listing(0, 5);
+            assert_eq!(result, Err(EscrowError::ListingNotFound));
+
+            // when listing exists
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+            // = when listing does not belong to caller
+            test_utils::change_caller(accounts.alice);
+            // = * it raises an error
+            result = escrow.deposit_tokens_into_listing(0, 5);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when listing belongs to caller
+            test_utils::change_caller(accounts.bob);
+            // == when listing is for AZERO
+            // == * it raises an error
+            result = escrow.deposit_tokens_into_listing(0, 5);
+            assert_eq!(result, Err(EscrowError::AssetMismatch));
+        }
+
+        #[ink::test]
+        fn test_disputes() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            for _ in 0..3 {
+                let _ = escrow.create_order(0, 1);
+            }
+            for id in 0..3 {
+                let _ = escrow.update_order_payment_verification(id, [1; 32], None);
+            }
+            test_utils::change_caller(accounts.bob);
+            // when there are no disputes
+            // * it returns an empty list
+            let mut result: Page<Dispute> = escrow.disputes(0, 10);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 0);
+            // when there are disputes
+            let _ = escrow.dispute_order(2);
+            let _ = escrow.dispute_order(0);
+            // = * it returns them newest first
+            result = escrow.disputes(0, 10);
+            assert_eq!(result.total, 2);
+            assert_eq!(result.items[0].order_id, 0);
+            assert_eq!(result.items[0].raised_by, accounts.bob);
+            assert_eq!(result.items[1].order_id, 2);
+            // = * it paginates
+            result = escrow.disputes(1, 1);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].order_id, 2);
+            result = escrow.disputes(2, 1);
+            assert_eq!(result.items.len(), 0);
+            // = when a dispute is resolved
+            let _ = escrow.force_cancel_order(2, CancelReason::Other);
+            // = * it records when
+            result = escrow.disputes(0, 10);
+            assert!(result.items[0].resolved_at.is_none());
+            assert!(result.items[1].resolved_at.is_some());
+        }
+
+        #[ink::test]
+        fn test_is_blacklisted() {
+            let (accounts, mut escrow) = init();
+            // when account is not blacklisted
+            // * it returns false
+            assert!(!escrow.is_blacklisted(accounts.django));
+            // when account is blacklisted
+            // * it returns true
+            escrow.blacklist.insert(accounts.django, &());
+            assert!(escrow.is_blacklisted(accounts.django));
+        }
+
+        #[ink::test]
+        fn test_listing_exposure() {
+            let (accounts, mut escrow) = init();
+            // when listing does not exist
+            // * it returns None
+            assert!(escrow.listing_exposure(0).is_none());
+            // when listing exists
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            // = when there are no orders
+            // = * it returns no exposure
+            assert_eq!(escrow.listing_exposure(0), Some(ListingExposure::default()));
+            // = when there are orders
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 2);
+            let _ = escrow.create_order(0, 3);
+            let _ = escrow.create_order(0, 4);
+            let _ = escrow.update_order_payment_verification(1, [1; 32], None);
+            // = * it counts open and pending orders
+            let mut exposure: ListingExposure = escrow.listing_exposure(0).unwrap();
+            assert_eq!(exposure.orders_length, 3);
+            assert_eq!(exposure.amount, 9);
+            // = * it excludes finalised and cancelled orders
+            let _ = escrow.cancel_order(2);
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.finalise_order(0);
+            exposure = escrow.listing_exposure(0).unwrap();
+            assert_eq!(exposure.orders_length, 1);
+            assert_eq!(exposure.amount, 3);
+        }
+
+        #[ink::test]
+        fn test_listings() {
+            let (_accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            // when there are no listings
+            // * it returns an empty page
+            let mut result = escrow.listings(None, 10, Direction::Descending);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 0);
+            assert!(!result.has_more);
+            // when there are listings
+            for _ in 0..3 {
+                let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            }
+            // = when descending
+            // = * it returns the newest listings first
+            result = escrow.listings(None, 2, Direction::Descending);
+            assert_eq!(result.total, 3);
+            assert_eq!(result.items.len(), 2);
+            assert_eq!(result.items[0].id, 2);
+            assert_eq!(result.items[1].id, 1);
+            assert!(result.has_more);
+            // = * it continues before the cursor
+            result = escrow.listings(Some(1), 2, Direction::Descending);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].id, 0);
+            assert!(!result.has_more);
+            result = escrow.listings(Some(0), 2, Direction::Descending);
+            assert_eq!(result.items.len(), 0);
+            // = when ascending
+            // = * it returns the oldest listings first
+            result = escrow.listings(None, 2, Direction::Ascending);
+            assert_eq!(result.items.len(), 2);
+            assert_eq!(result.items[0].id, 0);
+            assert_eq!(result.items[1].id, 1);
+            assert!(result.has_more);
+            // = * it continues after the cursor
+            result = escrow.listings(Some(1), 2, Direction::Ascending);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].id, 2);
+            assert!(!result.has_more);
+            // = when cursor is at or past the last listing
+            // = * it returns an empty page
+            result = escrow.listings(Some(2), 2, Direction::Ascending);
+            assert_eq!(result.items.len(), 0);
+            result = escrow.listings(Some(u32::MAX), 2, Direction::Ascending);
+            assert_eq!(result.items.len(), 0);
+            // = * it returns summaries of the listings
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(1);
+            result = escrow.listings(None, 2, Direction::Ascending);
+            assert_eq!(
+                result.items[1].vendor,
+                escrow.show_listing(1).unwrap().vendor
+            );
+            assert_eq!(result.items[1].available_amount, 10);
+            assert_eq!(result.items[1].price_per_unit, 1);
+            assert!(result.items[1].active);
+            // == * it reports listings without liquidity as inactive
+            assert!(!result.items[0].active);
+            // = when cursor is past the last listing and descending
+            // = * it starts from the newest listing
+            result = escrow.listings(Some(u32::MAX), 1, Direction::Descending);
+            assert_eq!(result.items[0].id, 2);
+        }
+
+        #[ink::test]
+        fn test_listings_count() {
+            let (_accounts, mut escrow) = init();
+            // * it returns the number of listings
+            assert_eq!(escrow.listings_count(), 0);
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            assert_eq!(escrow.listings_count(), 2);
+        }
+
+        #[ink::test]
+        fn test_listings_by_pair() {
+            let price_per_unit: Balance = 1_000_000;
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+
+            // when there are no listings for the pair
+            // * it returns an empty page
+            let mut result = escrow.listings_by_pair(None, "USD".to_string(), 0, 10);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 0);
+            // when there are listings for the pair
+            let _ = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+            let _ = escrow.create_listing(Some(accounts.django), "USD".to_string(), price_per_unit);
+            let _ = escrow.create_listing(None, "EUR".to_string(), price_per_unit);
+            let _ = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+            // * it only returns the listings matching both the asset and currency, newest first
+            result = escrow.listings_by_pair(None, "USD".to_string(), 0, 10);
+            assert_eq!(result.total, 2);
+            assert_eq!(result.items.len(), 2);
+            assert_eq!(result.items[0].id, 3);
+            assert_eq!(result.items[1].id, 0);
+            result = escrow.listings_by_pair(Some(accounts.django), "USD".to_string(), 0, 10);
+            assert_eq!(result.total, 1);
+            assert_eq!(result.items[0].id, 1);
+            // = when page is past the last listing
+            // = * it returns an empty page
+            result = escrow.listings_by_pair(None, "USD".to_string(), 1, 2);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 2);
+            // = when page is partially filled
+            // = * it returns the remaining listings
+            result = escrow.listings_by_pair(None, "USD".to_string(), 1, 1);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].id, 0);
+        }
+
+        #[ink::test]
+        fn test_listings_with_liquidity() {
+            let (_accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            for amount in [5, 0, 10, 20] {
+                let _ = escrow.create_listing(None, "USD".to_string(), 1);
+                ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(amount);
+                let _ = escrow.deposit_into_listing(escrow.listings.length() - 1);
+            }
+            // * it only returns listings with at least the minimum amount available
+            let mut result: ScanPage<u32, ListingSummary> =
+                escrow.listings_with_liquidity(10, None, 10, Direction::Descending);
+            assert_eq!(result.items.len(), 2);
+            assert_eq!(result.items[0].id, 3);
+            assert_eq!(result.items[1].id, 2);
+            assert_eq!(result.next_after_id, None);
+            // * it respects the cursor and limit
+            result = escrow.listings_with_liquidity(1, Some(0), 1, Direction::Ascending);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].id, 2);
+            assert_eq!(result.next_after_id, Some(2));
+            // when minimum amount is zero
+            // * it still skips empty listings
+            result = escrow.listings_with_liquidity(0, None, 10, Direction::Ascending);
+            assert_eq!(result.items.len(), 3);
+        }
+
+        #[ink::test]
+        fn test_onboard_vendor() {
+            let (accounts, mut escrow) = init();
+            escrow.curated = true;
+            // when called by someone without the moderator role
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.onboard_vendor(
+                accounts.charlie,
+                "0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string(),
+            );
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by a moderator
+            test_utils::change_caller(accounts.bob);
+            // = when account is blacklisted
+            // = * it raises an error
+            escrow.blacklist.insert(accounts.django, &());
+            result = escrow.onboard_vendor(
+                accounts.django,
+                "0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string(),
+            );
+            assert_eq!(result, Err(EscrowError::Blacklisted));
+            // = when account is not a vendor
+            result = escrow.onboard_vendor(
+                accounts.charlie,
+                "0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string(),
+            );
+            assert!(result.is_ok());
+            // = * it creates a vendor profile for the account
+            assert!(escrow.vendors.get(accounts.charlie).is_some());
+            // = when account is already a vendor
+            // = * it raises an error
+            result = escrow.onboard_vendor(
+                accounts.charlie,
+                "0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string(),
+            );
+            assert_eq!(result, Err(EscrowError::VendorAlreadyExists));
+        }
+
+        #[ink::test]
+        fn test_open_disputes() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            for _ in 0..3 {
+                let _ = escrow.create_order(0, 1);
+            }
+            for id in 0..3 {
+                let _ = escrow.update_order_payment_verification(id, [1; 32], None);
+            }
+            test_utils::change_caller(accounts.bob);
+            // when there are no disputes
+            // * it returns an empty page
+            assert_eq!(escrow.open_disputes(0, 10).total, 0);
+            // when there are disputes
+            let _ = escrow.dispute_order(2);
+            let _ = escrow.dispute_order(0);
+            // = * it returns them newest first
+            let mut disputes: Page<Dispute> = escrow.open_disputes(0, 10);
+            assert_eq!(disputes.total, 2);
+            assert_eq!(disputes.items[0].order_id, 0);
+            assert_eq!(disputes.items[1].order_id, 2);
+            // = * it returns them a page at a time
+            disputes = escrow.open_disputes(1, 1);
+            assert_eq!(disputes.items.len(), 1);
+            assert_eq!(disputes.items[0].order_id, 2);
+            assert!(!disputes.has_more);
+            // = when a dispute is resolved
+            let _ = escrow.force_cancel_order(2, CancelReason::Other);
+            // = * it is no longer returned
+            disputes = escrow.open_disputes(0, 10);
+            assert_eq!(disputes.total, 1);
+            assert_eq!(disputes.items[0].order_id, 0);
+        }
+
+        #[ink::test]
+        fn test_order_detail() {
+            let (accounts, mut escrow) = init();
+            // when order does not exist
+            // * it raises an error
+            assert_eq!(
+                escrow.order_detail(0).err(),
+                Some(EscrowError::OrderNotFound)
+            );
+            // when order exists
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 3);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 5);
+            // * it returns the order with its listing and parties
+            let order_detail: OrderDetail = escrow.order_detail(0).unwrap();
+            assert_eq!(order_detail.order.id, 0);
+            assert_eq!(order_detail.listing.id, 0);
+            assert_eq!(order_detail.listing.price_per_unit, 3);
+            assert_eq!(order_detail.listing.available_amount, 5);
+            assert_eq!(order_detail.vendor.unwrap().account, accounts.bob);
+            // * it has the buyer's order history
+            assert_eq!(order_detail.buyer.orders_length, 1);
+            // when the order's listing doesn't exist
+            // * it raises an error
+            escrow.listings.values.remove(0);
+            assert_eq!(
+                escrow.order_detail(0).err(),
+                Some(EscrowError::ListingNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn test_orders() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            // when there are no orders
+            // * it returns an empty page
+            let mut result = escrow.orders(None, 10, Direction::Descending);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 0);
+            // when there are orders
+            test_utils::change_caller(accounts.charlie);
+            for _ in 0..3 {
+                let _ = escrow.create_order(0, 1);
+            }
+            // = when descending
+            // = * it returns the newest orders first
+            result = escrow.orders(None, 2, Direction::Descending);
+            assert_eq!(result.total, 3);
+            assert_eq!(result.items.len(), 2);
+            assert_eq!(result.items[0].id, 2);
+            assert_eq!(result.items[1].id, 1);
+            // = * it continues before the cursor
+            result = escrow.orders(Some(1), 2, Direction::Descending);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].id, 0);
+            // = when ascending
+            // = * it returns the oldest orders first
+            result = escrow.orders(None, 2, Direction::Ascending);
+            assert_eq!(result.items[0].id, 0);
+            assert_eq!(result.items[1].id, 1);
+            // = * it continues after the cursor
+            result = escrow.orders(Some(1), 2, Direction::Ascending);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].id, 2);
+        }
+
+        #[ink::test]
+        fn test_orders_count() {
+            let (accounts, mut escrow) = init();
+            // * it returns the number of orders
+            assert_eq!(escrow.orders_count(), 0);
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            assert_eq!(escrow.orders_count(), 1);
+        }
+
+        #[ink::test]
+        fn test_orders_by_status() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+
+            // when the status is unknown
+            // * it raises an error
+            assert_eq!(
+                escrow.orders_by_status(5, 0, 10).unwrap_err(),
+                EscrowError::InvalidStatus
+            );
+            // when there are no orders with the status
+            // * it returns an empty page
+            let mut result = escrow.orders_by_status(1, 0, 10).unwrap();
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 0);
+            // when there are orders with the status
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.update_order_payment_verification(2, [1; 32], None);
+            let _ = escrow.update_order_payment_verification(0, [1; 32], None);
+            // * it returns the orders in the order they reached the status, newest first
+            result = escrow.orders_by_status(1, 0, 10).unwrap();
+            assert_eq!(result.total, 2);
+            assert_eq!(result.items[0].id, 0);
+            assert_eq!(result.items[1].id, 2);
+            result = escrow.orders_by_status(0, 0, 10).unwrap();
+            assert_eq!(result.total, 1);
+            assert_eq!(result.items[0].id, 1);
+            // when an order moves to another status
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.finalise_order(2);
+            // * it is moved to the new status' orders
+            result = escrow.orders_by_status(1, 0, 10).unwrap();
+            assert_eq!(result.total, 1);
+            assert_eq!(result.items[0].id, 0);
+            result = escrow.orders_by_status(2, 0, 10).unwrap();
+            assert_eq!(result.total, 1);
+            assert_eq!(result.items[0].id, 2);
+        }
+
+        #[ink::test]
+        fn test_orders_created_between() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            // when there are no orders
+            // * it returns an empty page
+            let mut result = escrow.orders_created_between(0, 100, 0, 10);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 0);
+            // when there are orders
+            test_utils::change_caller(accounts.charlie);
+            for timestamp in [10, 20, 20, 30, 40] {
+                ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(timestamp);
+                let _ = escrow.create_order(0, 1);
+            }
+            // * it returns the orders created in the range, oldest first
+            result = escrow.orders_created_between(20, 40, 0, 10);
+            assert_eq!(result.total, 3);
+            assert_eq!(result.items.len(), 3);
+            assert_eq!(result.items[0].id, 1);
+            assert_eq!(result.items[2].id, 3);
+            // * it pages through the range
+            result = escrow.orders_created_between(20, 40, 1, 2);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].id, 3);
+            // = when page is past the range
+            // = * it returns an empty page
+            result = escrow.orders_created_between(20, 40, 2, 2);
+            assert_eq!(result.items.len(), 0);
+            // = when range is empty or reversed
+            // = * it returns an empty page
+            result = escrow.orders_created_between(41, 100, 0, 10);
+            assert_eq!(result.total, 0);
+            result = escrow.orders_created_between(40, 20, 0, 10);
+            assert_eq!(result.total, 0);
+        }
+
+        #[ink::test]
+        fn test_orders_for_buyer() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+
+            // when buyer has no orders
+            // * it returns an empty page
+            let mut result = escrow.orders_for_buyer(accounts.charlie, 0, 10);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 0);
+            // when buyer has orders
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            test_utils::change_caller(accounts.django);
+            let _ = escrow.create_order(0, 1);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            // * it only returns the buyer's orders, newest first
+            result = escrow.orders_for_buyer(accounts.charlie, 0, 10);
+            assert_eq!(result.total, 2);
+            assert_eq!(result.items.len(), 2);
+            assert_eq!(result.items[0].id, 2);
+            assert_eq!(result.items[1].id, 0);
+            assert!(!result.has_more);
+            // * it returns summaries of the orders
+            let order: Order = escrow.show_order(2).unwrap();
+            assert_eq!(result.items[0].buyer, order.buyer);
+            assert_eq!(result.items[0].vendor, order.vendor);
+            assert_eq!(result.items[0].amount, order.amount);
+            assert_eq!(result.items[0].status, order.status());
+            // = when page is past the last order
+            // = * it returns an empty page
+            result = escrow.orders_for_buyer(accounts.charlie, 1, 2);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 2);
+            // = when page is partially filled
+            // = * it returns the remaining orders
+            result = escrow.orders_for_buyer(accounts.charlie, 1, 1);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].id, 0);
+            assert!(!result.has_more);
+            result = escrow.orders_for_buyer(accounts.charlie, 0, 1);
+            assert!(result.has_more);
+            // when one of the buyer's orders is no longer stored
+            escrow.orders.values.remove(2);
+            // * it skips the order
+            result = escrow.orders_for_buyer(accounts.charlie, 0, 10);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].id, 0);
+        }
+
+        #[ink::test]
+        fn test_orders_for_vendor() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            let _ = escrow.deposit_into_listing(1);
+
+            // when vendor has no orders
+            // * it returns an empty page
+            let mut result = escrow.orders_for_vendor(accounts.bob, 0, 10);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 0);
+            // when vendor has orders
+            test_utils::change_caller(accounts.django);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.create_order(1, 1);
+            let _ = escrow.create_order(0, 1);
+            // * it only returns the vendor's orders, newest first
+            result = escrow.orders_for_vendor(accounts.bob, 0, 10);
+            assert_eq!(result.total, 2);
+            assert_eq!(result.items.len(), 2);
+            assert_eq!(result.items[0].id, 2);
+            assert_eq!(result.items[1].id, 0);
+            result = escrow.orders_for_vendor(accounts.charlie, 0, 10);
+            assert_eq!(result.total, 1);
+            assert_eq!(result.items[0].id, 1);
+        }
+
+        #[ink::test]
+        fn test_pause() {
+            let price_per_unit: Balance = 1_000_000;
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), price_per_unit);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            // when called by someone without the pauser role
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.pause(PAUSE_CREATE_ORDER);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by a pauser
+            test_utils::change_caller(accounts.bob);
+            escrow.grant_role(Role::Pauser, accounts.charlie).unwrap();
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.pause(PAUSE_CREATE_ORDER);
+            assert!(result.is_ok());
+            // * it only blocks the given operations
+            assert_eq!(
+                escrow.paused_operations.get_or_default(),
+                PAUSE_CREATE_ORDER
+            );
+            test_utils::change_caller(accounts.alice);
+            assert_eq!(escrow.create_order(0, 5), Err(EscrowError::Paused));
+            test_utils::change_caller(accounts.bob);
+            assert!(escrow.deposit_into_listing(0).is_ok());
+            // * it adds to the operations already paused
+            test_utils::change_caller(accounts.charlie);
+            result = escrow
+                .pause(PAUSE_CREATE_LISTING | PAUSE_CREATE_VENDOR | PAUSE_DEPOSIT | PAUSE_WITHDRAW);
+            assert!(result.is_ok());
+            test_utils::change_caller(accounts.bob);
+            assert_eq!(
+                escrow.create_listing(None, "USD".to_string(), price_per_unit),
+                Err(EscrowError::Paused)
+            );
+            assert_eq!(escrow.deposit_into_listing(0), Err(EscrowError::Paused));
+            assert_eq!(
+                escrow.deposit_tokens_into_listing(0, 1),
+                Err(EscrowError::Paused)
+            );
+            assert_eq!(escrow.withdraw_from_listing(0, 1), Err(EscrowError::Paused));
+            test_utils::change_caller(accounts.alice);
+            assert_eq!(escrow.create_order(0, 5), Err(EscrowError::Paused));
+            assert_eq!(
+                escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string()),
+                Err(EscrowError::Paused)
+            );
+        }
+
+        #[ink::test]
+        fn test_pause_listing() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            // when listing does not exist
+            // * it raises an error
+            let mut result = escrow.pause_listing(1, true);
+            assert_eq!(result, Err(EscrowError::ListingNotFound));
+            // when listing exists
+            // = when called by someone other than the vendor
+            // = * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.pause_listing(0, true);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when called by the vendor
+            test_utils::change_caller(accounts.bob);
+            result = escrow.pause_listing(0, true);
+            assert!(result.is_ok());
+            // = * it stops the listing from taking orders
+            assert!(!escrow.show_listing(0).unwrap().is_active(0));
+            test_utils::change_caller(accounts.charlie);
+            assert_eq!(escrow.create_order(0, 1), Err(EscrowError::ListingPaused));
+            // = * it can be unpaused
+            test_utils::change_caller(accounts.bob);
+            result = escrow.pause_listing(0, false);
+            assert!(result.is_ok());
+            test_utils::change_caller(accounts.charlie);
+            assert!(escrow.create_order(0, 1).is_ok());
+        }
+
+        #[ink::test]
+        fn test_perform_upkeep() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            for _ in 0..4 {
+                let _ = escrow.create_listing(None, "USD".to_string(), 1);
+                ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+                let _ = escrow.deposit_into_listing(escrow.listings_count() - 1);
+            }
+            let _ = escrow.update_listing(1, 1, Some(5));
+            let _ = escrow.withdraw_from_listing(3, 8);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.create_order(2, 8);
+            test_utils::change_caller(accounts.bob);
+            escrow.set_upkeep_bounty(2).unwrap();
+            escrow.accrued_fees.insert(None::<AccountId>, &5);
+            set_balance(accounts.django, 0);
+            // when too many orders or listings are given
+            // * it raises an error
+            test_utils::change_caller(accounts.django);
+            let mut result = escrow.perform_upkeep(vec![0; MAX_BATCH_SIZE + 1], vec![]);
+            assert_eq!(result, Err(EscrowError::BatchTooLarge));
+            result = escrow.perform_upkeep(vec![], vec![0; MAX_BATCH_SIZE + 1]);
+            assert_eq!(result, Err(EscrowError::BatchTooLarge));
+            // when nothing is due
+            // * it leaves everything as it is and pays no bounty
+            result = escrow.perform_upkeep(vec![0, 1], vec![0, 1, 2]);
+            assert!(result.is_ok());
+            assert_eq!(escrow.show_order(0).unwrap().status(), 0);
+            assert!(!escrow.show_listing(1).unwrap().closed);
+            assert_eq!(get_balance(accounts.django), 0);
+            // when duties are due
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_PAYMENT_WINDOW,
+            );
+            update_limits(&mut escrow, |limits| limits.min_order_amount = 3);
+            result = escrow.perform_upkeep(vec![0, 5], vec![0, 1, 2, 3, 7]);
+            assert!(result.is_ok());
+            // * it expires the orders that are due
+            assert_eq!(escrow.show_order(0).unwrap().status(), 3);
+            // * it closes expired listings
+            assert!(escrow.show_listing(1).unwrap().closed);
+            // * it closes listings with only dust left
+            assert!(escrow.show_listing(3).unwrap().closed);
+            // * it keeps listings with only dust left open while they have open orders
+            assert!(!escrow.show_listing(2).unwrap().closed);
+            // * it skips the rest
+            assert!(!escrow.show_listing(0).unwrap().closed);
+            // * it pays the bounty out of the accrued fees, as far as they go
+            assert_eq!(get_balance(accounts.django), 5);
+            assert_eq!(escrow.accrued_fees.get(None::<AccountId>), Some(0));
+        }
+
+        #[ink::test]
+        fn test_pending_verifications_for_vendor() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            // when vendor has no orders awaiting verification
+            // * it returns an empty page
+            let mut result = escrow.pending_verifications_for_vendor(accounts.bob, 0, 10);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 0);
+            // when vendor has orders awaiting verification
+            test_utils::change_caller(accounts.charlie);
+            for _ in 0..4 {
+                let _ = escrow.create_order(0, 1);
+            }
+            for id in 1..4 {
+                let _ = escrow.update_order_payment_verification(id, [1; 32], None);
+            }
+            // * it only returns those orders, newest first
+            result = escrow.pending_verifications_for_vendor(accounts.bob, 0, 10);
+            assert_eq!(result.total, 3);
+            assert_eq!(result.items[0].id, 3);
+            assert_eq!(result.items[1].id, 2);
+            assert_eq!(result.items[2].id, 1);
+            // * it excludes other vendors' orders
+            result = escrow.pending_verifications_for_vendor(accounts.charlie, 0, 10);
+            assert_eq!(result.total, 0);
+            // = when vendor reviews an order
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.finalise_order(2);
+            let _ = escrow.dispute_order(3);
+            // = * it drops out of the queue
+            result = escrow.pending_verifications_for_vendor(accounts.bob, 0, 10);
+            assert_eq!(result.total, 1);
+            assert_eq!(result.items[0].id, 1);
+        }
+
+        #[ink::test]
+        fn test_queue_action() {
+            let (accounts, mut escrow) = init();
+            // when called by someone without the admin role
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.queue_action(AdminAction::FeeBps(50));
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by an admin
+            test_utils::change_caller(accounts.bob);
+            // = when setting a fee above the maximum
+            // = * it raises an error
+            result = escrow.queue_action(AdminAction::FeeBps(MAX_FEE_BPS + 1));
+            assert_eq!(result, Err(EscrowError::InvalidFee));
+            // = when setting signers with duplicates
+            // = * it raises an error
+            result = escrow.queue_action(AdminAction::Signers(
+                vec![accounts.charlie, accounts.charlie],
+                1,
+            ));
+            assert_eq!(result, Err(EscrowError::InvalidSigners));
+            // = when setting a threshold above the number of signers
+            // = * it raises an error
+            result = escrow.queue_action(AdminAction::Signers(vec![accounts.charlie], 2));
+            assert_eq!(result, Err(EscrowError::InvalidSigners));
+            // = when setting signers without a threshold
+            // = * it raises an error
+            result = escrow.queue_action(AdminAction::Signers(vec![accounts.charlie], 0));
+            assert_eq!(result, Err(EscrowError::InvalidSigners));
+            // = when the timelock delay would run past the largest timestamp
+            // = * it raises an error
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5);
+            escrow.timelock_delay = Timestamp::MAX;
+            result = escrow.queue_action(AdminAction::FeeBps(50));
+            assert_eq!(result, Err(EscrowError::Overflow));
+            escrow.timelock_delay = DEFAULT_TIMELOCK_DELAY;
+            // = when action is valid
+            result = escrow.queue_action(AdminAction::FeeBps(50));
+            assert!(result.is_ok());
+            // = * it queues the action to be executable after the timelock delay
+            let queued_action: QueuedAction = escrow.queued_action(0).unwrap();
+            assert_eq!(queued_action.action, AdminAction::FeeBps(50));
+            assert_eq!(queued_action.executable_at, 5 + DEFAULT_TIMELOCK_DELAY);
+            assert_eq!(escrow.queued_actions_length, 1);
+            // = * it does not apply the action yet
+            assert_eq!(escrow.fee_bps.get_or_default(), 0);
+            // = * it does not approve the action for a caller who is not a signer
+            assert_eq!(queued_action.approvals, vec![]);
+            // when called by a signer without the admin role
+            escrow.signers = vec![accounts.charlie];
+            escrow.threshold = 1;
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.queue_action(AdminAction::FeeBps(50));
+            assert!(result.is_ok());
+            // * it queues the action with the signer's approval
+            assert_eq!(
+                escrow.queued_action(1).unwrap().approvals,
+                vec![accounts.charlie]
+            );
+        }
+
+        #[ink::test]
+        fn test_rate_order() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 5);
+            // when the rating is outside 1 to 5
+            // * it raises an error
+            let mut result = escrow.rate_order(0, 0, None);
+            assert_eq!(result, Err(EscrowError::InvalidRating));
+            result = escrow.rate_order(0, 6, None);
+            assert_eq!(result, Err(EscrowError::InvalidRating));
+            // when order does not exist
+            // * it raises an error
+            result = escrow.rate_order(1, 5, None);
+            assert_eq!(result, Err(EscrowError::OrderNotFound));
+            // when order isn't finalised
+            // * it raises an error
+            result = escrow.rate_order(0, 5, None);
+            assert_eq!(result, Err(EscrowError::OrderNotFinalised));
+            // when order is finalised
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.finalise_order(0);
+            // = when called by someone other than the buyer or vendor
+            // = * it raises an error
+            test_utils::change_caller(accounts.django);
+            result = escrow.rate_order(0, 5, None);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when called by the buyer
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.rate_order(0, 4, None);
+            assert!(result.is_ok());
+            // = * it stores the rating on the order
+            let mut state: OrderState = escrow.orders.values.get(0).unwrap().state;
+            assert_eq!(state.rating_by_buyer(), Some(4));
+            assert_eq!(state.rating_by_vendor(), None);
+            // = * it counts towards the vendor's ratings
+            let mut reputation: PartyReputation = escrow.reputation(accounts.bob).as_vendor;
+            assert_eq!(reputation.ratings_length, 1);
+            assert_eq!(reputation.ratings_total, 4);
+            assert_eq!(reputation.average_rating, 400);
+            // == when the buyer amends the rating within the amend window
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                RATING_AMEND_WINDOW,
+            );
+            result = escrow.rate_order(0, 3, Some([1; 32]));
+            assert!(result.is_ok());
+            // == * it replaces the rating and review
+            state = escrow.orders.values.get(0).unwrap().state;
+            assert_eq!(state.rating_by_buyer(), Some(3));
+            assert_eq!(escrow.order_review(0, accounts.charlie), Some([1; 32]));
+            // == * it replaces the rating in the vendor's ratings
+            reputation = escrow.reputation(accounts.bob).as_vendor;
+            assert_eq!(reputation.ratings_length, 1);
+            assert_eq!(reputation.ratings_total, 3);
+            assert_eq!(reputation.average_rating, 300);
+            // == when the buyer rates again after the amend window
+            // == * it raises an error
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                RATING_AMEND_WINDOW + 1,
+            );
+            result = escrow.rate_order(0, 5, None);
+            assert_eq!(result, Err(EscrowError::AlreadyRated));
+            // = when called by the vendor
+            test_utils::change_caller(accounts.bob);
+            result = escrow.rate_order(0, 2, Some([2; 32]));
+            assert!(result.is_ok());
+            // = * it stores the review hash
+            assert_eq!(escrow.order_review(0, accounts.bob), Some([2; 32]));
+            // = * it stores the rating on the order
+            state = escrow.orders.values.get(0).unwrap().state;
+            assert_eq!(state.rating_by_buyer(), Some(3));
+            assert_eq!(state.rating_by_vendor(), Some(2));
+            // = * it counts towards the buyer's ratings
+            reputation = escrow.reputation(accounts.charlie).as_buyer;
+            assert_eq!(reputation.ratings_length, 1);
+            assert_eq!(reputation.ratings_total, 2);
+            assert_eq!(escrow.reputation(accounts.bob).as_vendor.ratings_total, 3);
+            assert_eq!(escrow.reputation(accounts.bob).as_buyer.ratings_length, 0);
+            // == when the vendor amends the rating without a review
+            // == * it removes the review
+            result = escrow.rate_order(0, 2, None);
+            assert!(result.is_ok());
+            assert_eq!(escrow.order_review(0, accounts.bob), None);
+            // == when the vendor rates again after the amend window
+            // == * it raises an error
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                2 * RATING_AMEND_WINDOW + 2,
+            );
+            result = escrow.rate_order(0, 5, None);
+            assert_eq!(result, Err(EscrowError::AlreadyRated));
+        }
+
+        #[ink::test]
+        fn test_remove_from_blacklist() {
+            let (accounts, mut escrow) = init();
+            escrow.add_to_blacklist(accounts.django).unwrap();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.remove_from_blacklist(accounts.django);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.remove_from_blacklist(accounts.django);
+            assert!(result.is_ok());
+            // * it removes the account from the blacklist
+            assert!(!escrow.is_blacklisted(accounts.django));
+            test_utils::change_caller(accounts.django);
+            result = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            assert!(result.is_ok());
+        }
+
+        #[ink::test]
+        fn test_remove_payment_processor() {
+            let (accounts, mut escrow) = init();
+            escrow.add_payment_processor([2; 33]).unwrap();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.remove_payment_processor([2; 33]);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.remove_payment_processor([2; 33]);
+            assert!(result.is_ok());
+            // * it deregisters the public key
+            assert!(!escrow.is_payment_processor([2; 33]));
+        }
+
+        #[ink::test]
+        fn test_remove_subscriber() {
+            let (accounts, mut escrow) = init();
+            escrow.add_subscriber(accounts.django).unwrap();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.remove_subscriber(accounts.django);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.remove_subscriber(accounts.django);
+            assert!(result.is_ok());
+            // * it removes the subscriber
+            assert!(escrow.subscribers().is_empty());
+        }
+
+        #[ink::test]
+        fn test_renounce_ownership() {
+            let (accounts, mut escrow) = init();
+            // when called by non-owner
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.renounce_ownership();
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by owner
+            test_utils::change_caller(accounts.bob);
+            // = when funds are escrowed
+            escrow.escrowed_amount = 1;
+            // = * it raises an error
+            result = escrow.renounce_ownership();
+            assert_eq!(result, Err(EscrowError::EscrowNotEmpty));
+            // = when there are open orders
+            escrow.escrowed_amount = 0;
+            escrow.orders.active_length = 1;
+            // = * it raises an error
+            result = escrow.renounce_ownership();
+            assert_eq!(result, Err(EscrowError::EscrowNotEmpty));
+            // = when the contract is empty
+            escrow.orders.active_length = 0;
+            escrow.transfer_ownership(accounts.alice).unwrap();
+            result = escrow.renounce_ownership();
+            assert!(result.is_ok());
+            // = * it removes the owner
+            assert_eq!(escrow.ownable.owner(), AccountId::from([0; 32]));
+            // = * it clears the pending owner
+            assert_eq!(escrow.pending_owner, None);
+        }
+
+        #[ink::test]
+        fn test_reputation() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            // when the account has no ratings or settled orders
+            // * it returns an empty reputation
+            assert_eq!(escrow.reputation(accounts.bob), Reputation::default());
+            // when the buyer submits payment verification
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.create_order(0, 1);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+            escrow
+                .update_order_payment_verification(0, [0; 32], None)
+                .unwrap();
+            // * it keeps a running average of the buyer's time to pay
+            let mut buyer_reputation: Reputation = escrow.reputation(accounts.charlie);
+            assert_eq!(buyer_reputation.payments_length, 1);
+            assert_eq!(buyer_reputation.average_time_to_pay, 60_000);
+            // = when the payment verification is updated
+            // = * it does not count again
+            escrow
+                .update_order_payment_verification(0, [1; 32], None)
+                .unwrap();
+            assert_eq!(escrow.reputation(accounts.charlie), buyer_reputation);
+            // when an order is finalised
+            test_utils::change_caller(accounts.bob);
+            escrow.finalise_order(0).unwrap();
+            // * it counts towards the vendor's completion rate
+            let mut reputation: PartyReputation = escrow.reputation(accounts.bob).as_vendor;
+            assert_eq!(reputation.finalised_orders_length, 1);
+            assert_eq!(reputation.completion_rate_bps, 10_000);
+            // * it counts towards the buyer's completion rate
+            buyer_reputation = escrow.reputation(accounts.charlie);
+            assert_eq!(buyer_reputation.as_buyer.finalised_orders_length, 1);
+            assert_eq!(buyer_reputation.as_buyer.completion_rate_bps, 10_000);
+            assert_eq!(buyer_reputation.as_vendor, PartyReputation::default());
+            // when an order is cancelled
+            test_utils::change_caller(accounts.charlie);
+            escrow.cancel_order(1).unwrap();
+            // * it lowers the vendor's and buyer's completion rates
+            reputation = escrow.reputation(accounts.bob).as_vendor;
+            assert_eq!(reputation.cancelled_orders_length, 1);
+            assert_eq!(reputation.completion_rate_bps, 5_000);
+            reputation = escrow.reputation(accounts.charlie).as_buyer;
+            assert_eq!(reputation.cancelled_orders_length, 1);
+            assert_eq!(reputation.completion_rate_bps, 5_000);
+            // when the account is rated
+            escrow.rate_order(0, 5, None).unwrap();
+            test_utils::change_caller(accounts.bob);
+            escrow.finalise_order(2).unwrap();
+            test_utils::change_caller(accounts.charlie);
+            escrow.rate_order(2, 2, None).unwrap();
+            // * it keeps a running average of the ratings in hundredths of a star
+            reputation = escrow.reputation(accounts.bob).as_vendor;
+            assert_eq!(reputation.ratings_length, 2);
+            assert_eq!(reputation.average_rating, 350);
+            assert_eq!(reputation.completion_rate_bps, 6_666);
+            // when activity is spread across epochs
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                REPUTATION_EPOCH + 60_000,
+            );
+            let _ = escrow.create_order(0, 1);
+            test_utils::change_caller(accounts.bob);
+            escrow.finalise_order(3).unwrap();
+            test_utils::change_caller(accounts.charlie);
+            escrow.rate_order(3, 1, None).unwrap();
+            // * it weights recent epochs more heavily than older ones
+            reputation = escrow.reputation(accounts.bob).as_vendor;
+            assert_eq!(reputation.ratings_length, 3);
+            assert_eq!(reputation.average_rating, 250);
+            assert_eq!(reputation.completion_rate_bps, 7_692);
+            // when all activity is older than the epochs covered
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                REPUTATION_EPOCH * (REPUTATION_EPOCHS + 1),
+            );
+            // * it keeps the lifetime counts but drops the averages
+            reputation = escrow.reputation(accounts.bob).as_vendor;
+            assert_eq!(reputation.ratings_length, 3);
+            assert_eq!(reputation.average_rating, 0);
+            assert_eq!(reputation.completion_rate_bps, 0);
+            // when disputes are settled
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.create_order(0, 1);
+            escrow
+                .update_order_payment_verification(4, [0; 32], None)
+                .unwrap();
+            escrow
+                .update_order_payment_verification(5, [0; 32], None)
+                .unwrap();
+            test_utils::change_caller(accounts.bob);
+            escrow.dispute_order(4).unwrap();
+            escrow.dispute_order(5).unwrap();
+            escrow.finalise_order(4).unwrap();
+            escrow.cancel_order(5).unwrap();
+            // * it records the outcome for both parties
+            reputation = escrow.reputation(accounts.bob).as_vendor;
+            assert_eq!(reputation.disputes_won_length, 1);
+            assert_eq!(reputation.disputes_lost_length, 1);
+            reputation = escrow.reputation(accounts.charlie).as_buyer;
+            assert_eq!(reputation.disputes_won_length, 1);
+            assert_eq!(reputation.disputes_lost_length, 1);
+            // * it weighs the outcomes into the completion rate
+            assert_eq!(reputation.completion_rate_bps, 4_000);
+            escrow
+                .set_dispute_weights(DisputeWeights {
+                    won_bps: 0,
+                    lost_bps: 0,
+                })
+                .unwrap();
+            reputation = escrow.reputation(accounts.bob).as_vendor;
+            assert_eq!(reputation.completion_rate_bps, 5_000);
+        }
+
+        #[ink::test]
+        fn test_revoke_role() {
+            let (accounts, mut escrow) = init();
+            escrow.grant_role(Role::Admin, accounts.alice).unwrap();
+            escrow
+                .grant_role(Role::Arbitrator, accounts.charlie)
+                .unwrap();
+            // when called by someone without the admin role
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.revoke_role(Role::Arbitrator, accounts.charlie);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by an admin
+            test_utils::change_caller(accounts.alice);
+            // = when revoking the admin role
+            // = * it raises an error
+            result = escrow.revoke_role(Role::Admin, accounts.alice);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when revoking any other role
+            // = * it revokes the role from the account
+            result = escrow.revoke_role(Role::Arbitrator, accounts.charlie);
+            assert!(result.is_ok());
+            assert!(!escrow.has_role(Role::Arbitrator, accounts.charlie));
+            // when called by owner
+            test_utils::change_caller(accounts.bob);
+            // * it can revoke the admin role
+            result = escrow.revoke_role(Role::Admin, accounts.alice);
+            assert!(result.is_ok());
+            assert!(!escrow.has_role(Role::Admin, accounts.alice));
+        }
+
+        #[ink::test]
+        fn test_set_badge_collection() {
+            let (accounts, mut escrow) = init();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.set_badge_collection(Some(accounts.django));
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.set_badge_collection(Some(accounts.django));
+            assert!(result.is_ok());
+            // * it sets the collection
+            assert_eq!(escrow.config().badge_collection, Some(accounts.django));
+        }
+
+        #[ink::test]
+        fn test_set_curated() {
+            let (accounts, mut escrow) = init();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.set_curated(true);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.set_curated(true);
+            assert!(result.is_ok());
+            // * it sets the flag
+            assert!(escrow.config().curated);
+        }
+
+        #[ink::test]
+        fn test_set_dispute_weights() {
+            let (accounts, mut escrow) = init();
+            let dispute_weights: DisputeWeights = DisputeWeights {
+                won_bps: 5_000,
+                lost_bps: 30_000,
+            };
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.set_dispute_weights(dispute_weights);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.set_dispute_weights(dispute_weights);
+            assert!(result.is_ok());
+            // * it sets the weights
+            assert_eq!(escrow.config().dispute_weights, dispute_weights);
+        }
+
+        #[ink::test]
+        fn test_set_governor() {
+            let (accounts, mut escrow) = init();
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.set_governor(Some(accounts.charlie));
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.set_governor(Some(accounts.charlie));
+            assert!(result.is_ok());
+            // * it sets the governor
+            assert_eq!(escrow.config().governor, Some(accounts.charlie));
+            // when a governor is set
+            // = when called by the owner
+            // = * it raises an error
+            result = escrow.set_governor(None);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            assert_eq!(
+                escrow.set_timeouts(escrow.timeouts.get_or_default()),
+                Err(EscrowError::Unauthorised)
+            );
+            // = when called by the governor
+            test_utils::change_caller(accounts.charlie);
+            // = * it allows parameters to be changed
+            assert!(escrow.set_limits(escrow.limits.get_or_default()).is_ok());
+            assert!(escrow
+                .set_timeouts(escrow.timeouts.get_or_default())
+                .is_ok());
+            // = when queueing fee or treasury changes
+            // = * it only allows the governor to queue or execute them
+            assert!(escrow.queue_action(AdminAction::FeeBps(50)).is_ok());
+            assert!(escrow
+                .queue_action(AdminAction::Treasury(accounts.django))
+                .is_ok());
+            test_utils::change_caller(accounts.bob);
+            assert_eq!(
+                escrow.queue_action(AdminAction::FeeBps(50)),
+                Err(EscrowError::Unauthorised)
+            );
+            assert_eq!(
+                escrow.queue_action(AdminAction::Treasury(accounts.django)),
+                Err(EscrowError::Unauthorised)
+            );
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_TIMELOCK_DELAY,
+            );
+            assert_eq!(escrow.execute_action(0), Err(EscrowError::Unauthorised));
+            // = * it leaves the other actions to admins and signers
+            assert!(escrow.queue_action(AdminAction::TimelockDelay(1)).is_ok());
+            test_utils::change_caller(accounts.charlie);
+            assert!(escrow.execute_action(0).is_ok());
+            assert_eq!(escrow.fee_bps.get_or_default(), 50);
+            result = escrow.set_governor(None);
+            assert!(result.is_ok());
+            // = * it can hand parameters back to the owner
+            assert_eq!(escrow.config().governor, None);
+        }
+
+        #[ink::test]
+        fn test_set_limits() {
+            let (accounts, mut escrow) = init();
+            let mut limits: Limits = Limits {
+                min_order_amount: 1,
+                max_order_amount: 1_000,
+                max_listings_per_vendor: 5,
+                max_open_orders_per_buyer: 3,
+                max_open_orders_per_listing: 10,
+                max_orders_per_block_per_buyer: 2,
+                hold_threshold: 500,
+                hold_amount: 1,
+                min_payment_window: 10,
+                max_payment_window: 5,
+            };
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.set_limits(limits);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            // = when the minimum payment window is above the maximum
+            // = * it raises an error
+            result = escrow.set_limits(limits);
+            assert_eq!(result, Err(EscrowError::InvalidLimits));
+            // = when the minimum order amount is above the maximum
+            // = * it raises an error
+            limits.min_payment_window = 0;
+            limits.min_order_amount = 1_001;
+            result = escrow.set_limits(limits);
+            assert_eq!(result, Err(EscrowError::InvalidLimits));
+            limits.min_order_amount = 1;
+            // = when the current payment window is outside the bounds
+            // = * it raises an error
+            limits.min_payment_window = DEFAULT_PAYMENT_WINDOW + 1;
+            limits.max_payment_window = DEFAULT_PAYMENT_WINDOW + 1;
+            result = escrow.set_limits(limits);
+            assert_eq!(result, Err(EscrowError::InvalidLimits));
+            // = when limits are valid
+            limits.min_payment_window = 10;
+            limits.max_payment_window = DEFAULT_PAYMENT_WINDOW;
+            result = escrow.set_limits(limits);
+            assert!(result.is_ok());
+            // = * it sets the limits
+            assert_eq!(escrow.config().limits, limits);
+        }
+
+        #[ink::test]
+        fn test_set_listing_yield() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            // when listing doesn't exist
+            // * it raises an error
+            let mut result = escrow.set_listing_yield(1, true);
+            assert_eq!(result, Err(EscrowError::ListingNotFound));
+            // when listing exists
+            // = when called by someone other than the vendor
+            // = * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.set_listing_yield(0, true);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // = when called by the vendor
+            test_utils::change_caller(accounts.bob);
+            // == when the listing is for a token
+            // == * it raises an error
+            let mut listing: Listing = escrow.listings.get(0).unwrap();
+            listing.asset = Some(accounts.eve);
+            escrow.listings.update(&listing);
+            result = escrow.set_listing_yield(0, true);
+            assert_eq!(result, Err(EscrowError::AssetMismatch));
+            listing.asset = None;
+            escrow.listings.update(&listing);
+            // == when there's no yield pool
+            // == * it raises an error
+            result = escrow.set_listing_yield(0, true);
+            assert_eq!(result, Err(EscrowError::YieldPoolNotSet));
+            // == when there's a yield pool
+            escrow.set_yield_pool(Some(accounts.django)).unwrap();
+            result = escrow.set_listing_yield(0, true);
+            assert!(result.is_ok());
+            // == * it opts the listing in
+            assert_eq!(escrow.listing_bonded_amount(0), Some(0));
+            // === when opting out
+            result = escrow.set_listing_yield(0, false);
+            assert!(result.is_ok());
+            // === * it opts the listing out
+            assert_eq!(escrow.listing_bonded_amount(0), None);
+        }
+
+        #[ink::test]
+        fn test_set_maintenance_mode() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 5);
+            // when called by someone without the pauser role
+            // * it raises an error
+            let mut result = escrow.set_maintenance_mode(true);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by a pauser
+            test_utils::change_caller(accounts.bob);
+            result = escrow.set_maintenance_mode(true);
+            assert!(result.is_ok());
+            // * it enables maintenance mode
+            assert!(escrow.config().maintenance_mode);
+            // * it blocks everything except fund recovery
+            result = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            assert_eq!(result, Err(EscrowError::MaintenanceMode));
+            result = escrow.create_listing(None, "USD".to_string(), 1);
+            assert_eq!(result, Err(EscrowError::MaintenanceMode));
+            result = escrow.deposit_into_listing(0);
+            assert_eq!(result, Err(EscrowError::MaintenanceMode));
+            result = escrow.deposit_tokens_into_listing(0, 1);
+            assert_eq!(result, Err(EscrowError::MaintenanceMode));
+            result = escrow.dispute_order(0);
+            assert_eq!(result, Err(EscrowError::MaintenanceMode));
+            result = escrow.expire_order(0);
+            assert_eq!(result, Err(EscrowError::MaintenanceMode));
+            result = escrow.finalise_order(0);
+            assert_eq!(result, Err(EscrowError::MaintenanceMode));
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.create_order(0, 1);
+            assert_eq!(result, Err(EscrowError::MaintenanceMode));
+            result = escrow.update_order_payment_verification(0, [1; 32], None);
+            assert_eq!(result, Err(EscrowError::MaintenanceMode));
+            // * it still allows orders to be cancelled and funds withdrawn
+            assert!(escrow.cancel_order(0).is_ok());
+            test_utils::change_caller(accounts.bob);
+            assert!(escrow.withdraw_from_listing(0, 10).is_ok());
+            // when disabled
+            result = escrow.set_maintenance_mode(false);
+            assert!(result.is_ok());
+            // * it allows everything again
+            assert!(!escrow.config().maintenance_mode);
+            assert!(escrow.create_listing(None, "USD".to_string(), 1).is_ok());
+        }
+
+        #[ink::test]
+        fn test_set_max_page_size() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            for _ in 0..3 {
+                let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            }
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.set_max_page_size(2);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            // = when the size is zero or above MAX_PAGE_SIZE
+            // = * it raises an error
+            result = escrow.set_max_page_size(0);
+            assert_eq!(result, Err(EscrowError::InvalidLimits));
+            result = escrow.set_max_page_size(MAX_PAGE_SIZE + 1);
+            assert_eq!(result, Err(EscrowError::InvalidLimits));
+            // = when the size is valid
+            result = escrow.set_max_page_size(2);
+            assert!(result.is_ok());
+            // = * it sets the max page size
+            assert_eq!(escrow.config().max_page_size, 2);
+            // = * it caps the items index queries return
+            let page: Page<ListingSummary> = escrow.listings(None, 10, Direction::Ascending);
+            assert_eq!(page.items.len(), 2);
+            assert!(page.has_more);
+            let page: Page<ListingSummary> =
+                escrow.listings_by_pair(None, "USD".to_string(), 0, 10);
+            assert_eq!(page.items.len(), 2);
+            assert_eq!(page.total, 3);
+            assert!(page.has_more);
+            // = * it pages by the capped size
+            let page: Page<ListingSummary> =
+                escrow.listings_by_pair(None, "USD".to_string(), 1, 10);
+            assert_eq!(page.items.len(), 1);
+            assert_eq!(page.items[0].id, 0);
+        }
+
+        #[ink::test]
+        fn test_set_order_size_schedule() {
+            let (accounts, mut escrow) = init();
+            let mut order_size_schedule: Vec<OrderSizeTier> = vec![
+                OrderSizeTier {
+                    completed_orders_length: 0,
+                    max_order_amount: 100,
+                },
+                OrderSizeTier {
+                    completed_orders_length: 10,
+                    max_order_amount: 50,
+                },
+            ];
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.set_order_size_schedule(order_size_schedule.clone());
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            // = when a tier has a lower maximum than the one before it
+            // = * it raises an error
+            result = escrow.set_order_size_schedule(order_size_schedule.clone());
+            assert_eq!(result, Err(EscrowError::InvalidLimits));
+            // = when tiers aren't in ascending order of completed orders
+            // = * it raises an error
+            order_size_schedule[1].max_order_amount = 1_000;
+            order_size_schedule[1].completed_orders_length = 0;
+            result = escrow.set_order_size_schedule(order_size_schedule.clone());
+            assert_eq!(result, Err(EscrowError::InvalidLimits));
+            // = when the schedule is valid
+            order_size_schedule[0].completed_orders_length = 1;
+            order_size_schedule[1].completed_orders_length = 10;
+            result = escrow.set_order_size_schedule(order_size_schedule.clone());
+            assert!(result.is_ok());
+            // = * it sets the schedule
+            assert_eq!(escrow.config().order_size_schedule, order_size_schedule);
+            // = * it doesn't cap buyers below the first tier
+            assert_eq!(escrow.order_size_limit(accounts.charlie), Balance::MAX);
+            // = * it caps buyers at the highest tier they've reached
+            let mut buyer_summary: BuyerSummary = BuyerSummary {
+                completed_orders_length: 9,
+                ..Default::default()
+            };
+            escrow
+                .buyer_summaries
+                .insert(accounts.charlie, &buyer_summary);
+            assert_eq!(escrow.order_size_limit(accounts.charlie), 100);
+            buyer_summary.completed_orders_length = 10;
+            escrow
+                .buyer_summaries
+                .insert(accounts.charlie, &buyer_summary);
+            assert_eq!(escrow.order_size_limit(accounts.charlie), 1_000);
+        }
+
+        #[ink::test]
+        fn test_set_timeouts() {
+            let (accounts, mut escrow) = init();
+            let mut timeouts: Timeouts = Timeouts {
+                payment_window: 1_000,
+                verification_window: 2_000,
+                dispute_window: 3_000,
+            };
+            // when called by someone other than the owner
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.set_timeouts(timeouts);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            // = when the payment window is outside the limits
+            // = * it raises an error
+            update_limits(&mut escrow, |limits| limits.min_payment_window = 1_001);
+            result = escrow.set_timeouts(timeouts);
+            assert_eq!(result, Err(EscrowError::InvalidTimeouts));
+            update_limits(&mut escrow, |limits| limits.min_payment_window = 0);
+            // = when a window is zero
+            // = * it raises an error
+            timeouts.dispute_window = 0;
+            result = escrow.set_timeouts(timeouts);
+            assert_eq!(result, Err(EscrowError::InvalidTimeouts));
+            // = when timeouts are valid
+            timeouts.dispute_window = 3_000;
+            result = escrow.set_timeouts(timeouts);
+            assert!(result.is_ok());
+            // = * it sets the timeouts
+            assert_eq!(escrow.config().timeouts, timeouts);
+        }
+
+        #[ink::test]
+        fn test_show_listing() {
+            let (_accounts, mut escrow) = init();
+            // when listing does not exist
+            // * it returns None
+            assert!(escrow.show_listing(0).is_none());
+            // when listing exists
+            // * it returns the listing
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            assert_eq!(escrow.show_listing(0).unwrap().currency, "USD".to_string());
+        }
+
+        #[ink::test]
+        fn test_show_listings() {
+            let (_accounts, mut escrow) = init();
+            // when too many ids are requested
+            // * it raises an error
+            let mut result = escrow.show_listings(vec![0; MAX_BATCH_SIZE + 1]);
+            assert_eq!(result.unwrap_err(), EscrowError::BatchTooLarge);
+            // when ids are within the limit
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            let _ = escrow.create_listing(None, "EUR".to_string(), 1);
+            // * it returns the listings in the requested order
+            // * it returns None for listings that don't exist
+            result = escrow.show_listings(vec![1, 5, 0]);
+            let listings: Vec<Option<Listing>> = result.unwrap();
+            assert_eq!(listings.len(), 3);
+            assert_eq!(listings[0].as_ref().unwrap().currency, "EUR".to_string());
+            assert!(listings[1].is_none());
+            assert_eq!(listings[2].as_ref().unwrap().currency, "USD".to_string());
+        }
+
+        #[ink::test]
+        fn test_show_order() {
+            let (accounts, mut escrow) = init();
+            // when order does not exist
+            // * it returns None
+            assert!(escrow.show_order(0).is_none());
+            // when order exists
+            // * it returns the order
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 5);
+            let order: Order = escrow.show_order(0).unwrap();
+            assert_eq!(order.buyer, accounts.charlie);
+            assert_eq!(order.amount, 5);
+        }
+
+        #[ink::test]
+        fn test_show_orders() {
+            let (accounts, mut escrow) = init();
+            // when too many ids are requested
+            // * it raises an error
+            let mut result = escrow.show_orders(vec![0; MAX_BATCH_SIZE + 1]);
+            assert_eq!(result.unwrap_err(), EscrowError::BatchTooLarge);
+            // when ids are within the limit
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 2);
+            let _ = escrow.create_order(0, 3);
+            // * it returns the orders in the requested order
+            // * it returns None for orders that don't exist
+            result = escrow.show_orders(vec![1, 5, 0]);
+            let orders: Vec<Option<Order>> = result.unwrap();
+            assert_eq!(orders.len(), 3);
+            assert_eq!(orders[0].as_ref().unwrap().amount, 3);
+            assert!(orders[1].is_none());
+            assert_eq!(orders[2].as_ref().unwrap().amount, 2);
+        }
+
+        #[ink::test]
+        fn test_status_transitions() {
+            let (accounts, mut escrow) = init();
+            // when no order has changed status
+            // * it returns an empty page
+            let mut result: Page<StatusTransition> = escrow.status_transitions(0, 10);
+            assert_eq!(result.items.len(), 0);
+            assert_eq!(result.total, 0);
+            // when orders have changed status
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5);
+            let _ = escrow.update_order_payment_verification(0, [1; 32], None);
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.dispute_order(0);
+            // * it returns them newest first
+            result = escrow.status_transitions(0, 10);
+            assert_eq!(result.total, 2);
+            assert_eq!(
+                result.items[0],
+                StatusTransition {
+                    order_id: 0,
+                    from: 1,
+                    to: 4,
+                    timestamp: 5
+                }
+            );
+            assert_eq!(result.items[1].from, 0);
+            assert_eq!(result.items[1].to, 1);
+            // * it pages through older transitions
+            result = escrow.status_transitions(1, 1);
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].to, 1);
+            // when more transitions are recorded than the capacity
+            test_utils::change_caller(accounts.charlie);
+            for _ in 0..STATUS_TRANSITIONS_CAPACITY {
+                let _ = escrow.create_order(0, 1);
+                let _ = escrow.cancel_order(escrow.orders.length() - 1);
+            }
+            // * it only keeps the latest transitions
+            result = escrow.status_transitions(0, u16::MAX);
+            assert_eq!(result.total, STATUS_TRANSITIONS_CAPACITY);
+            assert!(result.items.iter().all(|transition| transition.to == 3));
+        }
+
+        #[ink::test]
+        fn test_start_export() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            // when queued by someone without the admin role
+            // * it raises an error
+            let mut result = escrow.queue_action(AdminAction::StartExport(accounts.frank));
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when queued by an admin
+            test_utils::change_caller(accounts.bob);
+            escrow
+                .queue_action(AdminAction::StartExport(accounts.frank))
+                .unwrap();
+            // = when the timelock delay has not passed
+            // = * it raises an error
+            result = escrow.execute_action(0);
+            assert_eq!(result, Err(EscrowError::TimelockNotExpired));
+            // = when the timelock delay has passed
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                DEFAULT_TIMELOCK_DELAY,
+            );
+            // == when funds are bonded into the yield pool
+            // == * it raises an error
+            escrow.bonded_amount = 1;
+            result = escrow.execute_action(0);
+            assert_eq!(result, Err(EscrowError::YieldPoolInUse));
+            escrow.bonded_amount = 0;
+            // == when nothing is bonded
+            result = escrow.execute_action(0);
+            assert!(result.is_ok());
+            // == * it records the destination and enables maintenance mode
+            assert_eq!(escrow.config().export_destination, Some(accounts.frank));
+            assert!(escrow.config().maintenance_mode);
+            // == * it refuses withdrawals, closes and cancellations
+            assert_eq!(
+                escrow.withdraw_from_listing(0, 1),
+                Err(EscrowError::ExportStarted)
+            );
+            assert_eq!(escrow.close_listing(0), Err(EscrowError::ExportStarted));
+            assert_eq!(
+                escrow.pause_listing(0, true),
+                Err(EscrowError::ExportStarted)
+            );
+            assert_eq!(
+                escrow.force_cancel_order(0, CancelReason::Other),
+                Err(EscrowError::ExportStarted)
+            );
+            test_utils::change_caller(accounts.charlie);
+            assert_eq!(escrow.cancel_order(0), Err(EscrowError::ExportStarted));
+            // == * it can't be undone
             test_utils::change_caller(accounts.bob);
-            let escrow = Escrow::new();
-            (accounts, escrow)
+            assert_eq!(
+                escrow.set_maintenance_mode(false),
+                Err(EscrowError::ExportStarted)
+            );
+            result = start_export(&mut escrow, accounts.django);
+            assert_eq!(result, Err(EscrowError::ExportStarted));
         }
 
-        fn get_balance(account_id: AccountId) -> Balance {
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account_id)
-                .expect("Cannot get account balance")
+        #[ink::test]
+        fn test_terminate() {
+            let (accounts, mut escrow) = init();
+            // when called by non-owner
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.terminate();
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by owner
+            test_utils::change_caller(accounts.bob);
+            // = when funds are escrowed
+            escrow.escrowed_amount = 1;
+            // = * it raises an error
+            result = escrow.terminate();
+            assert_eq!(result, Err(EscrowError::EscrowNotEmpty));
+            // = when there are open orders
+            escrow.escrowed_amount = 0;
+            escrow.orders.active_length = 1;
+            // = * it raises an error
+            result = escrow.terminate();
+            assert_eq!(result, Err(EscrowError::EscrowNotEmpty));
+            // = when the contract is empty
+            // = * it terminates the contract and sends the balance to the treasury
+            escrow.orders.active_length = 0;
+            escrow.treasury.set(&accounts.charlie);
+            let contract_balance: Balance =
+                get_balance(ink::env::test::callee::<ink::env::DefaultEnvironment>());
+            let should_terminate = move || {
+                let _ = escrow.terminate();
+            };
+            ink::env::test::assert_contract_termination::<ink::env::DefaultEnvironment, _>(
+                should_terminate,
+                accounts.charlie,
+                contract_balance,
+            );
         }
 
-        fn set_balance(account_id: AccountId, balance: Balance) {
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(account_id, balance)
+        #[ink::test]
+        fn test_top_vendors() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(100);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.django);
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            let _ = escrow.deposit_into_listing(1);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 5);
+            let _ = escrow.create_order(1, 10);
+            let _ = escrow.create_order(0, 6);
+            // when no orders have been finalised
+            // * it returns no vendors
+            assert!(escrow.top_vendors(10).is_empty());
+            // when orders are finalised
+            test_utils::change_caller(accounts.bob);
+            escrow.finalise_order(0).unwrap();
+            test_utils::change_caller(accounts.django);
+            escrow.finalise_order(1).unwrap();
+            // * it ranks vendors by volume, highest first
+            assert_eq!(
+                escrow.top_vendors(10),
+                vec![
+                    VendorVolume {
+                        vendor: accounts.django,
+                        volume: 10
+                    },
+                    VendorVolume {
+                        vendor: accounts.bob,
+                        volume: 5
+                    }
+                ]
+            );
+            // * it moves vendors up as their volume grows
+            test_utils::change_caller(accounts.bob);
+            escrow.finalise_order(2).unwrap();
+            assert_eq!(
+                escrow.top_vendors(10),
+                vec![
+                    VendorVolume {
+                        vendor: accounts.bob,
+                        volume: 11
+                    },
+                    VendorVolume {
+                        vendor: accounts.django,
+                        volume: 10
+                    }
+                ]
+            );
+            // * it returns at most n vendors
+            assert_eq!(escrow.top_vendors(1).len(), 1);
+            // when the epoch has moved on
+            // * it returns no vendors
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(REPUTATION_EPOCH);
+            assert!(escrow.top_vendors(10).is_empty());
         }
 
-        // === TESTS ===
         #[ink::test]
-        fn test_new() {
-            let (accounts, escrow) = init();
-            // * it sets owner as caller
-            assert_eq!(escrow.ownable.owner(), accounts.bob);
-            // * it sets listings
-            // assert_eq!(escrow.listings.values, Mapping::default());
-            assert_eq!(escrow.listings.length, 0);
-            // * it sets vendors
-            // assert_eq!(escrow.vendors, Mapping::default());
+        fn test_total_value_locked() {
+            let (accounts, mut escrow) = init();
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            // * it includes available amounts
+            assert_eq!(escrow.total_value_locked(), 10);
+            // * it includes amounts locked in orders
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 4);
+            assert_eq!(escrow.total_value_locked(), 10);
+            // * it excludes released and withdrawn amounts
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.finalise_order(0);
+            let _ = escrow.withdraw_from_listing(0, 1);
+            assert_eq!(escrow.total_value_locked(), 5);
         }
 
         #[ink::test]
-        fn test_config() {
-            let (accounts, escrow) = init();
-            let config = escrow.config();
-            // * it returns the config
-            assert_eq!(config.admin, accounts.bob);
+        fn test_set_trade_manager() {
+            let (accounts, mut escrow) = init();
+            test_utils::change_caller(accounts.charlie);
+            // when approving a manager
+            let mut result = escrow.set_trade_manager(accounts.django, true);
+            assert!(result.is_ok());
+            // * it lets the manager open trades for the caller
+            assert!(escrow.is_trade_manager(accounts.charlie, accounts.django));
+            assert!(!escrow.is_trade_manager(accounts.eve, accounts.django));
+            // when revoking a manager
+            result = escrow.set_trade_manager(accounts.django, false);
+            assert!(result.is_ok());
+            // * it stops the manager from opening trades for the caller
+            assert!(!escrow.is_trade_manager(accounts.charlie, accounts.django));
         }
 
         #[ink::test]
-        fn test_create_listing() {
-            let price_per_tzero: Balance = 1_000_000;
+        fn test_set_upkeep_bounty() {
             let (accounts, mut escrow) = init();
-            // when the maximum number of listings has been reached
-            escrow.listings.length = u32::MAX;
+            // when called by someone other than the owner
             // * it raises an error
-            let mut result = escrow.create_listing(price_per_tzero);
-            assert_eq!(result, Err(EscrowError::ListingLimitReached));
-            // when the maximum number of listings hasn't been reached
-            escrow.listings.length = u32::MAX - 1;
-            // = when caller isn't a vendor
-            // = * it raises an error
-            result = escrow.create_listing(price_per_tzero);
-            assert_eq!(result, Err(EscrowError::ListingCanOnlyBeCreatedByAVendor));
-            // = when caller is a vendor
-            escrow.vendors.insert(
-                accounts.bob,
-                &Vendor {
-                    ethereum_address: "0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string(),
-                },
-            );
-            // = * it creates a listing at the listings length index
-            result = escrow.create_listing(price_per_tzero);
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.set_upkeep_bounty(5);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by the owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.set_upkeep_bounty(5);
             assert!(result.is_ok());
-            assert_eq!(
-                escrow.listings.values.get(u32::MAX - 1).unwrap().vendor,
-                accounts.bob
-            );
-            // = * it increases the listings length by one
-            assert_eq!(escrow.listings.length, u32::MAX);
+            // * it sets the bounty
+            assert_eq!(escrow.config().upkeep_bounty, 5);
         }
 
         #[ink::test]
-        fn test_create_order() {
-            let price_per_tzero: Balance = 1_000_000;
+        fn test_set_yield_pool() {
             let (accounts, mut escrow) = init();
-            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
-            let _ = escrow.create_listing(price_per_tzero);
-
-            // when listing does not exist
+            // when called by someone other than the owner
             // * it raises an error
-            let mut result = escrow.create_order(1, 5);
-            assert_eq!(result, Err(EscrowError::ListingNotFound));
-            // when listing exists
-            // = when caller is vendor
-            // = * it raises an error
-            result = escrow.create_order(0, 5);
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.set_yield_pool(Some(accounts.django));
             assert_eq!(result, Err(EscrowError::Unauthorised));
-            // = when caller is not vendor
-            test_utils::change_caller(accounts.alice);
-            // == when amount to purchase is not available
-            // == * it raises an error
-            result = escrow.create_order(0, 5);
-            assert_eq!(result, Err(EscrowError::AmountUnavailable));
-            // == when amount to purchase is available
+            // when called by the owner
             test_utils::change_caller(accounts.bob);
-            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(5);
-            let _ = escrow.deposit_into_listing(0);
-            test_utils::change_caller(accounts.alice);
-            result = escrow.create_order(0, 5);
+            result = escrow.set_yield_pool(Some(accounts.django));
             assert!(result.is_ok());
-            // == * it reduces the amount_availabe by the amount
-            assert_eq!(escrow.listings.values.get(0).unwrap().available_amount, 0);
-            // == * it create an order
-            let order: Order = escrow.orders.values.get(0).unwrap();
-            assert_eq!(order.amount, 5);
-            assert_eq!(order.buyer, accounts.alice);
-            assert_eq!(order.vendor, accounts.bob);
-            assert_eq!(order.id, 0);
-            assert_eq!(escrow.orders.length, 1);
-            assert_eq!(order.status, 0);
+            // * it sets the yield pool
+            assert_eq!(escrow.config().yield_pool, Some(accounts.django));
+            // = when funds are bonded into the yield pool
+            // = * it raises an error
+            escrow.bonded_amount = 1;
+            result = escrow.set_yield_pool(None);
+            assert_eq!(result, Err(EscrowError::YieldPoolInUse));
         }
 
         #[ink::test]
-        fn test_create_vendor() {
+        fn test_transfer_ownership() {
             let (accounts, mut escrow) = init();
-            // when account is not a vendor
-            // * it creates a vendor profile for account
-            // * it emits a CreateVendor event (TO DO AFTER HACKATHON)
-            let mut result =
-                escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            // when called by non-owner
+            // * it raises an error
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.transfer_ownership(accounts.alice);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by owner
+            test_utils::change_caller(accounts.bob);
+            result = escrow.transfer_ownership(accounts.alice);
+            assert!(result.is_ok());
+            // * it sets the pending owner
+            assert_eq!(escrow.pending_owner, Some(accounts.alice));
+            // * it does not change the owner
+            assert_eq!(escrow.ownable.owner(), accounts.bob);
+            // = when a pending owner already exists
+            // = * it replaces the pending owner
+            result = escrow.transfer_ownership(accounts.charlie);
             assert!(result.is_ok());
-            assert!(escrow.vendors.get(&accounts.bob).is_some());
+            assert_eq!(escrow.pending_owner, Some(accounts.charlie));
+        }
 
-            // when account is already a vendor
+        #[ink::test]
+        fn test_unpause() {
+            let (accounts, mut escrow) = init();
+            escrow.pause(PAUSE_CREATE_ORDER | PAUSE_WITHDRAW).unwrap();
+            // when called by someone without the pauser role
             // * it raises an error
-            result = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
-            assert_eq!(result, Err(EscrowError::VendorAlreadyExists));
+            test_utils::change_caller(accounts.alice);
+            let mut result = escrow.unpause(PAUSE_WITHDRAW);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by a pauser
+            test_utils::change_caller(accounts.bob);
+            result = escrow.unpause(PAUSE_WITHDRAW);
+            assert!(result.is_ok());
+            // * it only unblocks the given operations
+            assert_eq!(
+                escrow.paused_operations.get_or_default(),
+                PAUSE_CREATE_ORDER
+            );
         }
 
         #[ink::test]
-        fn test_deposit_into_listing() {
-            let price_per_tzero: Balance = 1_000_000;
+        fn test_update_listing() {
             let (accounts, mut escrow) = init();
-
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
             // when listing does not exist
             // * it raises an error
-            let mut result = escrow.deposit_into_listing(0);
+            let mut result = escrow.update_listing(1, 2, None);
             assert_eq!(result, Err(EscrowError::ListingNotFound));
-
             // when listing exists
-            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
-            let _ = escrow.create_listing(price_per_tzero);
-            // = when listing does not belong to caller
-            test_utils::change_caller(accounts.alice);
+            // = when called by someone other than the vendor
             // = * it raises an error
-            result = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            result = escrow.update_listing(0, 2, None);
             assert_eq!(result, Err(EscrowError::Unauthorised));
-            // = when listing belongs to caller
+            // = when called by the vendor
             test_utils::change_caller(accounts.bob);
-            set_balance(accounts.bob, 10);
-            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1);
-            // = * it increases the listing available_amount
-            result = escrow.deposit_into_listing(0);
+            result = escrow.update_listing(0, 2, None);
             assert!(result.is_ok());
-            assert_eq!(escrow.listings.values.get(0).unwrap().available_amount, 1);
+            // = * it updates the price for new orders
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 3);
+            assert_eq!(escrow.show_listing(0).unwrap().price_per_unit, 2);
+            assert_eq!(escrow.show_order(0).unwrap().total_price, 6);
+            // = when an expiry is set
+            test_utils::change_caller(accounts.bob);
+            result = escrow.update_listing(0, 2, Some(5));
+            assert!(result.is_ok());
+            // = * it stops taking orders from then
+            test_utils::change_caller(accounts.charlie);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5);
+            assert_eq!(escrow.create_order(0, 1), Err(EscrowError::ListingExpired));
+            // = when listing is closed
+            // = * it raises an error
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.close_listing(0);
+            result = escrow.update_listing(0, 2, None);
+            assert_eq!(result, Err(EscrowError::ListingClosed));
         }
 
         #[ink::test]
         fn test_update_order_payment_verification() {
-            let price_per_tzero: Balance = 1_000_000;
+            let price_per_unit: Balance = 1_000_000;
             let (accounts, mut escrow) = init();
-            let payment_verification: String = "tx-hash-that-proves-payment".to_string();
+            let payment_verification: [u8; 32] = [1; 32];
 
             // when order does not exist
             // * it raises an error
             let mut result =
-                escrow.update_order_payment_verification(0, payment_verification.clone());
+                escrow.update_order_payment_verification(0, payment_verification, None);
             assert_eq!(result, Err(EscrowError::OrderNotFound));
             // when order exists
             let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
-            let _ = escrow.create_listing(price_per_tzero);
+            let _ = escrow.create_listing(None, "USD".to_string(), price_per_unit);
             ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
             let _ = escrow.deposit_into_listing(0);
             test_utils::change_caller(accounts.alice);
@@ -721,57 +9136,180 @@ mod escrow {
             // = when called by non-buyer
             // = * it raises an error
             test_utils::change_caller(accounts.bob);
-            result = escrow.update_order_payment_verification(0, payment_verification.clone());
+            result = escrow.update_order_payment_verification(0, payment_verification, None);
             assert_eq!(result, Err(EscrowError::Unauthorised));
             // = when called by buyer
             test_utils::change_caller(accounts.alice);
             let mut order: Order = escrow.orders.values.get(0).unwrap();
             // == when order has status finalised
-            order.status = 2;
+            order.state.set_status(2);
             escrow.orders.update(&order);
             // == * it raises an error
-            result = escrow.update_order_payment_verification(0, payment_verification.clone());
+            result = escrow.update_order_payment_verification(0, payment_verification, None);
             assert_eq!(result, Err(EscrowError::OrderFinalised));
             // == when order has status cancelled
-            order.status = 3;
+            order.state.set_status(3);
             escrow.orders.update(&order);
             // == * it raises an error
-            result = escrow.update_order_payment_verification(0, payment_verification.clone());
+            result = escrow.update_order_payment_verification(0, payment_verification, None);
             assert_eq!(result, Err(EscrowError::OrderCancelled));
             // == when order has status open
-            order.status = 0;
+            order.state.set_status(0);
             escrow.orders.update(&order);
-            let _ = escrow.update_order_payment_verification(0, payment_verification.clone());
+            let _ = escrow.update_order_payment_verification(0, payment_verification, None);
             order = escrow.orders.values.get(0).unwrap();
             // == * it updates the order's tx hash
-            assert_eq!(
-                order.payment_verification,
-                Some(payment_verification.clone())
-            );
+            assert_eq!(order.payment_verification, Some(payment_verification));
             // == * it updates the status to PendingVerification
-            assert_eq!(order.status, 1);
+            assert_eq!(order.status(), 1);
             // == when order has status PendingVerification
             // == * it updates the order's tx hash
-            let payment_verification_two: String = "Hey Joni".to_string();
-            let _ = escrow.update_order_payment_verification(0, payment_verification_two.clone());
+            let payment_verification_two: [u8; 32] = [2; 32];
+            let _ = escrow.update_order_payment_verification(0, payment_verification_two, None);
             order = escrow.orders.values.get(0).unwrap();
             // == * it updates the order's tx hash
             assert_eq!(order.payment_verification, Some(payment_verification_two));
-            assert_eq!(order.status, 1);
+            assert_eq!(order.status(), 1);
             // == when order has status Disputed
-            order.status = 4;
+            order.state.set_status(4);
             escrow.orders.update(&order);
-            let _ = escrow.update_order_payment_verification(0, payment_verification.clone());
+            // == * it raises an error
+            result = escrow.update_order_payment_verification(0, payment_verification, None);
+            assert_eq!(result, Err(EscrowError::StatusCanNotBeChanged));
+            // == * it leaves the order in dispute
             order = escrow.orders.values.get(0).unwrap();
-            // == * it updates the order's tx hash
-            assert_eq!(order.payment_verification, Some(payment_verification));
-            // == * it updates the order's tx hash
-            assert_eq!(order.status, 1);
+            assert_eq!(order.payment_verification, Some(payment_verification_two));
+            assert_eq!(order.status(), 4);
+            order.state.set_status(1);
+            escrow.orders.update(&order);
+            // == when an attestation is provided
+            // === when the signature can't be recovered
+            // === * it raises an error
+            result =
+                escrow.update_order_payment_verification(0, payment_verification, Some([0; 65]));
+            assert_eq!(result, Err(EscrowError::InvalidSignature));
+            // === when the signer is not a registered payment processor
+            // === * it raises an error
+            let signature: [u8; 65] = [1; 65];
+            result =
+                escrow.update_order_payment_verification(0, payment_verification, Some(signature));
+            assert_eq!(result, Err(EscrowError::InvalidSignature));
+            // === when the signer is a registered payment processor
+            let mut public_key: [u8; 33] = [0; 33];
+            ink::env::ecdsa_recover(
+                &signature,
+                &escrow.payment_attestation_hash(0, payment_verification),
+                &mut public_key,
+            )
+            .unwrap();
+            test_utils::change_caller(accounts.bob);
+            escrow.add_payment_processor(public_key).unwrap();
+            test_utils::change_caller(accounts.alice);
+            result =
+                escrow.update_order_payment_verification(0, payment_verification, Some(signature));
+            assert!(result.is_ok());
+            // === * it finalises the order
+            assert_eq!(escrow.orders.values.get(0).unwrap().status(), 2);
+        }
+
+        #[ink::test]
+        fn test_update_vendor() {
+            let (accounts, mut escrow) = init();
+            // when caller is not a vendor
+            // * it raises an error
+            let mut result = escrow.update_vendor("0x0".to_string());
+            assert_eq!(result, Err(EscrowError::VendorNotFound));
+            // when caller is a vendor
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5);
+            // = when the ethereum address is too long
+            // = * it raises an error
+            result = escrow.update_vendor("0".repeat(MAX_ETHEREUM_ADDRESS_LENGTH + 1));
+            assert_eq!(result, Err(EscrowError::EthereumAddressTooLong));
+            // = when the ethereum address is valid
+            result = escrow.update_vendor("0x0".to_string());
+            assert!(result.is_ok());
+            // * it updates the vendor
+            let vendor: Vendor = escrow.vendors.get(accounts.bob).unwrap();
+            assert_eq!(vendor.ethereum_address, "0x0".to_string());
+            assert_eq!(vendor.last_active_at, 5);
+        }
+
+        #[ink::test]
+        fn test_vendor_profile() {
+            let (accounts, mut escrow) = init();
+            // when account is not a vendor
+            // * it returns None
+            assert!(escrow.vendor_profile(accounts.bob).is_none());
+            // when account is a vendor
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5);
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_listing(None, "USD".to_string(), 1);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(10);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10);
+            let _ = escrow.deposit_into_listing(0);
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_order(0, 1);
+            let _ = escrow.create_order(0, 1);
+            test_utils::change_caller(accounts.bob);
+            let _ = escrow.finalise_order(0);
+            escrow.verify_vendor(accounts.bob, true, 2).unwrap();
+            // * it returns the vendor's profile, stats and flags
+            let profile: VendorProfile = escrow.vendor_profile(accounts.bob).unwrap();
+            assert_eq!(profile.account, accounts.bob);
+            assert_eq!(
+                profile.ethereum_address,
+                "0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string()
+            );
+            assert!(profile.verified);
+            assert_eq!(profile.tier, 2);
+            assert_eq!(profile.listings_length, 1);
+            assert_eq!(profile.orders_length, 2);
+            assert_eq!(profile.completed_orders_length, 1);
+            assert_eq!(profile.last_active_at, 10);
+        }
+
+        #[ink::test]
+        fn test_vendors_count() {
+            let (accounts, mut escrow) = init();
+            // * it returns the number of vendors
+            assert_eq!(escrow.vendors_count(), 0);
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            assert_eq!(escrow.vendors_count(), 2);
+        }
+
+        #[ink::test]
+        fn test_verify_vendor() {
+            let (accounts, mut escrow) = init();
+            // when called by someone without the moderator role
+            // * it raises an error
+            test_utils::change_caller(accounts.charlie);
+            let mut result = escrow.verify_vendor(accounts.charlie, true, 1);
+            assert_eq!(result, Err(EscrowError::Unauthorised));
+            // when called by a moderator
+            test_utils::change_caller(accounts.bob);
+            // = when account is not a vendor
+            // = * it raises an error
+            result = escrow.verify_vendor(accounts.charlie, true, 1);
+            assert_eq!(result, Err(EscrowError::VendorNotFound));
+            // = when account is a vendor
+            test_utils::change_caller(accounts.charlie);
+            let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
+            test_utils::change_caller(accounts.bob);
+            result = escrow.verify_vendor(accounts.charlie, true, 1);
+            assert!(result.is_ok());
+            // = * it sets the verification badge and tier
+            let vendor: Vendor = escrow.vendors.get(accounts.charlie).unwrap();
+            assert!(vendor.verified);
+            assert_eq!(vendor.tier, 1);
         }
 
         #[ink::test]
         fn test_withdraw_from_listing() {
-            let price_per_tzero: Balance = 1_000_000;
+            let price_per_unit: Balance = 1_000_000;
             let (accounts, mut escrow) = init();
 
             // when listing does not exist
@@ -781,7 +9319,7 @@ mod escrow {
 
             // when listing exists
             let _ = escrow.create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string());
-            let _ = escrow.create_listing(price_per_tzero);
+            let _ = escrow.create_listing(None, "USD".to_string(), price_per_unit);
             // = when listing does not belong to caller
             test_utils::change_caller(accounts.alice);
             // = * it raises an error
@@ -798,11 +9336,394 @@ mod escrow {
             assert!(result.is_ok());
             assert_eq!(get_balance(accounts.bob), 11);
             // == * it reduces the available amount
-            assert_eq!(escrow.listings.values.get(0).unwrap().available_amount, 4);
+            assert_eq!(escrow.listings.get(0).unwrap().available_amount, 4);
+            // == * it reduces the escrowed amount
+            assert_eq!(escrow.escrowed_amount, 4);
             // == when amount is greater than the available_amount
             // == * it raises an error
             result = escrow.withdraw_from_listing(0, 5);
-            assert_eq!(result, Err(EscrowError::InsufficientFunds));
+            assert_eq!(result, Err(EscrowError::InsufficientFunds(4)));
+            // == when called during a token transfer
+            // == * it raises an error
+            escrow.reentrancy_lock.set(&true);
+            result = escrow.withdraw_from_listing(0, 1);
+            assert_eq!(result, Err(EscrowError::ReentrantCall));
+        }
+    }
+
+    // Random sequences of vendor, buyer and moderator actions, checking after every step that
+    // funds are conserved and that orders only move along their lifecycle
+    #[cfg(all(test, feature = "std"))]
+    mod proptests {
+        use super::*;
+        use crate::traits::Escrow as _;
+        use ink::env::{test::DefaultAccounts, DefaultEnvironment};
+        use openbrush::test_utils;
+        use proptest::prelude::*;
+
+        const LISTINGS_LENGTH: u32 = 2;
+
+        // (action, target, amount)
+        type Action = (u8, u8, Balance);
+
+        // === HELPERS ===
+        fn get_balance(account_id: AccountId) -> Balance {
+            ink::env::test::get_account_balance::<DefaultEnvironment>(account_id)
+                .expect("Cannot get account balance")
+        }
+
+        fn set_balance(account_id: AccountId, balance: Balance) {
+            ink::env::test::set_account_balance::<DefaultEnvironment>(account_id, balance)
+        }
+
+        fn setup(accounts: &DefaultAccounts<DefaultEnvironment>) -> Escrow {
+            test_utils::change_caller(accounts.bob);
+            let mut escrow = Escrow::new(InstantiateArgs::default()).unwrap();
+            escrow.fee_bps.set(&250);
+            let mut limits: Limits = escrow.limits.get_or_default();
+            limits.hold_threshold = 50;
+            limits.hold_amount = 3;
+            escrow.limits.set(&limits);
+            set_balance(accounts.alice, 0);
+            escrow
+                .create_vendor("0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA".to_string())
+                .unwrap();
+            for _ in 0..LISTINGS_LENGTH {
+                escrow.create_listing(None, "USD".to_string(), 1).unwrap();
+            }
+            escrow
+        }
+
+        fn step(
+            escrow: &mut Escrow,
+            accounts: &DefaultAccounts<DefaultEnvironment>,
+            now: &mut Timestamp,
+            (action, target, amount): Action,
+        ) {
+            let buyers: [AccountId; 3] = [accounts.charlie, accounts.django, accounts.eve];
+            let listing_id: u32 = u32::from(target) % LISTINGS_LENGTH;
+            let order: Option<Order> = if escrow.orders.length() > 0 {
+                escrow
+                    .orders
+                    .values
+                    .get(u64::from(target) % escrow.orders.length())
+            } else {
+                None
+            };
+            match (action, order) {
+                (0, _) => {
+                    // The value arrives with the call and goes back to the vendor on failure
+                    test_utils::change_caller(accounts.bob);
+                    let balance: Balance = get_balance(accounts.alice);
+                    set_balance(accounts.alice, balance + amount);
+                    ink::env::test::set_value_transferred::<DefaultEnvironment>(amount);
+                    if escrow.deposit_into_listing(listing_id).is_err() {
+                        set_balance(accounts.alice, balance);
+                    }
+                    ink::env::test::set_value_transferred::<DefaultEnvironment>(0);
+                }
+                (1, _) => {
+                    test_utils::change_caller(accounts.bob);
+                    let _ = escrow.withdraw_from_listing(listing_id, amount);
+                }
+                (2, _) => {
+                    test_utils::change_caller(buyers[usize::from(target) % buyers.len()]);
+                    let limits: Limits = escrow.limits.get_or_default();
+                    if amount > limits.hold_threshold {
+                        let balance: Balance = get_balance(accounts.alice);
+                        let hold: Balance = limits.hold_amount;
+                        set_balance(accounts.alice, balance + hold);
+                        ink::env::test::set_value_transferred::<DefaultEnvironment>(hold);
+                        if escrow.create_order_with_hold(listing_id, amount).is_err() {
+                            set_balance(accounts.alice, balance);
+                        }
+                        ink::env::test::set_value_transferred::<DefaultEnvironment>(0);
+                    } else {
+                        let _ = escrow.create_order(listing_id, amount);
+                    }
+                }
+                (3, Some(order)) => {
+                    test_utils::change_caller(order.buyer);
+                    let _ = escrow.update_order_payment_verification(order.id, [1; 32], None);
+                }
+                (4, Some(order)) => {
+                    test_utils::change_caller(accounts.bob);
+                    let _ = escrow.finalise_order(order.id);
+                }
+                (5, Some(order)) => {
+                    test_utils::change_caller(order.buyer);
+                    let _ = escrow.cancel_order(order.id);
+                }
+                (6, Some(order)) => {
+                    test_utils::change_caller(accounts.bob);
+                    let _ = escrow.dispute_order(order.id);
+                }
+                (7, Some(order)) => {
+                    *now += Timestamp::try_from(amount).unwrap() * 3_600_000;
+                    ink::env::test::set_block_timestamp::<DefaultEnvironment>(*now);
+                    test_utils::change_caller(accounts.frank);
+                    let _ = escrow.expire_order(order.id);
+                }
+                (_, Some(order)) => {
+                    test_utils::change_caller(accounts.bob);
+                    let _ = escrow.force_cancel_order(order.id, CancelReason::Other);
+                }
+                _ => {}
+            }
+        }
+
+        proptest! {
+            #![proptest_config(ProptestConfig::with_cases(64))]
+            #[test]
+            fn test_random_actions_keep_invariants(
+                actions in prop::collection::vec((0u8..9, 0u8..16, 0u128..100), 1..60)
+            ) {
+                ink::env::test::run_test::<DefaultEnvironment, _>(|accounts| {
+                    let mut escrow: Escrow = setup(&accounts);
+                    let mut now: Timestamp = 0;
+                    for action in actions {
+                        let statuses: Vec<u8> = (0..escrow.orders.length())
+                            .map(|id| escrow.orders.values.get(id).unwrap().status())
+                            .collect();
+
+                        step(&mut escrow, &accounts, &mut now, action);
+
+                        // * it keeps the listings, balances and orders consistent
+                        let report: InvariantReport = escrow.check_invariants();
+                        assert!(report.holds, "{:?} after {:?}", report, action);
+                        // * it holds exactly what's escrowed plus the fees not yet claimed
+                        assert_eq!(
+                            get_balance(accounts.alice),
+                            escrow.escrowed_amount
+                                + escrow.accrued_fees.get(None).unwrap_or(0)
+                                + escrow.held_amount
+                        );
+                        // * it only moves orders along their lifecycle
+                        for id in 0..escrow.orders.length() {
+                            let mut order: Order = escrow.orders.values.get(id).unwrap();
+                            let to: u8 = order.status();
+                            match statuses.get(id as usize) {
+                                Some(&from) if from != to => {
+                                    order.state.set_status(from);
+                                    let to_status: OrderStatus = OrderStatus::try_from(to).unwrap();
+                                    assert!(
+                                        order.try_transition(to_status).is_ok(),
+                                        "order {} moved from {} to {}",
+                                        id,
+                                        from,
+                                        to
+                                    );
+                                }
+                                Some(_) => {}
+                                None => assert_eq!(to, OrderStatus::Open as u8),
+                            }
+                        }
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            }
+        }
+    }
+
+    // Run against a live node with `cargo test --features e2e-tests`, these cover the real
+    // balance transfers that the off-chain environment can only approximate
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use crate::traits::Escrow as _;
+        use ink_e2e::build_message;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        const ETHEREUM_ADDRESS: &str = "0xcBfb370dd23CacF0DD1b56d942480DE752A7D2AA";
+
+        #[ink_e2e::test]
+        async fn e2e_deposit_order_release_and_withdraw(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let charlie: AccountId = ink_e2e::account_id(ink_e2e::AccountKeyring::Charlie);
+            let contract: AccountId = client
+                .instantiate(
+                    "escrow",
+                    &ink_e2e::bob(),
+                    EscrowRef::new(InstantiateArgs::default()),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+
+            // when a vendor deposits into a listing
+            let create_vendor = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.create_vendor(ETHEREUM_ADDRESS.to_string()));
+            client
+                .call(&ink_e2e::bob(), create_vendor, 0, None)
+                .await
+                .expect("create_vendor failed");
+            let create_listing = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.create_listing(None, "USD".to_string(), 1));
+            client
+                .call(&ink_e2e::bob(), create_listing, 0, None)
+                .await
+                .expect("create_listing failed");
+            let contract_balance: Balance = client.balance(contract).await?;
+            let deposit_into_listing = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.deposit_into_listing(0));
+            client
+                .call(&ink_e2e::bob(), deposit_into_listing, 1_000, None)
+                .await
+                .expect("deposit_into_listing failed");
+            // * it holds the deposit
+            assert_eq!(client.balance(contract).await?, contract_balance + 1_000);
+
+            // when a buyer orders and pays
+            let create_order = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.create_order(0, 100));
+            client
+                .call(&ink_e2e::charlie(), create_order, 0, None)
+                .await
+                .expect("create_order failed");
+            let update_order_payment_verification = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.update_order_payment_verification(0, [1; 32], None));
+            client
+                .call(
+                    &ink_e2e::charlie(),
+                    update_order_payment_verification,
+                    0,
+                    None,
+                )
+                .await
+                .expect("update_order_payment_verification failed");
+
+            // when the vendor releases the order
+            let charlie_balance: Balance = client.balance(charlie).await?;
+            let finalise_order = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.finalise_order(0));
+            client
+                .call(&ink_e2e::bob(), finalise_order, 0, None)
+                .await
+                .expect("finalise_order failed");
+            // * it pays the buyer
+            assert_eq!(client.balance(charlie).await?, charlie_balance + 100);
+            assert_eq!(client.balance(contract).await?, contract_balance + 900);
+
+            // when the vendor withdraws what's left
+            let withdraw_from_listing = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.withdraw_from_listing(0, 900));
+            client
+                .call(&ink_e2e::bob(), withdraw_from_listing, 0, None)
+                .await
+                .expect("withdraw_from_listing failed");
+            // * it returns the funds to the vendor
+            assert_eq!(client.balance(contract).await?, contract_balance);
+            let total_value_locked = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.total_value_locked());
+            let result: Balance = client
+                .call_dry_run(&ink_e2e::alice(), &total_value_locked, 0, None)
+                .await
+                .return_value();
+            assert_eq!(result, 0);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn e2e_dispute_cancelled_by_arbitrator(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let dave: AccountId = ink_e2e::account_id(ink_e2e::AccountKeyring::Dave);
+            let contract: AccountId = client
+                .instantiate(
+                    "escrow",
+                    &ink_e2e::bob(),
+                    EscrowRef::new(InstantiateArgs::default()),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let create_vendor = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.create_vendor(ETHEREUM_ADDRESS.to_string()));
+            client
+                .call(&ink_e2e::bob(), create_vendor, 0, None)
+                .await
+                .expect("create_vendor failed");
+            let create_listing = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.create_listing(None, "USD".to_string(), 1));
+            client
+                .call(&ink_e2e::bob(), create_listing, 0, None)
+                .await
+                .expect("create_listing failed");
+            let deposit_into_listing = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.deposit_into_listing(0));
+            client
+                .call(&ink_e2e::bob(), deposit_into_listing, 1_000, None)
+                .await
+                .expect("deposit_into_listing failed");
+            let create_order = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.create_order(0, 100));
+            client
+                .call(&ink_e2e::charlie(), create_order, 0, None)
+                .await
+                .expect("create_order failed");
+            let update_order_payment_verification = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.update_order_payment_verification(0, [1; 32], None));
+            client
+                .call(
+                    &ink_e2e::charlie(),
+                    update_order_payment_verification,
+                    0,
+                    None,
+                )
+                .await
+                .expect("update_order_payment_verification failed");
+
+            // when the vendor disputes the payment
+            let dispute_order =
+                build_message::<EscrowRef>(contract.clone()).call(|escrow| escrow.dispute_order(0));
+            client
+                .call(&ink_e2e::bob(), dispute_order, 0, None)
+                .await
+                .expect("dispute_order failed");
+
+            // when an arbitrator cancels the order
+            let grant_role = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.grant_role(Role::Arbitrator, dave));
+            client
+                .call(&ink_e2e::bob(), grant_role, 0, None)
+                .await
+                .expect("grant_role failed");
+            let contract_balance: Balance = client.balance(contract).await?;
+            let cancel_order =
+                build_message::<EscrowRef>(contract.clone()).call(|escrow| escrow.cancel_order(0));
+            client
+                .call(&ink_e2e::dave(), cancel_order, 0, None)
+                .await
+                .expect("cancel_order failed");
+            // * it keeps the funds in escrow
+            assert_eq!(client.balance(contract).await?, contract_balance);
+            // * it returns the amount to the listing
+            let show_listing =
+                build_message::<EscrowRef>(contract.clone()).call(|escrow| escrow.show_listing(0));
+            let listing: Listing = client
+                .call_dry_run(&ink_e2e::alice(), &show_listing, 0, None)
+                .await
+                .return_value()
+                .unwrap();
+            assert_eq!(listing.available_amount, 1_000);
+            // * it resolves the dispute
+            let open_disputes = build_message::<EscrowRef>(contract.clone())
+                .call(|escrow| escrow.open_disputes(0, 10));
+            let disputes: Page<Dispute> = client
+                .call_dry_run(&ink_e2e::alice(), &open_disputes, 0, None)
+                .await
+                .return_value();
+            assert!(disputes.items.is_empty());
+
+            Ok(())
         }
     }
 }
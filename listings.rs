@@ -0,0 +1,297 @@
+use crate::errors::EscrowError;
+use crate::pagination::{ChunkedIds, Direction, PaginatedMapping, ScanPage};
+use crate::{AccountId, Balance, Timestamp};
+use ink::prelude::{string::String, vec::Vec};
+use ink::storage::Mapping;
+
+// Orders against a listing that haven't been finalised or cancelled yet
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListingExposure {
+    pub(crate) orders_length: u32,
+    pub(crate) amount: Balance,
+}
+
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct Listing {
+    pub(crate) id: u32,
+    pub(crate) vendor: AccountId,
+    // None => native AZERO, Some => PSP22 token contract
+    pub(crate) asset: Option<AccountId>,
+    // ISO 4217 code of the fiat currency the buyer pays in e.g. USD
+    pub(crate) currency: String,
+    pub(crate) available_amount: Balance,
+    pub(crate) price_per_unit: Balance,
+    // Set by the vendor to stop taking orders without withdrawing
+    pub(crate) paused: bool,
+    // Closed listings no longer take orders or deposits
+    pub(crate) closed: bool,
+    // From when the listing stops taking orders
+    pub(crate) expires_at: Option<Timestamp>,
+}
+impl Listing {
+    // Can currently be ordered from
+    pub fn is_active(&self, now: Timestamp) -> bool {
+        self.available_amount > 0 && !self.paused && !self.closed && !self.is_expired(now)
+    }
+
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    pub fn summary(&self, now: Timestamp) -> ListingSummary {
+        ListingSummary {
+            id: self.id,
+            vendor: self.vendor,
+            asset: self.asset,
+            available_amount: self.available_amount,
+            price_per_unit: self.price_per_unit,
+            active: self.is_active(now),
+        }
+    }
+}
+
+// Listing as first stored under storage version 1, before listings could be paused
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct LegacyListingWithoutPause {
+    pub(crate) id: u32,
+    pub(crate) vendor: AccountId,
+    pub(crate) asset: Option<AccountId>,
+    pub(crate) currency: String,
+    pub(crate) available_amount: Balance,
+    pub(crate) price_per_unit: Balance,
+}
+impl From<LegacyListingWithoutPause> for Listing {
+    fn from(legacy: LegacyListingWithoutPause) -> Self {
+        Listing {
+            id: legacy.id,
+            vendor: legacy.vendor,
+            asset: legacy.asset,
+            currency: legacy.currency,
+            available_amount: legacy.available_amount,
+            price_per_unit: legacy.price_per_unit,
+            paused: false,
+            closed: false,
+            expires_at: None,
+        }
+    }
+}
+
+// Listing as stored under storage version 1 once listings could be paused, before they could
+// be closed or expire
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct LegacyListingWithoutClose {
+    pub(crate) id: u32,
+    pub(crate) vendor: AccountId,
+    pub(crate) asset: Option<AccountId>,
+    pub(crate) currency: String,
+    pub(crate) available_amount: Balance,
+    pub(crate) price_per_unit: Balance,
+    pub(crate) paused: bool,
+}
+impl From<LegacyListingWithoutClose> for Listing {
+    fn from(legacy: LegacyListingWithoutClose) -> Self {
+        Listing {
+            id: legacy.id,
+            vendor: legacy.vendor,
+            asset: legacy.asset,
+            currency: legacy.currency,
+            available_amount: legacy.available_amount,
+            price_per_unit: legacy.price_per_unit,
+            paused: legacy.paused,
+            closed: false,
+            expires_at: None,
+        }
+    }
+}
+
+// What index queries return for each listing, the full listing is available from show_listing
+#[derive(Debug, Clone, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct ListingSummary {
+    pub(crate) id: u32,
+    pub(crate) vendor: AccountId,
+    pub(crate) asset: Option<AccountId>,
+    pub(crate) available_amount: Balance,
+    pub(crate) price_per_unit: Balance,
+    // See Listing::is_active
+    pub(crate) active: bool,
+}
+
+#[derive(Debug, Default)]
+#[ink::storage_item]
+pub struct Listings {
+    pub(crate) values: PaginatedMapping<u32, Listing>,
+    // Ids of the listings for each asset and currency
+    pub(crate) ids_by_pair: ChunkedIds<(Option<AccountId>, String)>,
+    pub(crate) exposures: Mapping<u32, ListingExposure>,
+    // Kept apart from values so deposits, withdrawals and orders only rewrite the balance.
+    // Listings without an entry fall back to the available_amount stored with them.
+    pub(crate) available_amounts: Mapping<u32, Balance>,
+}
+impl Listings {
+    // Storage version 1 was kept while listings gained fields, so it's told apart which of
+    // those layouts each listing was stored with. Returns None for listings already stored
+    // as Listing.
+    pub fn get_v1(&self, id: u32) -> Result<Option<Listing>, EscrowError> {
+        if !self.values.contains(id) || self.values.get_exactly_as::<Listing>(id).is_some() {
+            Ok(None)
+        } else if let Some(legacy) = self.values.get_exactly_as::<LegacyListingWithoutClose>(id) {
+            Ok(Some(legacy.into()))
+        } else if let Some(legacy) = self.values.get_exactly_as::<LegacyListingWithoutPause>(id) {
+            Ok(Some(legacy.into()))
+        } else {
+            Err(EscrowError::StorageDecodeFailed)
+        }
+    }
+
+    pub fn available_amount(&self, id: u32) -> Option<Balance> {
+        self.available_amounts
+            .get(id)
+            .or_else(|| self.values.get(id).map(|listing| listing.available_amount))
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.values.contains(id)
+    }
+
+    pub fn get(&self, id: u32) -> Option<Listing> {
+        self.values
+            .get(id)
+            .map(|listing| self.with_available_amount(listing))
+    }
+
+    pub fn index(&self, after_id: Option<u32>, limit: u16, direction: Direction) -> Vec<Listing> {
+        self.values
+            .index(after_id, limit, direction)
+            .into_iter()
+            .map(|listing| self.with_available_amount(listing))
+            .collect()
+    }
+
+    pub fn index_where<F: Fn(&Listing) -> bool>(
+        &self,
+        after_id: Option<u32>,
+        limit: u16,
+        direction: Direction,
+        filter: F,
+    ) -> ScanPage<u32, Listing> {
+        let listings: ScanPage<u32, Listing> =
+            self.values
+                .index_where(after_id, limit, direction, |listing| {
+                    filter(&self.with_available_amount(listing.clone()))
+                });
+        ScanPage {
+            items: listings
+                .items
+                .into_iter()
+                .map(|listing| self.with_available_amount(listing))
+                .collect(),
+            next_after_id: listings.next_after_id,
+        }
+    }
+
+    pub fn index_by_pair(
+        &self,
+        asset: Option<AccountId>,
+        currency: String,
+        page: u32,
+        size: u16,
+    ) -> (Vec<Listing>, u32) {
+        let (ids, length) = self.ids_by_pair.page((asset, currency), page.into(), size);
+        let listings: Vec<Listing> = ids
+            .into_iter()
+            .filter_map(|id| self.get(id as u32))
+            .collect();
+        (listings, length as u32)
+    }
+
+    pub fn length(&self) -> u32 {
+        self.values.length()
+    }
+
+    pub fn create(&mut self, value: &Listing) {
+        if self.values.push(value) {
+            self.add_to_indexes(value);
+        }
+    }
+
+    // Stores a listing exported by another instance under its id. Returns false if that id
+    // isn't after every listing stored so far.
+    pub fn import(&mut self, value: &Listing) -> bool {
+        if !self.values.push_at(value.id, value) {
+            return false;
+        }
+        self.add_to_indexes(value);
+        true
+    }
+
+    fn add_to_indexes(&mut self, value: &Listing) {
+        self.available_amounts
+            .insert(value.id, &value.available_amount);
+        self.ids_by_pair
+            .push((value.asset, value.currency.clone()), value.id.into());
+    }
+
+    pub fn update(&mut self, value: &Listing) {
+        self.values.update(value.id, value);
+        self.available_amounts
+            .insert(value.id, &value.available_amount);
+    }
+
+    // For when available_amount is the only change, leaving the rest of the listing untouched
+    pub fn update_available_amount(&mut self, id: u32, amount: Balance) {
+        self.available_amounts.insert(id, &amount);
+    }
+
+    pub fn expose(&mut self, id: u32, amount: Balance) -> Result<(), EscrowError> {
+        let mut exposure: ListingExposure = self.exposures.get(id).unwrap_or_default();
+        exposure.orders_length += 1;
+        exposure.amount = exposure
+            .amount
+            .checked_add(amount)
+            .ok_or(EscrowError::Overflow)?;
+        self.exposures.insert(id, &exposure);
+        Ok(())
+    }
+
+    pub fn unexpose(&mut self, id: u32, amount: Balance) -> Result<(), EscrowError> {
+        let mut exposure: ListingExposure = self.exposures.get(id).unwrap_or_default();
+        exposure.orders_length = exposure
+            .orders_length
+            .checked_sub(1)
+            .ok_or(EscrowError::Underflow)?;
+        exposure.amount = exposure
+            .amount
+            .checked_sub(amount)
+            .ok_or(EscrowError::Underflow)?;
+        self.exposures.insert(id, &exposure);
+        Ok(())
+    }
+
+    fn with_available_amount(&self, mut listing: Listing) -> Listing {
+        if let Some(amount) = self.available_amounts.get(listing.id) {
+            listing.available_amount = amount;
+        }
+        listing
+    }
+}
@@ -0,0 +1,744 @@
+use crate::errors::EscrowError;
+use crate::escrow::{BuyerSummary, CancelReason, STATUS_TRANSITIONS_CAPACITY};
+use crate::listings::Listing;
+use crate::pagination::{page_bounds, ChunkedIdSet, ChunkedIds, Direction, PaginatedMapping};
+use crate::vendors::VendorProfile;
+use crate::{AccountId, Balance, Timestamp};
+use ink::env::hash::Blake2x256;
+use ink::prelude::{string::String, vec, vec::Vec};
+use ink::storage::Mapping;
+
+// Orders store the u8 value of their status so events and frontends can keep using it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum OrderStatus {
+    Open = 0,
+    PendingVerification = 1,
+    Finalised = 2,
+    Cancelled = 3,
+    Disputed = 4,
+}
+// Anything that takes a status as a u8 decodes it through here, so that unknown values
+// are rejected rather than treated as a status of their own
+impl TryFrom<u8> for OrderStatus {
+    type Error = EscrowError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(OrderStatus::Open),
+            1 => Ok(OrderStatus::PendingVerification),
+            2 => Ok(OrderStatus::Finalised),
+            3 => Ok(OrderStatus::Cancelled),
+            4 => Ok(OrderStatus::Disputed),
+            _ => Err(EscrowError::InvalidStatus),
+        }
+    }
+}
+
+// Order as first stored under storage version 1, before order timeouts
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct LegacyOrderWithoutTimestamps {
+    pub(crate) id: u64,
+    pub(crate) buyer: AccountId,
+    pub(crate) vendor: AccountId,
+    pub(crate) amount: Balance,
+    pub(crate) payment_verification: Option<String>,
+    pub(crate) status: u8,
+    pub(crate) listing_id: u32,
+    pub(crate) total_price: Balance,
+}
+impl LegacyOrderWithoutTimestamps {
+    // Creation times weren't recorded, and the status is treated as changed at status_updated_at
+    pub fn into_legacy(self, status_updated_at: Timestamp) -> LegacyOrder {
+        LegacyOrder {
+            id: self.id,
+            buyer: self.buyer,
+            vendor: self.vendor,
+            amount: self.amount,
+            payment_verification: self.payment_verification,
+            status: self.status,
+            listing_id: self.listing_id,
+            total_price: self.total_price,
+            created_at: 0,
+            status_updated_at,
+        }
+    }
+}
+
+// Order as stored under storage version 1 once order timeouts were added, before order creation
+// times were recorded
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct LegacyOrderWithoutCreatedAt {
+    pub(crate) id: u64,
+    pub(crate) buyer: AccountId,
+    pub(crate) vendor: AccountId,
+    pub(crate) amount: Balance,
+    pub(crate) payment_verification: Option<String>,
+    pub(crate) status: u8,
+    pub(crate) listing_id: u32,
+    pub(crate) total_price: Balance,
+    pub(crate) status_updated_at: Timestamp,
+}
+impl From<LegacyOrderWithoutCreatedAt> for LegacyOrder {
+    // Orders stored like this are older than any with a creation time, so zero keeps ids in
+    // creation order
+    fn from(legacy: LegacyOrderWithoutCreatedAt) -> Self {
+        LegacyOrder {
+            id: legacy.id,
+            buyer: legacy.buyer,
+            vendor: legacy.vendor,
+            amount: legacy.amount,
+            payment_verification: legacy.payment_verification,
+            status: legacy.status,
+            listing_id: legacy.listing_id,
+            total_price: legacy.total_price,
+            created_at: 0,
+            status_updated_at: legacy.status_updated_at,
+        }
+    }
+}
+
+// Order as last stored under storage version 1, when payment verifications were kept as strings
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct LegacyOrder {
+    pub(crate) id: u64,
+    pub(crate) buyer: AccountId,
+    pub(crate) vendor: AccountId,
+    pub(crate) amount: Balance,
+    pub(crate) payment_verification: Option<String>,
+    pub(crate) status: u8,
+    pub(crate) listing_id: u32,
+    pub(crate) total_price: Balance,
+    pub(crate) created_at: Timestamp,
+    pub(crate) status_updated_at: Timestamp,
+}
+impl From<LegacyOrder> for UnpackedOrder {
+    // Keeps the hash of the old payment verification in its place
+    fn from(legacy: LegacyOrder) -> Self {
+        let payment_verification: Option<[u8; 32]> =
+            legacy.payment_verification.map(|payment_verification| {
+                let mut hash: [u8; 32] = [0; 32];
+                ink::env::hash_bytes::<Blake2x256>(payment_verification.as_bytes(), &mut hash);
+                hash
+            });
+        UnpackedOrder {
+            id: legacy.id,
+            buyer: legacy.buyer,
+            vendor: legacy.vendor,
+            amount: legacy.amount,
+            payment_verification,
+            status: legacy.status,
+            listing_id: legacy.listing_id,
+            total_price: legacy.total_price,
+            created_at: legacy.created_at,
+            status_updated_at: legacy.status_updated_at,
+        }
+    }
+}
+
+// Order as stored in storage versions 2 and 3, before its status was packed into OrderState
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct UnpackedOrder {
+    pub(crate) id: u64,
+    pub(crate) buyer: AccountId,
+    pub(crate) vendor: AccountId,
+    pub(crate) amount: Balance,
+    pub(crate) payment_verification: Option<[u8; 32]>,
+    pub(crate) status: u8,
+    pub(crate) listing_id: u32,
+    pub(crate) total_price: Balance,
+    pub(crate) created_at: Timestamp,
+    pub(crate) status_updated_at: Timestamp,
+}
+impl From<UnpackedOrder> for Order {
+    fn from(unpacked: UnpackedOrder) -> Self {
+        Order {
+            id: unpacked.id,
+            buyer: unpacked.buyer,
+            vendor: unpacked.vendor,
+            amount: unpacked.amount,
+            payment_verification: unpacked.payment_verification,
+            state: OrderState::new(unpacked.status),
+            listing_id: unpacked.listing_id,
+            total_price: unpacked.total_price,
+            created_at: unpacked.created_at,
+            status_updated_at: unpacked.status_updated_at,
+        }
+    }
+}
+
+// Status, flags and terminal reason of an order, packed into one word
+// bits 0-7 => status, see OrderStatus
+// bit 8 => has been disputed at some point
+// bits 16-23 => CancelReason code plus one, 0 unless a moderator cancelled the order
+// bits 24-27 => rating from 1 to 5 the buyer gave the vendor, 0 until rated
+// bits 28-31 => rating from 1 to 5 the vendor gave the buyer, 0 until rated
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OrderState(u32);
+impl OrderState {
+    const STATUS_MASK: u32 = 0xff;
+    const DISPUTED: u32 = 1 << 8;
+    const CANCEL_REASON_SHIFT: u32 = 16;
+    const CANCEL_REASON_MASK: u32 = 0xff << Self::CANCEL_REASON_SHIFT;
+    const BUYER_RATING_SHIFT: u32 = 24;
+    const VENDOR_RATING_SHIFT: u32 = 28;
+    const RATING_MASK: u32 = 0xf;
+
+    pub fn new(status: u8) -> Self {
+        OrderState(status.into())
+    }
+
+    pub fn status(&self) -> u8 {
+        (self.0 & Self::STATUS_MASK) as u8
+    }
+
+    pub fn set_status(&mut self, status: u8) {
+        self.0 = (self.0 & !Self::STATUS_MASK) | u32::from(status);
+    }
+
+    pub fn disputed(&self) -> bool {
+        self.0 & Self::DISPUTED != 0
+    }
+
+    pub fn set_disputed(&mut self) {
+        self.0 |= Self::DISPUTED;
+    }
+
+    pub fn cancel_reason(&self) -> Option<CancelReason> {
+        match (self.0 & Self::CANCEL_REASON_MASK) >> Self::CANCEL_REASON_SHIFT {
+            1 => Some(CancelReason::BuyerUnresponsive),
+            2 => Some(CancelReason::Duplicate),
+            3 => Some(CancelReason::Fraud),
+            4 => Some(CancelReason::Other),
+            5 => Some(CancelReason::VendorUnresponsive),
+            _ => None,
+        }
+    }
+
+    pub fn set_cancel_reason(&mut self, reason: CancelReason) {
+        let code: u32 = reason as u32 + 1;
+        self.0 = (self.0 & !Self::CANCEL_REASON_MASK) | (code << Self::CANCEL_REASON_SHIFT);
+    }
+
+    pub fn rating_by_buyer(&self) -> Option<u8> {
+        self.rating(Self::BUYER_RATING_SHIFT)
+    }
+
+    pub fn set_rating_by_buyer(&mut self, rating: u8) {
+        self.set_rating(Self::BUYER_RATING_SHIFT, rating)
+    }
+
+    pub fn rating_by_vendor(&self) -> Option<u8> {
+        self.rating(Self::VENDOR_RATING_SHIFT)
+    }
+
+    pub fn set_rating_by_vendor(&mut self, rating: u8) {
+        self.set_rating(Self::VENDOR_RATING_SHIFT, rating)
+    }
+
+    fn rating(&self, shift: u32) -> Option<u8> {
+        match (self.0 >> shift) & Self::RATING_MASK {
+            0 => None,
+            rating => Some(rating as u8),
+        }
+    }
+
+    fn set_rating(&mut self, shift: u32, rating: u8) {
+        let rating: u32 = u32::from(rating) & Self::RATING_MASK;
+        self.0 = (self.0 & !(Self::RATING_MASK << shift)) | (rating << shift);
+    }
+}
+
+// What a buyer signs off-chain so that a relayer can create the order for them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct OrderIntent {
+    pub(crate) listing_id: u32,
+    pub(crate) amount: Balance,
+    // Must match the buyer's current nonce, so each intent can only be used once
+    pub(crate) nonce: u64,
+    pub(crate) deadline: Timestamp,
+}
+
+// What a buyer signs off-chain so that a relayer can act on one of their orders for them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct OrderActionIntent {
+    pub(crate) order_id: u64,
+    pub(crate) action: OrderAction,
+    // Shared with OrderIntent, so each intent can only be used once
+    pub(crate) nonce: u64,
+    pub(crate) deadline: Timestamp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum OrderAction {
+    Cancel,
+    SubmitPaymentVerification([u8; 32]),
+}
+
+// An order with its listing's current terms, the buyer's order history and the vendor's profile
+#[derive(Debug, Clone, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct OrderDetail {
+    pub(crate) order: Order,
+    pub(crate) listing: Listing,
+    pub(crate) buyer: BuyerSummary,
+    pub(crate) vendor: Option<VendorProfile>,
+}
+
+// Order statuses, see OrderStatus
+// 0 => Open
+// 1 => PendingVerification
+// 2 => Finalised
+// 3 => Cancelled
+// 4 => Disputed
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct Order {
+    pub(crate) id: u64,
+    pub(crate) buyer: AccountId,
+    pub(crate) vendor: AccountId,
+    pub(crate) amount: Balance,
+    // Hash of the buyer's proof of payment, which is kept off-chain
+    pub(crate) payment_verification: Option<[u8; 32]>,
+    pub(crate) state: OrderState,
+    pub(crate) listing_id: u32,
+    pub(crate) total_price: Balance,
+    pub(crate) created_at: Timestamp,
+    // Start of the current status' timeout window
+    pub(crate) status_updated_at: Timestamp,
+}
+impl Order {
+    pub fn status(&self) -> u8 {
+        self.state.status()
+    }
+
+    // Not yet finalised or cancelled, so its amount is still held for it
+    pub fn is_active(&self) -> bool {
+        self.status() != OrderStatus::Finalised as u8
+            && self.status() != OrderStatus::Cancelled as u8
+    }
+
+    pub fn summary(&self) -> OrderSummary {
+        OrderSummary {
+            id: self.id,
+            buyer: self.buyer,
+            vendor: self.vendor,
+            listing_id: self.listing_id,
+            amount: self.amount,
+            total_price: self.total_price,
+            status: self.status(),
+            created_at: self.created_at,
+        }
+    }
+
+    // Every status change goes through here so that no message can skip or reverse a step
+    pub fn try_transition(&mut self, to: OrderStatus) -> Result<(), EscrowError> {
+        let from: OrderStatus = OrderStatus::try_from(self.status())?;
+        let allowed: bool = matches!(
+            (from, to),
+            (
+                OrderStatus::Open,
+                OrderStatus::PendingVerification | OrderStatus::Finalised | OrderStatus::Cancelled
+            ) | (
+                OrderStatus::PendingVerification,
+                OrderStatus::PendingVerification
+                    | OrderStatus::Finalised
+                    | OrderStatus::Cancelled
+                    | OrderStatus::Disputed
+            ) | (
+                OrderStatus::Disputed,
+                OrderStatus::Finalised | OrderStatus::Cancelled
+            )
+        );
+        if !allowed {
+            return Err(EscrowError::StatusCanNotBeChanged);
+        }
+
+        self.state.set_status(to as u8);
+        if to == OrderStatus::Disputed {
+            self.state.set_disputed();
+        }
+        Ok(())
+    }
+}
+
+// What index queries return for each order, the full order is available from show_order
+#[derive(Debug, Clone, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct OrderSummary {
+    pub(crate) id: u64,
+    pub(crate) buyer: AccountId,
+    pub(crate) vendor: AccountId,
+    pub(crate) listing_id: u32,
+    pub(crate) amount: Balance,
+    pub(crate) total_price: Balance,
+    pub(crate) status: u8,
+    pub(crate) created_at: Timestamp,
+}
+
+#[derive(Debug, Default)]
+#[ink::storage_item]
+pub struct Orders {
+    pub(crate) values: PaginatedMapping<u64, Order>,
+    // Orders that are not yet finalised or cancelled
+    pub(crate) active_length: u64,
+    pub(crate) active_length_by_buyer: Mapping<AccountId, u32>,
+    pub(crate) ids_by_buyer: ChunkedIds<AccountId>,
+    pub(crate) ids_by_vendor: ChunkedIds<AccountId>,
+    // Ids in the order they reached each status, see ChunkedIdSet
+    pub(crate) ids_by_status: ChunkedIdSet<u8>,
+    // Ids of each vendor's orders that are awaiting payment verification, see ChunkedIdSet
+    pub(crate) pending_verification_ids_by_vendor: ChunkedIdSet<AccountId>,
+    // Ring buffer of the last STATUS_TRANSITIONS_CAPACITY status changes
+    pub(crate) transitions: Mapping<u64, StatusTransition>,
+    // Total number of status changes ever recorded
+    pub(crate) transitions_length: u64,
+}
+impl Orders {
+    // Storage version 1 was kept while orders gained fields, so it's told apart which of those
+    // layouts each order was stored with. Orders from before timeouts start theirs at now.
+    pub fn get_v1(&self, id: u64, now: Timestamp) -> Result<Option<LegacyOrder>, EscrowError> {
+        if !self.values.contains(id) {
+            return Ok(None);
+        }
+        if let Some(legacy) = self.values.get_exactly_as::<LegacyOrder>(id) {
+            Ok(Some(legacy))
+        } else if let Some(legacy) = self
+            .values
+            .get_exactly_as::<LegacyOrderWithoutCreatedAt>(id)
+        {
+            Ok(Some(legacy.into()))
+        } else if let Some(legacy) = self
+            .values
+            .get_exactly_as::<LegacyOrderWithoutTimestamps>(id)
+        {
+            Ok(Some(legacy.into_legacy(now)))
+        } else {
+            Err(EscrowError::StorageDecodeFailed)
+        }
+    }
+
+    pub fn index(&self, after_id: Option<u64>, limit: u16, direction: Direction) -> Vec<Order> {
+        self.values.index(after_id, limit, direction)
+    }
+
+    pub fn index_by_buyer(&self, buyer: AccountId, page: u64, size: u16) -> (Vec<Order>, u64) {
+        let (ids, length) = self.ids_by_buyer.page(buyer, page, size);
+        (self.get_all(&ids), length)
+    }
+
+    pub fn index_pending_verifications_by_vendor(
+        &self,
+        vendor: AccountId,
+        page: u64,
+        size: u16,
+    ) -> (Vec<Order>, u64) {
+        let (ids, length) = self
+            .pending_verification_ids_by_vendor
+            .page(vendor, page, size);
+        (self.get_all(&ids), length)
+    }
+
+    pub fn index_by_vendor(&self, vendor: AccountId, page: u64, size: u16) -> (Vec<Order>, u64) {
+        let (ids, length) = self.ids_by_vendor.page(vendor, page, size);
+        (self.get_all(&ids), length)
+    }
+
+    pub fn index_by_status(&self, status: u8, page: u64, size: u16) -> (Vec<Order>, u64) {
+        let (ids, length) = self.ids_by_status.page(status, page, size);
+        (self.get_all(&ids), length)
+    }
+
+    // Oldest first page of the orders created from (inclusive) to (exclusive),
+    // relying on ids being assigned in creation order
+    pub fn index_created_between(
+        &self,
+        from: Timestamp,
+        to: Timestamp,
+        page: u64,
+        size: u16,
+    ) -> (Vec<Order>, u64) {
+        let mut orders: Vec<Order> = vec![];
+        let starting_id: u64 = self.first_created_at_or_after(from);
+        let ending_id: u64 = self.first_created_at_or_after(to).max(starting_id);
+        let length: u64 = ending_id - starting_id;
+        let (start, end) = page_bounds(length, page, size);
+        for offset in start..end {
+            if let Some(order) = self.values.get(starting_id + offset) {
+                orders.push(order)
+            }
+        }
+        (orders, length)
+    }
+
+    // Binary search for the first order created at or after timestamp
+    fn first_created_at_or_after(&self, timestamp: Timestamp) -> u64 {
+        let mut low: u64 = 0;
+        let mut high: u64 = self.values.length();
+        while low < high {
+            let mid: u64 = low + (high - low) / 2;
+            // Archived orders leave gaps, so mid is compared using the next stored order
+            match (mid..high).find_map(|id| self.values.get(id)) {
+                Some(order) if order.created_at < timestamp => low = order.id + 1,
+                _ => high = mid,
+            }
+        }
+        low
+    }
+
+    // Leaves out ids whose order is no longer stored
+    fn get_all(&self, ids: &[u64]) -> Vec<Order> {
+        ids.iter().filter_map(|id| self.values.get(*id)).collect()
+    }
+
+    pub fn length(&self) -> u64 {
+        self.values.length()
+    }
+
+    pub fn create(&mut self, value: &Order) {
+        if self.values.push(value) {
+            self.add_to_indexes(value);
+        }
+    }
+
+    // Stores an order exported by another instance under its id. Returns false if that id
+    // isn't after every order stored so far.
+    pub fn import(&mut self, value: &Order) -> bool {
+        if !self.values.push_at(value.id, value) {
+            return false;
+        }
+        self.add_to_indexes(value);
+        if value.status() == OrderStatus::PendingVerification as u8 {
+            self.pending_verification_ids_by_vendor
+                .insert(value.vendor, value.id);
+        }
+        true
+    }
+
+    fn add_to_indexes(&mut self, value: &Order) {
+        if value.is_active() {
+            self.active_length += 1;
+            let active_length_by_buyer: u32 =
+                self.active_length_by_buyer.get(value.buyer).unwrap_or(0) + 1;
+            self.active_length_by_buyer
+                .insert(value.buyer, &active_length_by_buyer);
+        }
+        self.ids_by_buyer.push(value.buyer, value.id);
+        self.ids_by_vendor.push(value.vendor, value.id);
+        self.ids_by_status.insert(value.status(), value.id);
+    }
+
+    // Removes a finalised or cancelled order along with its place in the indexes
+    pub fn archive(&mut self, value: &Order) {
+        self.values.remove(value.id);
+        self.ids_by_buyer.remove(value.buyer, value.id);
+        self.ids_by_vendor.remove(value.vendor, value.id);
+        self.ids_by_status.remove(value.status(), value.id);
+    }
+
+    // Called once an order is finalised or cancelled
+    pub fn deactivate(&mut self, value: &Order) -> Result<(), EscrowError> {
+        let active_length: u64 = self
+            .active_length
+            .checked_sub(1)
+            .ok_or(EscrowError::Underflow)?;
+        let active_length_by_buyer: u32 = self
+            .active_length_by_buyer
+            .get(value.buyer)
+            .unwrap_or(0)
+            .checked_sub(1)
+            .ok_or(EscrowError::Underflow)?;
+        self.active_length = active_length;
+        self.active_length_by_buyer
+            .insert(value.buyer, &active_length_by_buyer);
+        Ok(())
+    }
+
+    pub fn update(&mut self, value: &Order) {
+        if let Some(previous) = self.values.get(value.id) {
+            if previous.status() != value.status() {
+                self.ids_by_status.remove(previous.status(), value.id);
+                self.ids_by_status.insert(value.status(), value.id);
+                if value.status() == OrderStatus::PendingVerification as u8 {
+                    self.pending_verification_ids_by_vendor
+                        .insert(value.vendor, value.id);
+                } else if previous.status() == OrderStatus::PendingVerification as u8 {
+                    self.pending_verification_ids_by_vendor
+                        .remove(value.vendor, value.id);
+                }
+                let transition: StatusTransition = StatusTransition {
+                    order_id: value.id,
+                    from: previous.status(),
+                    to: value.status(),
+                    timestamp: value.status_updated_at,
+                };
+                self.transitions.insert(
+                    self.transitions_length % STATUS_TRANSITIONS_CAPACITY,
+                    &transition,
+                );
+                self.transitions_length += 1;
+            }
+        }
+        self.values.update(value.id, value);
+    }
+}
+
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusTransition {
+    pub(crate) order_id: u64,
+    pub(crate) from: u8,
+    pub(crate) to: u8,
+    pub(crate) timestamp: Timestamp,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openbrush::test_utils;
+
+    #[ink::test]
+    fn test_order_state() {
+        let mut state: OrderState = OrderState::new(OrderStatus::PendingVerification as u8);
+        assert_eq!(state.status(), 1);
+        assert!(!state.disputed());
+        assert_eq!(state.cancel_reason(), None);
+        // when the flag and reason are set
+        state.set_disputed();
+        state.set_cancel_reason(CancelReason::Fraud);
+        // * it keeps the status
+        assert_eq!(state.status(), 1);
+        assert!(state.disputed());
+        assert_eq!(state.cancel_reason(), Some(CancelReason::Fraud));
+        // when the status changes
+        state.set_status(OrderStatus::Cancelled as u8);
+        // * it keeps the flag and reason
+        assert_eq!(state.status(), 3);
+        assert!(state.disputed());
+        assert_eq!(state.cancel_reason(), Some(CancelReason::Fraud));
+        // when the reason is replaced
+        state.set_cancel_reason(CancelReason::BuyerUnresponsive);
+        // * it only keeps the latest
+        assert_eq!(state.cancel_reason(), Some(CancelReason::BuyerUnresponsive));
+        // when the parties rate each other
+        assert_eq!(state.rating_by_buyer(), None);
+        assert_eq!(state.rating_by_vendor(), None);
+        state.set_rating_by_buyer(5);
+        state.set_rating_by_vendor(1);
+        // * it keeps both ratings apart from everything else
+        assert_eq!(state.rating_by_buyer(), Some(5));
+        assert_eq!(state.rating_by_vendor(), Some(1));
+        assert_eq!(state.status(), 3);
+        assert!(state.disputed());
+        assert_eq!(state.cancel_reason(), Some(CancelReason::BuyerUnresponsive));
+    }
+
+    #[ink::test]
+    fn test_order_try_transition() {
+        let accounts = test_utils::accounts();
+        let statuses: [OrderStatus; 5] = [
+            OrderStatus::Open,
+            OrderStatus::PendingVerification,
+            OrderStatus::Finalised,
+            OrderStatus::Cancelled,
+            OrderStatus::Disputed,
+        ];
+        let allowed: [(OrderStatus, OrderStatus); 9] = [
+            (OrderStatus::Open, OrderStatus::PendingVerification),
+            (OrderStatus::Open, OrderStatus::Finalised),
+            (OrderStatus::Open, OrderStatus::Cancelled),
+            (
+                OrderStatus::PendingVerification,
+                OrderStatus::PendingVerification,
+            ),
+            (OrderStatus::PendingVerification, OrderStatus::Finalised),
+            (OrderStatus::PendingVerification, OrderStatus::Cancelled),
+            (OrderStatus::PendingVerification, OrderStatus::Disputed),
+            (OrderStatus::Disputed, OrderStatus::Finalised),
+            (OrderStatus::Disputed, OrderStatus::Cancelled),
+        ];
+        for from in statuses {
+            for to in statuses {
+                let mut order: Order = Order {
+                    id: 0,
+                    buyer: accounts.alice,
+                    vendor: accounts.bob,
+                    amount: 1,
+                    payment_verification: None,
+                    state: OrderState::new(from as u8),
+                    listing_id: 0,
+                    total_price: 1,
+                    created_at: 0,
+                    status_updated_at: 0,
+                };
+                let result = order.try_transition(to);
+                if allowed.contains(&(from, to)) {
+                    // when the transition is part of the lifecycle
+                    // * it updates the status
+                    assert!(result.is_ok());
+                    assert_eq!(order.status(), to as u8);
+                    assert_eq!(order.state.disputed(), to == OrderStatus::Disputed);
+                } else {
+                    // when the transition is not part of the lifecycle
+                    // * it raises an error and leaves the status alone
+                    assert_eq!(result, Err(EscrowError::StatusCanNotBeChanged));
+                    assert_eq!(order.status(), from as u8);
+                }
+            }
+        }
+        // when the stored status is unknown
+        // * it raises an error
+        let mut order: Order = Order {
+            id: 0,
+            buyer: accounts.alice,
+            vendor: accounts.bob,
+            amount: 1,
+            payment_verification: None,
+            state: OrderState::new(5),
+            listing_id: 0,
+            total_price: 1,
+            created_at: 0,
+            status_updated_at: 0,
+        };
+        assert_eq!(
+            order.try_transition(OrderStatus::Cancelled),
+            Err(EscrowError::InvalidStatus)
+        );
+    }
+}
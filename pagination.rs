@@ -0,0 +1,666 @@
+use crate::errors::EscrowError;
+use crate::escrow::{ID_CHUNK_SIZE, MAX_SCAN_LENGTH};
+use crate::Key;
+use ink::prelude::{vec, vec::Vec};
+use ink::storage::{
+    traits::{Packed, StorageKey},
+    Mapping,
+};
+
+// Order of pages returned by listings and orders, by id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct Page<T> {
+    pub(crate) items: Vec<T>,
+    // Number of items across all pages
+    pub(crate) total: u64,
+    pub(crate) has_more: bool,
+}
+impl<T> Page<T> {
+    // For items starting at offset within the total
+    pub fn new(items: Vec<T>, total: u64, offset: u64) -> Self {
+        let has_more: bool = offset.saturating_add(items.len() as u64) < total;
+        Page {
+            items,
+            total,
+            has_more,
+        }
+    }
+}
+
+// Values found by a filtered index, which stops after MAX_SCAN_LENGTH ids, along with the
+// after_id to carry on from. That's None once the index has reached the end.
+#[derive(Debug, Clone, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct ScanPage<K, T> {
+    pub(crate) items: Vec<T>,
+    pub(crate) next_after_id: Option<K>,
+}
+
+// Start (inclusive) and end (exclusive) offsets of a page within length items.
+// Pages past the end are empty rather than wrapping or reaching outside the items.
+pub fn page_bounds(length: u64, page: u64, size: u16) -> (u64, u64) {
+    let start: u64 = page.saturating_mul(size.into()).min(length);
+    let end: u64 = start.saturating_add(size.into()).min(length);
+    (start, end)
+}
+
+// Decodes the value stored under key with an older layout, for migrations. Values that don't
+// decode as L raise an error, so that the migration step fails instead of skipping them.
+pub fn get_stored_as<K: Packed + core::fmt::Debug, L: Packed>(
+    key: &(Key, K),
+) -> Result<Option<L>, EscrowError> {
+    ink::env::get_contract_storage::<(Key, K), L>(key).map_err(|_| EscrowError::StorageDecodeFailed)
+}
+
+// Like get_stored_as, but only if the value was stored with exactly L's layout. Decoding leaves
+// any bytes after L's fields unread, so this tells apart layouts stored under the same version.
+pub fn get_stored_exactly_as<K: Packed + core::fmt::Debug, L: Packed + scale::Encode>(
+    key: &(Key, K),
+) -> Option<L> {
+    let size: u32 = ink::env::contains_contract_storage::<(Key, K)>(key)?;
+    ink::env::get_contract_storage::<(Key, K), L>(key)
+        .ok()
+        .flatten()
+        .filter(|value| value.encoded_size() == size as usize)
+}
+
+// Ids that PaginatedMapping can hand out sequentially
+pub trait SequentialId: Packed + Copy + Ord + core::fmt::Debug {
+    fn from_u64(value: u64) -> Self;
+    fn to_u64(self) -> u64;
+}
+impl SequentialId for u32 {
+    fn from_u64(value: u64) -> Self {
+        u32::try_from(value).unwrap()
+    }
+
+    fn to_u64(self) -> u64 {
+        self.into()
+    }
+}
+impl SequentialId for u64 {
+    fn from_u64(value: u64) -> Self {
+        value
+    }
+
+    fn to_u64(self) -> u64 {
+        self
+    }
+}
+
+// Values stored under sequential ids starting from zero, with cursor and page based indexes.
+// Collections keep any extra indexes of their own alongside one of these.
+#[derive(Debug)]
+#[ink::storage_item]
+pub struct PaginatedMapping<K: SequentialId, V: Packed> {
+    pub(crate) values: Mapping<K, V>,
+    pub(crate) length: K,
+}
+impl<K: SequentialId, V: Packed> Default for PaginatedMapping<K, V> {
+    fn default() -> Self {
+        PaginatedMapping {
+            values: Mapping::default(),
+            length: K::from_u64(0),
+        }
+    }
+}
+impl<K: SequentialId, V: Packed> PaginatedMapping<K, V> {
+    pub fn length(&self) -> K {
+        self.length
+    }
+
+    pub fn get(&self, id: K) -> Option<V> {
+        self.values.get(id)
+    }
+
+    pub fn contains(&self, id: K) -> bool {
+        self.values.contains(id)
+    }
+
+    // Stores value under the next id. Returns false if that id is already taken.
+    pub fn push(&mut self, value: &V) -> bool {
+        if self.values.contains(self.length) {
+            return false;
+        }
+        self.values.insert(self.length, value);
+        self.length = K::from_u64(self.length.to_u64() + 1);
+        true
+    }
+
+    // Stores value under an id at or past the end, for values carried over from another
+    // instance. Ids skipped over stay empty, like removed ones. Returns false for earlier ids.
+    pub fn push_at(&mut self, id: K, value: &V) -> bool {
+        if id.to_u64() < self.length.to_u64() {
+            return false;
+        }
+        self.values.insert(id, value);
+        self.length = K::from_u64(id.to_u64() + 1);
+        true
+    }
+
+    pub fn update(&mut self, id: K, value: &V) {
+        self.values.insert(id, value);
+    }
+
+    // Ids stay taken once their value is removed, and indexes skip over them
+    pub fn remove(&mut self, id: K) {
+        self.values.remove(id);
+    }
+
+    // See get_stored_as
+    pub fn get_as<L: Packed>(&self, id: K) -> Result<Option<L>, EscrowError> {
+        get_stored_as::<K, L>(&(self.values.key(), id))
+    }
+
+    // See get_stored_exactly_as
+    pub fn get_exactly_as<L: Packed + scale::Encode>(&self, id: K) -> Option<L> {
+        get_stored_exactly_as::<K, L>(&(self.values.key(), id))
+    }
+
+    // Encodes value under id with an older layout, for migrations that pass through several
+    pub fn update_as<L: Packed>(&mut self, id: K, value: &L) {
+        ink::env::set_contract_storage::<(Key, K), L>(&(self.values.key(), id), value);
+    }
+
+    // Up to limit items after the after_id cursor, exclusive, in the given direction
+    pub fn index(&self, after_id: Option<K>, limit: u16, direction: Direction) -> Vec<V> {
+        self.scan(after_id, limit, direction, u64::MAX, |_| true)
+            .items
+    }
+
+    // Like index, but skipping values that don't match the filter. As those don't count
+    // towards the limit, it gives up after MAX_SCAN_LENGTH ids and returns where it got to.
+    pub fn index_where<F: Fn(&V) -> bool>(
+        &self,
+        after_id: Option<K>,
+        limit: u16,
+        direction: Direction,
+        filter: F,
+    ) -> ScanPage<K, V> {
+        self.scan(after_id, limit, direction, MAX_SCAN_LENGTH, filter)
+    }
+
+    fn scan<F: Fn(&V) -> bool>(
+        &self,
+        after_id: Option<K>,
+        limit: u16,
+        direction: Direction,
+        max_scan_length: u64,
+        filter: F,
+    ) -> ScanPage<K, V> {
+        let mut values: Vec<V> = vec![];
+        let length: u64 = self.length.to_u64();
+        let after_id: Option<u64> = after_id.map(|id| id.to_u64());
+        let mut next_id: Option<u64> = match direction {
+            Direction::Ascending => after_id.map_or(Some(0), |id| id.checked_add(1)),
+            Direction::Descending => after_id.map_or(length, |id| id.min(length)).checked_sub(1),
+        };
+        let mut last_id: Option<u64> = after_id;
+        let mut scanned: u64 = 0;
+        while let Some(id) = next_id {
+            if id >= length {
+                next_id = None;
+                break;
+            }
+            if values.len() == usize::from(limit) || scanned == max_scan_length {
+                break;
+            }
+            if let Some(value) = self.values.get(K::from_u64(id)) {
+                if filter(&value) {
+                    values.push(value)
+                }
+            }
+            scanned += 1;
+            last_id = Some(id);
+            next_id = match direction {
+                Direction::Ascending => id.checked_add(1),
+                Direction::Descending => id.checked_sub(1),
+            };
+        }
+        ScanPage {
+            items: values,
+            next_after_id: next_id.and(last_id).map(K::from_u64),
+        }
+    }
+
+    // Newest first page of all ids, leaving out any that have been removed
+    pub fn page(&self, page: u64, size: u16) -> Vec<V> {
+        let mut values: Vec<V> = vec![];
+        let length: u64 = self.length.to_u64();
+        let (start, end) = page_bounds(length, page, size);
+        for offset in start..end {
+            if let Some(value) = self.values.get(K::from_u64(length - 1 - offset)) {
+                values.push(value)
+            }
+        }
+        values
+    }
+
+    // Newest first page of the values with the given ids, along with the number of ids.
+    // Like page, ids whose value has been removed are left out rather than filled in.
+    pub fn page_ids(&self, ids: &[K], page: u64, size: u16) -> (Vec<V>, u64) {
+        let mut values: Vec<V> = vec![];
+        let length: u64 = ids.len() as u64;
+        let (start, end) = page_bounds(length, page, size);
+        for offset in start..end {
+            if let Some(value) = self.values.get(ids[(length - 1 - offset) as usize]) {
+                values.push(value)
+            }
+        }
+        (values, length)
+    }
+}
+
+// Ids kept per key in chunks of up to ID_CHUNK_SIZE, in the order they were added, so that
+// adding an id or reading a page only decodes the chunks involved instead of the whole list
+#[derive(Debug)]
+#[ink::storage_item]
+pub struct ChunkedIds<K: Packed + Clone + core::fmt::Debug> {
+    pub(crate) chunks: Mapping<(K, u64), Vec<u64>>,
+    pub(crate) lengths: Mapping<K, u64>,
+}
+impl<K: Packed + Clone + core::fmt::Debug> Default for ChunkedIds<K> {
+    fn default() -> Self {
+        ChunkedIds {
+            chunks: Mapping::default(),
+            lengths: Mapping::default(),
+        }
+    }
+}
+impl<K: Packed + Clone + core::fmt::Debug> ChunkedIds<K> {
+    pub fn length(&self, key: K) -> u64 {
+        self.lengths.get(key).unwrap_or(0)
+    }
+
+    pub fn push(&mut self, key: K, id: u64) {
+        let length: u64 = self.length(key.clone());
+        let chunk: u64 = length / ID_CHUNK_SIZE;
+        let mut ids: Vec<u64> = self.chunks.get((key.clone(), chunk)).unwrap_or_default();
+        ids.push(id);
+        self.chunks.insert((key.clone(), chunk), &ids);
+        self.lengths.insert(key, &(length + 1));
+    }
+
+    // Later ids move down a place to fill the gap, so every chunk but the last stays full
+    pub fn remove(&mut self, key: K, id: u64) {
+        let length: u64 = self.length(key.clone());
+        let chunks_length: u64 = length.div_ceil(ID_CHUNK_SIZE);
+        for chunk in 0..chunks_length {
+            let mut ids: Vec<u64> = self.chunks.get((key.clone(), chunk)).unwrap_or_default();
+            if let Some(position) = ids.iter().position(|stored_id| *stored_id == id) {
+                ids.remove(position);
+                let mut current: u64 = chunk;
+                for next in chunk + 1..chunks_length {
+                    let mut next_ids: Vec<u64> =
+                        self.chunks.get((key.clone(), next)).unwrap_or_default();
+                    ids.push(next_ids.remove(0));
+                    self.chunks.insert((key.clone(), current), &ids);
+                    current = next;
+                    ids = next_ids;
+                }
+                if ids.is_empty() {
+                    self.chunks.remove((key.clone(), current));
+                } else {
+                    self.chunks.insert((key.clone(), current), &ids);
+                }
+                self.lengths.insert(key, &(length - 1));
+                return;
+            }
+        }
+    }
+
+    // Newest first page of the key's ids, along with the number of ids
+    pub fn page(&self, key: K, page: u64, size: u16) -> (Vec<u64>, u64) {
+        let mut ids: Vec<u64> = vec![];
+        let length: u64 = self.length(key.clone());
+        let (start, end) = page_bounds(length, page, size);
+        let mut loaded_chunk: Option<u64> = None;
+        let mut chunk_ids: Vec<u64> = vec![];
+        for offset in start..end {
+            let position: u64 = length - 1 - offset;
+            let chunk: u64 = position / ID_CHUNK_SIZE;
+            if loaded_chunk != Some(chunk) {
+                chunk_ids = self.chunks.get((key.clone(), chunk)).unwrap_or_default();
+                loaded_chunk = Some(chunk);
+            }
+            ids.push(chunk_ids[(position % ID_CHUNK_SIZE) as usize])
+        }
+        (ids, length)
+    }
+}
+
+// Ids kept per key like ChunkedIds, for indexes that ids keep coming and going from. Each id's
+// position is recorded so that removing it moves the key's last id into its place, touching at
+// most two chunks. Ids stay in the order they were added until one is removed.
+#[derive(Debug)]
+#[ink::storage_item]
+pub struct ChunkedIdSet<K: Packed + Clone + core::fmt::Debug> {
+    pub(crate) ids: ChunkedIds<K>,
+    pub(crate) positions: Mapping<(K, u64), u64>,
+}
+impl<K: Packed + Clone + core::fmt::Debug> Default for ChunkedIdSet<K> {
+    fn default() -> Self {
+        ChunkedIdSet {
+            ids: ChunkedIds::default(),
+            positions: Mapping::default(),
+        }
+    }
+}
+impl<K: Packed + Clone + core::fmt::Debug> ChunkedIdSet<K> {
+    pub fn length(&self, key: K) -> u64 {
+        self.ids.length(key)
+    }
+
+    pub fn contains(&self, key: K, id: u64) -> bool {
+        self.positions.contains((key, id))
+    }
+
+    // Ids already in the key's set are left where they are
+    pub fn insert(&mut self, key: K, id: u64) {
+        if self.contains(key.clone(), id) {
+            return;
+        }
+        let position: u64 = self.ids.length(key.clone());
+        self.positions.insert((key.clone(), id), &position);
+        self.ids.push(key, id);
+    }
+
+    pub fn remove(&mut self, key: K, id: u64) {
+        let position: u64 = match self.positions.get((key.clone(), id)) {
+            Some(position) => position,
+            None => return,
+        };
+        self.positions.remove((key.clone(), id));
+        let last_position: u64 = self.ids.length(key.clone()) - 1;
+        let last_chunk: u64 = last_position / ID_CHUNK_SIZE;
+        let mut last_ids: Vec<u64> = self
+            .ids
+            .chunks
+            .get((key.clone(), last_chunk))
+            .unwrap_or_default();
+        let last_id: u64 = last_ids.pop().unwrap();
+        if position != last_position {
+            let chunk: u64 = position / ID_CHUNK_SIZE;
+            let index: usize = (position % ID_CHUNK_SIZE) as usize;
+            if chunk == last_chunk {
+                last_ids[index] = last_id;
+            } else {
+                let mut ids: Vec<u64> = self
+                    .ids
+                    .chunks
+                    .get((key.clone(), chunk))
+                    .unwrap_or_default();
+                ids[index] = last_id;
+                self.ids.chunks.insert((key.clone(), chunk), &ids);
+            }
+            self.positions.insert((key.clone(), last_id), &position);
+        }
+        if last_ids.is_empty() {
+            self.ids.chunks.remove((key.clone(), last_chunk));
+        } else {
+            self.ids.chunks.insert((key.clone(), last_chunk), &last_ids);
+        }
+        self.ids.lengths.insert(key, &last_position);
+    }
+
+    // See ChunkedIds::page
+    pub fn page(&self, key: K, page: u64, size: u16) -> (Vec<u64>, u64) {
+        self.ids.page(key, page, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Balance;
+
+    #[ink::test]
+    fn test_chunked_ids() {
+        let mut ids: ChunkedIds<u8> = ChunkedIds::default();
+        // when a key has no ids
+        // * it returns an empty page
+        assert_eq!(ids.length(0), 0);
+        assert_eq!(ids.page(0, 0, 10), (vec![], 0));
+        // when ids run over more than one chunk
+        for id in 0..ID_CHUNK_SIZE * 2 + 1 {
+            ids.push(0, id);
+        }
+        ids.push(1, 7);
+        // * it keeps them in chunks per key
+        assert_eq!(ids.length(0), ID_CHUNK_SIZE * 2 + 1);
+        assert_eq!(ids.chunks.get((0, 2)), Some(vec![ID_CHUNK_SIZE * 2]));
+        assert_eq!(ids.length(1), 1);
+        // * it returns pages newest first across chunks
+        let last: u64 = ID_CHUNK_SIZE * 2;
+        assert_eq!(
+            ids.page(0, 0, 3),
+            (vec![last, last - 1, last - 2], last + 1)
+        );
+        assert_eq!(
+            ids.page(0, 0, u16::MAX).0,
+            (0..=last).rev().collect::<Vec<u64>>()
+        );
+        assert_eq!(ids.page(1, 0, 10), (vec![7], 1));
+        // when an id is removed
+        ids.remove(0, 5);
+        // * it moves the later ids down a place
+        assert_eq!(ids.length(0), last);
+        assert_eq!(ids.chunks.get((0, 0)).unwrap().len() as u64, ID_CHUNK_SIZE);
+        assert_eq!(
+            ids.chunks.get((0, 1)).unwrap()[ID_CHUNK_SIZE as usize - 1],
+            last
+        );
+        assert_eq!(ids.chunks.get((0, 2)), None);
+        assert_eq!(
+            ids.page(0, 0, u16::MAX).0,
+            (0..=last).rev().filter(|id| *id != 5).collect::<Vec<u64>>()
+        );
+        // when the id isn't there
+        ids.remove(0, 5);
+        // * it leaves the ids alone
+        assert_eq!(ids.length(0), last);
+        assert_eq!(ids.length(1), 1);
+    }
+
+    #[ink::test]
+    fn test_chunked_id_set() {
+        let mut ids: ChunkedIdSet<u8> = ChunkedIdSet::default();
+        let last: u64 = ID_CHUNK_SIZE * 2;
+        for id in 0..=last {
+            ids.insert(0, id);
+        }
+        ids.insert(1, 7);
+        // when an id is already in the set
+        ids.insert(0, 3);
+        // * it leaves the ids alone
+        assert_eq!(ids.length(0), last + 1);
+        assert_eq!(ids.page(0, 0, 1), (vec![last], last + 1));
+        // when an id is removed
+        ids.remove(0, 5);
+        // * it moves the key's last id into its place
+        assert!(!ids.contains(0, 5));
+        assert_eq!(ids.length(0), last);
+        assert_eq!(ids.ids.chunks.get((0, 0)).unwrap()[5], last);
+        assert_eq!(ids.positions.get((0, last)), Some(5));
+        assert_eq!(ids.ids.chunks.get((0, 2)), None);
+        // * it leaves other keys alone
+        assert_eq!(ids.page(1, 0, 10), (vec![7], 1));
+        // when the last id is removed
+        ids.remove(0, last - 1);
+        // * it drops it from the end
+        assert_eq!(ids.length(0), last - 1);
+        assert_eq!(ids.page(0, 0, 1), (vec![last - 2], last - 1));
+        // when the id isn't there
+        ids.remove(0, 5);
+        // * it leaves the ids alone
+        assert_eq!(ids.length(0), last - 1);
+        // when every id is removed
+        for id in 0..=last {
+            ids.remove(0, id);
+        }
+        // * it returns an empty page
+        assert_eq!(ids.page(0, 0, 10), (vec![], 0));
+        assert_eq!(ids.ids.chunks.get((0, 0)), None);
+    }
+
+    #[ink::test]
+    fn test_page_bounds() {
+        // when there are no items
+        // * it returns an empty page
+        assert_eq!(page_bounds(0, 0, 10), (0, 0));
+        assert_eq!(page_bounds(0, 3, 10), (0, 0));
+        // when the items are an exact multiple of the page size
+        // * it returns full pages
+        assert_eq!(page_bounds(20, 0, 10), (0, 10));
+        assert_eq!(page_bounds(20, 1, 10), (10, 20));
+        // * it returns an empty page after the last one
+        assert_eq!(page_bounds(20, 2, 10), (20, 20));
+        // when the last page is partially filled
+        // * it returns the remaining items
+        assert_eq!(page_bounds(25, 2, 10), (20, 25));
+        assert_eq!(page_bounds(25, 3, 10), (25, 25));
+        // when size is zero
+        // * it returns an empty page
+        assert_eq!(page_bounds(25, 1, 0), (0, 0));
+        // when page and size overflow
+        // * it returns an empty page
+        assert_eq!(page_bounds(25, u64::MAX, u16::MAX), (25, 25));
+        assert_eq!(
+            page_bounds(u64::MAX, u64::MAX, u16::MAX),
+            (u64::MAX, u64::MAX)
+        );
+    }
+
+    #[ink::test]
+    fn test_paginated_mapping() {
+        let mut values: PaginatedMapping<u32, Balance> = PaginatedMapping::default();
+        // when empty
+        // * it returns empty indexes
+        assert_eq!(values.length(), 0);
+        assert_eq!(values.get(0), None);
+        assert!(values.index(None, 10, Direction::Ascending).is_empty());
+        assert!(values.index(None, 10, Direction::Descending).is_empty());
+        assert!(values.page(0, 10).is_empty());
+        // when values are pushed
+        // * it stores them under sequential ids
+        for value in 0..5 {
+            assert!(values.push(&(value * 10)));
+        }
+        assert_eq!(values.length(), 5);
+        assert_eq!(values.get(3), Some(30));
+        assert!(values.contains(4));
+        assert!(!values.contains(5));
+        // when the next id is already taken
+        values.update(5, &99);
+        // * it does not push
+        assert!(!values.push(&50));
+        assert_eq!(values.length(), 5);
+        assert_eq!(values.get(5), Some(99));
+        // when pushing at an id
+        // * it stores the value there, skipping over any ids in between
+        assert!(values.push_at(7, &70));
+        assert_eq!(values.length(), 8);
+        assert_eq!(values.get(7), Some(70));
+        assert!(!values.contains(6));
+        // = when the id is before the end
+        // = * it does not push
+        assert!(!values.push_at(6, &60));
+        assert!(!values.contains(6));
+        values.remove(7);
+        values.length = 5;
+        // when indexing ascending
+        // * it returns up to limit values after the cursor
+        assert_eq!(values.index(None, 2, Direction::Ascending), vec![0, 10]);
+        assert_eq!(
+            values.index(Some(1), 10, Direction::Ascending),
+            vec![20, 30, 40]
+        );
+        assert!(values
+            .index(Some(u32::MAX), 10, Direction::Ascending)
+            .is_empty());
+        // when indexing descending
+        // * it returns up to limit values before the cursor
+        assert_eq!(values.index(None, 2, Direction::Descending), vec![40, 30]);
+        assert_eq!(
+            values.index(Some(2), 10, Direction::Descending),
+            vec![10, 0]
+        );
+        assert!(values.index(Some(0), 10, Direction::Descending).is_empty());
+        // when indexing with a filter
+        // * it skips values that don't match without counting them towards the limit
+        let mut scan: ScanPage<u32, Balance> =
+            values.index_where(None, 2, Direction::Ascending, |value| *value % 20 == 0);
+        assert_eq!(scan.items, vec![0, 20]);
+        // * it returns the cursor to carry on from
+        assert_eq!(scan.next_after_id, Some(2));
+        scan = values.index_where(Some(2), 2, Direction::Ascending, |value| *value % 20 == 0);
+        assert_eq!(scan.items, vec![40]);
+        // * it returns no cursor once it has reached the end
+        assert_eq!(scan.next_after_id, None);
+        scan = values.index_where(None, 2, Direction::Descending, |value| *value % 20 == 0);
+        assert_eq!(scan.items, vec![40, 20]);
+        assert_eq!(scan.next_after_id, Some(2));
+        // when paging
+        // * it returns the newest first
+        assert_eq!(values.page(0, 2), vec![40, 30]);
+        assert_eq!(values.page(2, 2), vec![0]);
+        assert!(values.page(3, 2).is_empty());
+
+        let mut values: PaginatedMapping<u64, Balance> = PaginatedMapping::default();
+        for value in 0..5 {
+            values.push(&value);
+        }
+        // when paging a subset of ids
+        // * it returns the newest first along with the number of ids
+        assert_eq!(values.page_ids(&[0, 2, 4], 0, 2), (vec![4, 2], 3));
+        assert_eq!(values.page_ids(&[0, 2, 4], 1, 2), (vec![0], 3));
+        assert_eq!(values.page_ids(&[], 0, 2), (vec![], 0));
+        // when ids have been removed
+        values.remove(0);
+        values.remove(2);
+        values.remove(4);
+        // * it skips the holes without changing the length
+        assert_eq!(values.length(), 5);
+        assert_eq!(values.get(2), None);
+        assert_eq!(values.index(None, 10, Direction::Ascending), vec![1, 3]);
+        assert_eq!(values.index(None, 1, Direction::Descending), vec![3]);
+        assert_eq!(values.index(Some(3), 10, Direction::Descending), vec![1]);
+        assert_eq!(values.page(0, 2), vec![3]);
+        assert_eq!(values.page(1, 2), vec![1]);
+        assert_eq!(values.page_ids(&[0, 2, 3, 4], 0, 10), (vec![3], 4));
+        // when every id has been removed
+        values.remove(1);
+        values.remove(3);
+        // * it returns empty indexes
+        assert!(values.index(None, 10, Direction::Ascending).is_empty());
+        assert!(values.page(0, 10).is_empty());
+        assert_eq!(values.page_ids(&[1, 3], 0, 10), (vec![], 2));
+
+        let mut values: PaginatedMapping<u32, Balance> = PaginatedMapping::default();
+        for value in 0..=MAX_SCAN_LENGTH {
+            values.push(&value.into());
+        }
+        // when a filter doesn't match within MAX_SCAN_LENGTH values
+        // * it stops with the cursor to carry on from
+        let mut scan: ScanPage<u32, Balance> =
+            values.index_where(None, 10, Direction::Ascending, |value| *value == 0);
+        assert_eq!(scan.items, vec![0]);
+        assert_eq!(scan.next_after_id, Some(MAX_SCAN_LENGTH as u32 - 1));
+        scan = values.index_where(scan.next_after_id, 10, Direction::Ascending, |_| false);
+        assert!(scan.items.is_empty());
+        assert_eq!(scan.next_after_id, None);
+        // * it doesn't limit unfiltered indexes
+        assert_eq!(
+            values.index(None, u16::MAX, Direction::Ascending).len() as u64,
+            MAX_SCAN_LENGTH + 1
+        );
+    }
+}
@@ -0,0 +1,84 @@
+// The order lifecycle as an ink trait, so that other contracts (aggregators, bots, DAOs) can
+// call the escrow in a typed way through EscrowRef e.g.
+// EscrowRef::create_order(&escrow, listing_id, amount)
+use crate::errors::EscrowError;
+use crate::listings::Listing;
+use crate::orders::Order;
+use crate::{AccountId, Balance};
+
+// Selector the escrow calls subscribers' on_order_event with
+pub const ON_ORDER_EVENT_SELECTOR: [u8; 4] =
+    ink::selector_bytes!("OrderEventSubscriber::on_order_event");
+// Selector the escrow calls managers' on_order_settled with
+pub const ON_ORDER_SETTLED_SELECTOR: [u8; 4] =
+    ink::selector_bytes!("TradeManager::on_order_settled");
+// Selectors the escrow calls the yield pool with
+pub const YIELD_POOL_BOND_SELECTOR: [u8; 4] = ink::selector_bytes!("YieldPool::bond");
+pub const YIELD_POOL_UNBOND_SELECTOR: [u8; 4] = ink::selector_bytes!("YieldPool::unbond");
+
+#[openbrush::wrapper]
+pub type EscrowRef = dyn Escrow;
+
+// Each message keeps the selector it had before moving into the trait, the first four bytes of
+// the blake2b hash of its bare name, so that callers built against earlier releases keep working.
+// Only the labels in the metadata change, to Escrow::<name>.
+#[openbrush::trait_definition]
+pub trait Escrow {
+    #[ink(message, selector = 0xE90B7FF6)]
+    fn cancel_order(&mut self, order_id: u64) -> Result<(), EscrowError>;
+
+    #[ink(message, selector = 0xE5E70B32)]
+    fn create_order(&mut self, listing_id: u32, amount: Balance) -> Result<(), EscrowError>;
+
+    #[ink(message, selector = 0xC4816693)]
+    fn dispute_order(&mut self, order_id: u64) -> Result<(), EscrowError>;
+
+    #[ink(message, selector = 0x0C11EE96)]
+    fn finalise_order(&mut self, order_id: u64) -> Result<(), EscrowError>;
+
+    #[ink(message, selector = 0x2E4EEF53)]
+    fn show_listing(&self, id: u32) -> Option<Listing>;
+
+    #[ink(message, selector = 0xD9FE025F)]
+    fn show_order(&self, id: u64) -> Option<Order>;
+
+    #[ink(message, selector = 0xECE43F06)]
+    fn update_order_payment_verification(
+        &mut self,
+        order_id: u64,
+        payment_verification: [u8; 32],
+        attestation: Option<[u8; 65]>,
+    ) -> Result<(), EscrowError>;
+}
+
+// Implemented by contracts that subscribe to order events with the escrow. Called whenever an
+// order is created or changes status, with the order's new status.
+#[openbrush::trait_definition]
+pub trait OrderEventSubscriber {
+    #[ink(message)]
+    fn on_order_event(&mut self, order_id: u64, status: u8);
+}
+
+// Implemented by contracts that open managed trades with create_managed_order. Called once the
+// order is settled, with its new status: finalised when it's released to the buyer, or
+// cancelled.
+#[openbrush::trait_definition]
+pub trait TradeManager {
+    #[ink(message)]
+    fn on_order_settled(&mut self, order_id: u64, status: u8);
+}
+
+// Implemented by the staking contract that opted-in listings' idle AZERO is bonded into, e.g. a
+// wrapper around a nomination pool that keeps enough liquidity to pay unbonds straight away
+// (see Yield pools in the README).
+// Rewards on the bonded amount accrue to the beneficiary, the listing's vendor, who claims them
+// from the pool directly.
+#[openbrush::trait_definition]
+pub trait YieldPool {
+    #[ink(message, payable)]
+    fn bond(&mut self, beneficiary: AccountId);
+
+    // Has to transfer the amount back to the caller before returning
+    #[ink(message)]
+    fn unbond(&mut self, beneficiary: AccountId, amount: Balance);
+}
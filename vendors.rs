@@ -0,0 +1,98 @@
+use crate::errors::EscrowError;
+use crate::pagination::get_stored_exactly_as;
+use crate::{AccountId, Key, Timestamp};
+use ink::prelude::string::String;
+use ink::storage::{traits::StorageKey, Mapping};
+
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct Vendor {
+    pub(crate) ethereum_address: String,
+    pub(crate) listings_length: u32,
+    // Set by moderators once the vendor has been vetted
+    pub(crate) verified: bool,
+    pub(crate) tier: u8,
+    pub(crate) completed_orders_length: u64,
+    // Last time the vendor managed a listing or order
+    pub(crate) last_active_at: Timestamp,
+}
+
+// Vendor as first stored under storage version 1, before listings were counted
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct LegacyVendorWithoutListings {
+    pub(crate) ethereum_address: String,
+}
+impl From<LegacyVendorWithoutListings> for Vendor {
+    // Listings created before they were counted don't count towards max_listings_per_vendor
+    fn from(legacy: LegacyVendorWithoutListings) -> Self {
+        Vendor::from(LegacyVendorWithoutProfile {
+            ethereum_address: legacy.ethereum_address,
+            listings_length: 0,
+        })
+    }
+}
+
+// Vendor as stored under storage version 1 once listings were counted, before vendor profiles
+#[derive(scale::Decode, scale::Encode)]
+#[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+#[derive(Debug, Clone)]
+pub struct LegacyVendorWithoutProfile {
+    pub(crate) ethereum_address: String,
+    pub(crate) listings_length: u32,
+}
+impl From<LegacyVendorWithoutProfile> for Vendor {
+    fn from(legacy: LegacyVendorWithoutProfile) -> Self {
+        Vendor {
+            ethereum_address: legacy.ethereum_address,
+            listings_length: legacy.listings_length,
+            verified: false,
+            tier: 0,
+            completed_orders_length: 0,
+            last_active_at: 0,
+        }
+    }
+}
+
+// Storage version 1 was kept while vendors gained fields, so it's told apart which of those
+// layouts the vendor was stored with. Returns None for vendors already stored as Vendor.
+pub fn get_v1(
+    vendors: &Mapping<AccountId, Vendor>,
+    account: AccountId,
+) -> Result<Option<Vendor>, EscrowError> {
+    let key: (Key, AccountId) = (vendors.key(), account);
+    if !vendors.contains(account) || get_stored_exactly_as::<_, Vendor>(&key).is_some() {
+        Ok(None)
+    } else if let Some(legacy) = get_stored_exactly_as::<_, LegacyVendorWithoutProfile>(&key) {
+        Ok(Some(legacy.into()))
+    } else if let Some(legacy) = get_stored_exactly_as::<_, LegacyVendorWithoutListings>(&key) {
+        Ok(Some(legacy.into()))
+    } else {
+        Err(EscrowError::StorageDecodeFailed)
+    }
+}
+
+// Everything a marketplace frontend shows about a vendor
+#[derive(Debug, Clone, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct VendorProfile {
+    pub(crate) account: AccountId,
+    pub(crate) ethereum_address: String,
+    pub(crate) verified: bool,
+    pub(crate) tier: u8,
+    pub(crate) listings_length: u32,
+    pub(crate) orders_length: u64,
+    pub(crate) completed_orders_length: u64,
+    pub(crate) last_active_at: Timestamp,
+}